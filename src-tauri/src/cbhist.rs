@@ -1,6 +1,7 @@
 //! In-memory copy-history panel (tray-triggered); internal codename cbhist.
 
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -43,8 +44,9 @@ pub struct CbhistItemDto {
 pub struct CbhistStore {
     deque: Mutex<VecDeque<HistoryEntry>>,
     last_raw_clipboard: Mutex<Option<String>>,
-    pub max_entries: usize,
-    pub disabled: bool,
+    max_entries: AtomicUsize,
+    disabled: AtomicBool,
+    watcher_started: AtomicBool,
     panel_dims: Mutex<(f64, f64)>,
 }
 
@@ -53,12 +55,41 @@ impl CbhistStore {
         Self {
             deque: Mutex::new(VecDeque::new()),
             last_raw_clipboard: Mutex::new(None),
-            max_entries,
-            disabled,
+            max_entries: AtomicUsize::new(max_entries),
+            disabled: AtomicBool::new(disabled),
+            watcher_started: AtomicBool::new(false),
             panel_dims: Mutex::new((panel_width, panel_height)),
         }
     }
 
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries.load(Ordering::Relaxed)
+    }
+
+    /// Applies reloaded `clipboard` settings. Existing history beyond the new limit is dropped.
+    pub fn apply_config(
+        &self,
+        max_entries: usize,
+        disabled: bool,
+        panel_width: f64,
+        panel_height: f64,
+    ) -> Result<(), String> {
+        self.max_entries.store(max_entries, Ordering::Relaxed);
+        self.disabled.store(disabled, Ordering::Relaxed);
+        {
+            let mut dq = self
+                .deque
+                .lock()
+                .map_err(|_| "cbhist lock failed".to_string())?;
+            dq.truncate(max_entries);
+        }
+        self.set_panel_size(panel_width, panel_height)
+    }
+
     pub fn panel_size(&self) -> Result<(f64, f64), String> {
         let g = self
             .panel_dims
@@ -206,7 +237,7 @@ fn panel_position_for_tray<R: Runtime>(
 }
 
 fn maybe_record_clipboard_update<R: Runtime>(handle: &AppHandle<R>, store: &CbhistStore) {
-    if store.disabled {
+    if store.is_disabled() {
         return;
     }
     let text = match handle.clipboard().read_text() {
@@ -236,12 +267,16 @@ fn maybe_record_clipboard_update<R: Runtime>(handle: &AppHandle<R>, store: &Cbhi
         text: normalized,
         truncated_from_clipboard: trunc,
     });
-    while dq.len() > store.max_entries {
+    while dq.len() > store.max_entries() {
         dq.pop_back();
     }
 }
 
+/// Starts polling the clipboard. Later calls are no-ops, so it is safe to call on config reload.
 pub fn spawn_cbhist_watcher<R: Runtime>(app: AppHandle<R>, store: Arc<CbhistStore>) {
+    if store.watcher_started.swap(true, Ordering::SeqCst) {
+        return;
+    }
     thread::spawn(move || {
         loop {
             thread::sleep(POLL_INTERVAL);
@@ -268,7 +303,7 @@ pub fn show_cbhist_panel<R: Runtime>(app: &AppHandle<R>) {
     let Some(store) = app.try_state::<Arc<CbhistStore>>() else {
         return;
     };
-    if store.disabled {
+    if store.is_disabled() {
         return;
     }
     let (lw, lh) = store.panel_size().unwrap_or((420.0, 480.0));
@@ -319,7 +354,7 @@ pub fn show_cbhist_panel<R: Runtime>(app: &AppHandle<R>) {
 pub fn cbhist_list(
     store: tauri::State<'_, Arc<CbhistStore>>,
 ) -> Result<Vec<CbhistItemDto>, String> {
-    if store.disabled {
+    if store.is_disabled() {
         return Ok(Vec::new());
     }
     let dq = store
@@ -339,7 +374,7 @@ pub fn cbhist_apply(
     store: tauri::State<'_, Arc<CbhistStore>>,
     index: usize,
 ) -> Result<(), String> {
-    if store.disabled {
+    if store.is_disabled() {
         return Err("clipboard history is disabled".to_string());
     }
     let entry = {
@@ -1,13 +1,17 @@
 use directories::BaseDirs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{fs, io::Write, path::PathBuf, sync::Arc};
 use tauri::State;
 
+use crate::config_schema::{
+    ConfigIssue, format_config_issues, has_config_errors, validate_config_source,
+};
 use crate::web_server::WebConfig;
 
 const IS_DEV: bool = tauri::is_dev();
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Clock {
     #[serde(default = "df_name")]
     pub name: String,
@@ -24,7 +28,7 @@ fn df_timezone() -> String {
     "UTC".to_string()
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(untagged)]
 pub enum InFontSize {
     Int(i32),
@@ -43,7 +47,7 @@ fn df_clocks() -> Vec<Clock> {
     cls
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ClipboardConfig {
     #[serde(default)]
@@ -82,7 +86,7 @@ impl Default for ClipboardConfig {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AppConfig {
     #[serde(default = "df_clocks")]
@@ -226,7 +230,30 @@ pub fn parse_config_json_to_value(config_json: &str) -> Result<serde_json::Value
         .map_err(|e| vec!["JSON config: ", &e.to_string()].join(""))
 }
 
-pub fn load_app_config_for_identifier(app_identifier: &str) -> Result<AppConfig, String> {
+/// Absolute path of the config file for `app_identifier`, whether or not it exists yet.
+pub fn config_file_path(app_identifier: &str) -> Result<PathBuf, String> {
+    let base_dir = BaseDirs::new().ok_or("Failed to get base dir")?;
+    Ok(base_dir
+        .config_dir()
+        .join(get_config_app_path(&app_identifier.to_string())))
+}
+
+/// Parses `config_json` and validates it against the config schema.
+/// Any error-level issue fails the parse; warnings (e.g. unknown keys) are returned with the config.
+pub fn parse_app_config(config_json: &str) -> Result<(AppConfig, Vec<ConfigIssue>), String> {
+    let config_value = parse_config_json_to_value(config_json)?;
+    let issues = validate_config_source(config_json, &config_value);
+    if has_config_errors(&issues) {
+        return Err(["JSON config:\n", &format_config_issues(&issues)].join(""));
+    }
+    let config = serde_json::from_value(config_value)
+        .map_err(|e| ["JSON config: ", &e.to_string()].join(""))?;
+    Ok((config, issues))
+}
+
+pub fn load_app_config_with_issues(
+    app_identifier: &str,
+) -> Result<(AppConfig, Vec<ConfigIssue>), String> {
     let base_dir = BaseDirs::new().ok_or("Failed to get base dir")?;
     let config_path = base_dir
         .config_dir()
@@ -237,8 +264,11 @@ pub fn load_app_config_for_identifier(app_identifier: &str) -> Result<AppConfig,
         ensure_config_file_exists(&config_path, &config_json)?;
     }
 
-    let config_value = parse_config_json_to_value(&config_json)?;
-    serde_json::from_value(config_value).map_err(|e| vec!["JSON config: ", &e.to_string()].join(""))
+    parse_app_config(&config_json)
+}
+
+pub fn load_app_config_for_identifier(app_identifier: &str) -> Result<AppConfig, String> {
+    load_app_config_with_issues(app_identifier).map(|(config, _)| config)
 }
 
 #[tauri::command]
@@ -625,6 +655,27 @@ mod tests {
         assert!(result.is_ok(), "Should parse trailing commas");
     }
 
+    #[test]
+    fn test_parse_app_config_keeps_warnings() {
+        let json = "{\n  \"font\": \"Arial\",\n  \"fontsize\": 16\n}";
+        let (config, issues) = parse_app_config(json).expect("warnings do not fail the parse");
+        assert_eq!(config.font, "Arial");
+        assert_eq!(
+            format_config_issues(&issues),
+            "3:3: warning: unknown key `fontsize` (did you mean `fontSize`?)"
+        );
+    }
+
+    #[test]
+    fn test_parse_app_config_rejects_schema_errors_with_location() {
+        let json = "{\n  \"forefront\": \"yes\"\n}";
+        let err = parse_app_config(json).expect_err("type error");
+        assert_eq!(
+            err,
+            "JSON config:\n2:16: error: `forefront`: expected boolean, found string"
+        );
+    }
+
     #[test]
     fn test_clock_deserialize_empty() {
         let json = "{}";
@@ -0,0 +1,789 @@
+//! JSON Schema of config.json, generated from the config structs, and validation that reports
+//! problems with their line and column in the JSON5 source.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use schemars::Schema;
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use serde_json::Value;
+
+use crate::config::AppConfig;
+
+/// Legacy key names accepted through `#[serde(alias)]`, which schemars does not emit.
+/// The first field is the `$defs` entry of the owning struct, `None` for the root object.
+const KEY_ALIASES: &[(Option<&str>, &str, &str)] = &[
+    (None, "fontSize", "size"),
+    (None, "fontColor", "color"),
+    (None, "formatDateTime", "format"),
+    (None, "localeDateTime", "locale"),
+    (None, "alwaysOnTop", "forefront"),
+    (Some("WebMarkdownConfig"), "allowRawHtml", "allowRawHTML"),
+    (
+        Some("WebMarkdownConfig"),
+        "openLinksInNewTab",
+        "openExternalLinkInNewTab",
+    ),
+];
+
+/// JSON Schema (draft 2020-12) for config.json. Objects are closed so that misspelled keys are
+/// reported instead of being silently ignored.
+pub fn app_config_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let close_objects = RecursiveTransform(|schema: &mut Schema| {
+            if schema.get("properties").is_some() && schema.get("additionalProperties").is_none() {
+                schema.insert("additionalProperties".to_string(), false.into());
+            }
+        });
+        let mut schema = SchemaSettings::draft2020_12()
+            .with_transform(close_objects)
+            .into_generator()
+            .into_root_schema_for::<AppConfig>()
+            .to_value();
+        for (owner, alias, canonical) in KEY_ALIASES {
+            let object = match owner {
+                Some(name) => schema.pointer_mut(&format!("/$defs/{}", name)),
+                None => Some(&mut schema),
+            };
+            let Some(properties) = object
+                .and_then(|o| o.get_mut("properties"))
+                .and_then(Value::as_object_mut)
+            else {
+                continue;
+            };
+            if let Some(property) = properties.get(*canonical).cloned() {
+                properties.insert(alias.to_string(), property);
+            }
+        }
+        schema
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueSeverity {
+    /// The config cannot be used as written.
+    Error,
+    /// The config loads, but part of it is ignored (e.g. an unknown key).
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub severity: IssueSeverity,
+    /// 1-based line in the config source.
+    pub line: usize,
+    /// 1-based column (in characters) in the config source.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            IssueSeverity::Error => "error",
+            IssueSeverity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )
+    }
+}
+
+pub fn format_config_issues(issues: &[ConfigIssue]) -> String {
+    issues
+        .iter()
+        .map(ConfigIssue::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn has_config_errors(issues: &[ConfigIssue]) -> bool {
+    issues.iter().any(|i| i.severity == IssueSeverity::Error)
+}
+
+/// Validates an already-parsed config against `app_config_schema` and checks time zone names.
+/// `source` is the JSON5 text `value` was parsed from; it is only used to locate issues.
+pub fn validate_config_source(source: &str, value: &Value) -> Vec<ConfigIssue> {
+    let mut validator = Validator {
+        root: app_config_schema(),
+        issues: Vec::new(),
+    };
+    validator.check(value, validator.root, &mut Vec::new());
+    check_time_zones(value, &mut validator.issues);
+
+    let locations = SourceLocations::scan(source);
+    let mut issues: Vec<ConfigIssue> = validator
+        .issues
+        .into_iter()
+        .map(|raw| {
+            let offset = locations.find(&raw.path, raw.at_key);
+            let (line, column) = line_column(source, offset);
+            ConfigIssue {
+                severity: raw.severity,
+                line,
+                column,
+                message: raw.message,
+            }
+        })
+        .collect();
+    issues.sort_by_key(|i| (i.line, i.column));
+    issues
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PathSeg {
+    Key(String),
+    Index(usize),
+}
+
+fn display_path(path: &[PathSeg]) -> String {
+    let mut out = String::new();
+    for seg in path {
+        match seg {
+            PathSeg::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(key);
+            }
+            PathSeg::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    if out.is_empty() {
+        "(root)".to_string()
+    } else {
+        out
+    }
+}
+
+struct RawIssue {
+    path: Vec<PathSeg>,
+    /// Point at the key rather than the value (unknown keys).
+    at_key: bool,
+    severity: IssueSeverity,
+    message: String,
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    issues: Vec<RawIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, path: &[PathSeg], message: String) {
+        self.issues.push(RawIssue {
+            path: path.to_vec(),
+            at_key: false,
+            severity: IssueSeverity::Error,
+            message,
+        });
+    }
+
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+            .and_then(|pointer| self.root.pointer(pointer))
+            .unwrap_or(schema)
+    }
+
+    fn check(&mut self, value: &Value, schema: &'a Value, path: &mut Vec<PathSeg>) {
+        let schema = self.resolve(schema);
+        if let Some(branches) = schema
+            .get("anyOf")
+            .or_else(|| schema.get("oneOf"))
+            .and_then(Value::as_array)
+        {
+            self.check_any_of(value, branches, path);
+            return;
+        }
+        if let Some(expected) = schema.get("type")
+            && !type_allows(expected, value)
+        {
+            self.error(
+                path,
+                format!(
+                    "`{}`: expected {}, found {}",
+                    display_path(path),
+                    self.describe_types(schema).join(" or "),
+                    json_type_name(value)
+                ),
+            );
+            return;
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+            && !allowed.contains(value)
+        {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            self.error(
+                path,
+                format!(
+                    "`{}`: expected one of {}, found {}",
+                    display_path(path),
+                    allowed.join(", "),
+                    value
+                ),
+            );
+            return;
+        }
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && n < min
+            {
+                self.error(
+                    path,
+                    format!(
+                        "`{}`: must be at least {}, found {}",
+                        display_path(path),
+                        schema["minimum"],
+                        value
+                    ),
+                );
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && n > max
+            {
+                self.error(
+                    path,
+                    format!(
+                        "`{}`: must be at most {}, found {}",
+                        display_path(path),
+                        schema["maximum"],
+                        value
+                    ),
+                );
+            }
+        }
+        match value {
+            Value::Object(map) => self.check_object(map, schema, path),
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        path.push(PathSeg::Index(i));
+                        self.check(item, item_schema, path);
+                        path.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_any_of(&mut self, value: &Value, branches: &'a [Value], path: &mut Vec<PathSeg>) {
+        let mut matching_type = Vec::new();
+        for branch in branches {
+            let mut sub = Validator {
+                root: self.root,
+                issues: Vec::new(),
+            };
+            sub.check(value, branch, path);
+            if sub.issues.is_empty() {
+                return;
+            }
+            if self.type_matches(value, branch) {
+                matching_type.push(sub.issues);
+            }
+        }
+        // Exactly one branch has the right shape: its issues are more useful than a type error.
+        if matching_type.len() == 1 {
+            self.issues.extend(matching_type.remove(0));
+            return;
+        }
+        let mut expected = Vec::new();
+        for branch in branches {
+            for name in self.describe_types(branch) {
+                if !expected.contains(&name) {
+                    expected.push(name);
+                }
+            }
+        }
+        self.error(
+            path,
+            format!(
+                "`{}`: expected {}, found {}",
+                display_path(path),
+                expected.join(" or "),
+                json_type_name(value)
+            ),
+        );
+    }
+
+    fn check_object(
+        &mut self,
+        map: &serde_json::Map<String, Value>,
+        schema: &'a Value,
+        path: &mut Vec<PathSeg>,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    path.push(PathSeg::Key(key.to_string()));
+                    let message = format!("missing required key `{}`", display_path(path));
+                    path.pop();
+                    self.error(path, message);
+                }
+            }
+        }
+        let additional = schema.get("additionalProperties");
+        let closed = additional == Some(&Value::Bool(false));
+        // Maps (e.g. `web.mounts`) describe their values with an `additionalProperties` schema.
+        let additional_schema = additional.filter(|a| a.is_object());
+        for (key, child) in map {
+            path.push(PathSeg::Key(key.clone()));
+            match properties.and_then(|p| p.get(key)).or(additional_schema) {
+                Some(child_schema) => self.check(child, child_schema, path),
+                None if closed => {
+                    let mut message = format!("unknown key `{}`", display_path(path));
+                    let candidates = properties.into_iter().flat_map(|p| p.keys());
+                    if let Some(suggestion) = closest_match(key, candidates.map(String::as_str)) {
+                        message.push_str(&format!(" (did you mean `{}`?)", suggestion));
+                    }
+                    self.issues.push(RawIssue {
+                        path: path.clone(),
+                        at_key: true,
+                        severity: IssueSeverity::Warning,
+                        message,
+                    });
+                }
+                None => {}
+            }
+            path.pop();
+        }
+    }
+
+    /// Whether `value` has one of the JSON types `schema` allows, ignoring nested constraints.
+    fn type_matches(&self, value: &Value, schema: &'a Value) -> bool {
+        let schema = self.resolve(schema);
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+            return branches.iter().any(|b| self.type_matches(value, b));
+        }
+        schema
+            .get("type")
+            .is_none_or(|expected| type_allows(expected, value))
+    }
+
+    fn describe_types(&self, schema: &'a Value) -> Vec<String> {
+        let schema = self.resolve(schema);
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+            return branches
+                .iter()
+                .flat_map(|b| self.describe_types(b))
+                .collect();
+        }
+        match schema.get("type") {
+            Some(Value::String(t)) => vec![t.clone()],
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn type_allows(expected: &Value, value: &Value) -> bool {
+    let allows = |t: &str| match t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    };
+    match expected {
+        Value::String(t) => allows(t),
+        Value::Array(types) => types.iter().filter_map(Value::as_str).any(allows),
+        _ => true,
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Time zone names are checked against the IANA database; the schema only says "string".
+fn check_time_zones(value: &Value, issues: &mut Vec<RawIssue>) {
+    let mut check = |path: Vec<PathSeg>, name: &str| {
+        if chrono_tz::Tz::from_str_insensitive(name).is_ok() {
+            return;
+        }
+        let mut message = format!("`{}`: unknown time zone `{}`", display_path(&path), name);
+        let names = chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name());
+        if let Some(suggestion) = closest_match(name, names) {
+            message.push_str(&format!(" (did you mean `{}`?)", suggestion));
+        }
+        issues.push(RawIssue {
+            path,
+            at_key: false,
+            severity: IssueSeverity::Error,
+            message,
+        });
+    };
+    if let Some(clocks) = value.get("clocks").and_then(Value::as_array) {
+        for (i, clock) in clocks.iter().enumerate() {
+            if let Some(tz) = clock.get("timezone").and_then(Value::as_str) {
+                let path = vec![
+                    PathSeg::Key("clocks".to_string()),
+                    PathSeg::Index(i),
+                    PathSeg::Key("timezone".to_string()),
+                ];
+                check(path, tz);
+            }
+        }
+    }
+    // An empty `convtz` means "local time zone".
+    if let Some(tz) = value.get("convtz").and_then(Value::as_str)
+        && !tz.is_empty()
+    {
+        check(vec![PathSeg::Key("convtz".to_string())], tz);
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn closest_match<'c>(input: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    let limit = (input.chars().count() / 4).max(1);
+    candidates
+        .map(|c| (edit_distance(input, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Byte offsets of every key and value in a JSON5 document, indexed by path.
+/// The source has already been parsed successfully, so the scanner can be lenient.
+#[derive(Default)]
+struct SourceLocations {
+    keys: HashMap<Vec<PathSeg>, usize>,
+    values: HashMap<Vec<PathSeg>, usize>,
+}
+
+impl SourceLocations {
+    fn scan(source: &str) -> Self {
+        let mut scanner = Scanner {
+            src: source,
+            pos: 0,
+            locations: SourceLocations::default(),
+        };
+        scanner.value(&mut Vec::new());
+        scanner.locations
+    }
+
+    /// Falls back to the nearest located ancestor, then the start of the file.
+    fn find(&self, path: &[PathSeg], at_key: bool) -> usize {
+        if at_key && let Some(offset) = self.keys.get(path) {
+            return *offset;
+        }
+        (0..=path.len())
+            .rev()
+            .find_map(|len| self.values.get(&path[..len]))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+struct Scanner<'s> {
+    src: &'s str,
+    pos: usize,
+    locations: SourceLocations,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                self.pos += comment.find("*/").map(|i| i + 4).unwrap_or(rest.len());
+            } else if self
+                .peek()
+                .is_some_and(|c| c.is_whitespace() || c == '\u{feff}')
+            {
+                self.bump();
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn value(&mut self, path: &mut Vec<PathSeg>) {
+        self.skip_trivia();
+        self.locations.values.insert(path.clone(), self.pos);
+        match self.peek() {
+            Some('{') => self.object(path),
+            Some('[') => self.array(path),
+            Some(q @ ('"' | '\'')) => {
+                self.string(q);
+            }
+            Some(_) => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, ',' | '}' | ']' | '/') && !c.is_whitespace())
+                {
+                    self.bump();
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn object(&mut self, path: &mut Vec<PathSeg>) {
+        self.bump();
+        loop {
+            self.skip_trivia();
+            let key_start = self.pos;
+            let key = match self.peek() {
+                None => return,
+                Some('}') => {
+                    self.bump();
+                    return;
+                }
+                Some(',') => {
+                    self.bump();
+                    continue;
+                }
+                Some(q @ ('"' | '\'')) => self.string(q),
+                Some(_) => {
+                    let mut key = String::new();
+                    while let Some(c) = self.peek() {
+                        if c == ':' || c == '/' || c.is_whitespace() {
+                            break;
+                        }
+                        key.push(c);
+                        self.bump();
+                    }
+                    key
+                }
+            };
+            self.skip_trivia();
+            if self.peek() != Some(':') {
+                // Not reachable for input json5 accepted; avoid looping forever regardless.
+                self.bump();
+                continue;
+            }
+            self.bump();
+            path.push(PathSeg::Key(key));
+            self.locations.keys.insert(path.clone(), key_start);
+            self.value(path);
+            path.pop();
+        }
+    }
+
+    fn array(&mut self, path: &mut Vec<PathSeg>) {
+        self.bump();
+        let mut index = 0;
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return,
+                Some(']') => {
+                    self.bump();
+                    return;
+                }
+                Some(',') => {
+                    self.bump();
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    path.push(PathSeg::Index(index));
+                    self.value(path);
+                    path.pop();
+                    index += 1;
+                    if self.pos == start {
+                        self.bump();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a quoted string starting at the opening quote and returns its unescaped content.
+    fn string(&mut self, quote: char) -> String {
+        self.bump();
+        let mut out = String::new();
+        while let Some(c) = self.bump() {
+            if c == quote {
+                break;
+            }
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match self.bump() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('b') => out.push('\u{8}'),
+                Some('f') => out.push('\u{c}'),
+                Some('0') => out.push('\0'),
+                Some('u') => {
+                    let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        out.push(c);
+                    }
+                }
+                Some('\n') | Some('\r') => {}
+                Some(other) => out.push(other),
+                None => break,
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config_json_to_value;
+
+    fn validate(source: &str) -> Vec<ConfigIssue> {
+        let value = parse_config_json_to_value(source).expect("parse");
+        validate_config_source(source, &value)
+    }
+
+    #[test]
+    fn test_app_config_schema_lists_fields_and_aliases() {
+        let schema = app_config_schema();
+        let properties = schema["properties"].as_object().expect("properties");
+        assert!(properties.contains_key("clocks"));
+        assert!(properties.contains_key("web"));
+        assert!(properties.contains_key("fontSize"), "serde alias");
+        assert_eq!(schema["additionalProperties"], Value::Bool(false));
+        let markdown = &schema["$defs"]["WebMarkdownConfig"]["properties"];
+        assert!(markdown.get("allowRawHtml").is_some(), "nested serde alias");
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_config() {
+        let source = r#"{
+  // comment
+  clocks: [{ name: 'Tokyo', timezone: 'Asia/Tokyo' }],
+  fontSize: 14,
+  alwaysOnTop: true,
+  convtz: "",
+  web: { root: "/tmp", port: 3030, content: { markdown: { allowRawHtml: true } } },
+}"#;
+        assert_eq!(validate(source), Vec::new());
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_key_with_location() {
+        let source =
+            "{\n  \"web\": {\n    \"root\": \"/tmp\",\n    \"openBrowserOnStart\": true\n  }\n}";
+        let issues = validate(source);
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].severity, IssueSeverity::Warning);
+        assert_eq!((issues[0].line, issues[0].column), (4, 5));
+        assert_eq!(
+            issues[0].message,
+            "unknown key `web.openBrowserOnStart` (did you mean `openBrowserAtStart`?)"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_type_errors_and_ranges() {
+        let source = "{\n  size: [14],\n  web: { root: '/tmp', port: 'auto', workers: 100 },\n  clocks: [{ name: 1 }],\n}";
+        let issues = validate(source);
+        let rendered: Vec<String> = issues.iter().map(ConfigIssue::to_string).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "2:9: error: `size`: expected integer or string, found array",
+                "3:30: error: `web.port`: expected integer, found string",
+                "3:47: error: `web.workers`: must be at most 64, found 100",
+                "4:20: error: `clocks[0].name`: expected string, found integer",
+            ]
+        );
+        assert!(has_config_errors(&issues));
+    }
+
+    #[test]
+    fn test_validate_reports_missing_required_key() {
+        let issues = validate("{ web: { port: 3030 } }");
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].message, "missing required key `web.root`");
+        assert_eq!((issues[0].line, issues[0].column), (1, 8));
+    }
+
+    #[test]
+    fn test_validate_checks_map_values() {
+        let source = "{\n  web: {\n    root: '/tmp',\n    mounts: { '/docs': '/tmp', '/logs': { path: '/tmp', defaultMode: 'html' } },\n  },\n}";
+        let issues = validate(source);
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(
+            issues[0].message,
+            "`web.mounts./logs.defaultMode`: expected one of \"content\", \"source\", \"raw\", found \"html\""
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_bad_time_zone() {
+        let source = "{\n  \"clocks\": [\n    { \"name\": \"UTC\", \"timezone\": \"UTC\" },\n    { \"name\": \"JST\", \"timezone\": \"Asia/Tokio\" }\n  ],\n  \"convtz\": \"asia/tokyo\"\n}";
+        let issues = validate(source);
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(
+            issues[0].to_string(),
+            "4:34: error: `clocks[1].timezone`: unknown time zone `Asia/Tokio` (did you mean `Asia/Tokyo`?)"
+        );
+    }
+
+    #[test]
+    fn test_source_locations_handle_comments_and_escapes() {
+        let source = "{\n  /* \"a\": { */ 'k\\'ey': [1, // x\n 2],\n}";
+        let locations = SourceLocations::scan(source);
+        let key = vec![PathSeg::Key("k'ey".to_string())];
+        assert_eq!(line_column(source, locations.find(&key, true)), (2, 16));
+        let second = vec![PathSeg::Key("k'ey".to_string()), PathSeg::Index(1)];
+        assert_eq!(line_column(source, locations.find(&second, false)), (3, 2));
+    }
+}
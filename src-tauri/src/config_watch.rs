@@ -0,0 +1,221 @@
+//! Watches config.json and applies edits at runtime, without restarting mclocks.
+
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::cbhist::{self, CbhistStore};
+use crate::config::{
+    AppConfig, config_file_path, load_app_config_for_identifier, load_app_config_with_issues,
+};
+use crate::config_schema::format_config_issues;
+use crate::web::markdown_live_reload::{
+    markdown_live_reload_ws_port, start_markdown_live_reload_server,
+};
+use crate::web_server::{WebServers, reload_web_server_config};
+use crate::{WINDOW_NAME, WebMainPortStore, clamp_cbhist_max_entries, clamp_cbhist_window_px};
+
+/// Event sent to the clock webview when a reload changed settings it renders from.
+pub const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+/// Editors save in several steps (truncate, write, rename); wait until events settle.
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+fn event_targets_file(event: &Event, file_name: &std::ffi::OsStr) -> bool {
+    event
+        .paths
+        .iter()
+        .any(|p| p.file_name().is_some_and(|name| name == file_name))
+}
+
+fn wait_until_settled(rx: &Receiver<notify::Result<Event>>) {
+    while rx.recv_timeout(SETTLE_DELAY).is_ok() {}
+}
+
+fn show_config_error(app: &AppHandle, message: &str) {
+    eprintln!("[mclocks] config reload failed: {}", message);
+    app.dialog()
+        .message(format!(
+            "config.json was not applied; the previous settings stay in effect.\n\n{}",
+            message
+        ))
+        .kind(MessageDialogKind::Error)
+        .title("Config Error")
+        .show(|_| {});
+}
+
+/// Re-resolves the web config around the ports our listeners already hold and applies it.
+/// Returns the main port now serving requests.
+fn reload_web_servers(identifier: &String, web_servers: &Mutex<WebServers>) -> Result<u16, String> {
+    let mut servers = web_servers.lock().map_err(|e| e.to_string())?;
+    let running_ws_port = markdown_live_reload_ws_port();
+    let mut held_ports = servers.held_ports();
+    held_ports.extend(running_ws_port);
+    let mut config = reload_web_server_config(identifier, &held_ports)?;
+    // The live-reload WebSocket server is started once and keeps its port for the app lifetime.
+    config.markdown_live_reload_ws_port = match running_ws_port {
+        Some(port) => Some(port),
+        None => config
+            .markdown_live_reload_ws_port
+            .filter(|port| start_markdown_live_reload_server(*port)),
+    };
+    servers.apply(&config)?;
+    Ok(config.port)
+}
+
+fn apply_clipboard_config(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
+    let Some(store) = app.try_state::<Arc<CbhistStore>>() else {
+        return Ok(());
+    };
+    let clipboard = &config.clipboard;
+    store.apply_config(
+        clamp_cbhist_max_entries(clipboard.max_clip_number),
+        clipboard.disabled,
+        clamp_cbhist_window_px(clipboard.window_width),
+        clamp_cbhist_window_px(clipboard.window_height),
+    )?;
+    if !clipboard.disabled {
+        cbhist::spawn_cbhist_watcher(app.clone(), store.inner().clone());
+    }
+    Ok(())
+}
+
+/// Config minus the sections applied in place, i.e. what the clock window has to reload for.
+fn without_runtime_sections(config: &serde_json::Value) -> serde_json::Value {
+    let mut config = config.clone();
+    if let Some(obj) = config.as_object_mut() {
+        obj.remove("web");
+        obj.remove("clipboard");
+    }
+    config
+}
+
+struct ReloadState {
+    /// Last applied config, serialized with defaults filled in so formatting-only edits compare equal.
+    applied: serde_json::Value,
+}
+
+impl ReloadState {
+    fn reload(&mut self, app: &AppHandle, identifier: &String, web_servers: &Mutex<WebServers>) {
+        let (config, issues) = match load_app_config_with_issues(identifier) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                show_config_error(app, &e);
+                return;
+            }
+        };
+        let mut next = match serde_json::to_value(&config) {
+            Ok(value) => value,
+            Err(e) => {
+                show_config_error(app, &e.to_string());
+                return;
+            }
+        };
+        if next == self.applied {
+            return;
+        }
+        if !issues.is_empty() {
+            app.dialog()
+                .message(format_config_issues(&issues))
+                .kind(MessageDialogKind::Warning)
+                .title("Config Warning")
+                .show(|_| {});
+        }
+
+        if next.get("web") != self.applied.get("web") {
+            match reload_web_servers(identifier, web_servers) {
+                Ok(port) => {
+                    let store = app.state::<WebMainPortStore>();
+                    if let Ok(mut guard) = store.0.lock() {
+                        *guard = Some(port);
+                    }
+                }
+                Err(e) => {
+                    show_config_error(app, &e);
+                    // Keep comparing against the web section that is actually running.
+                    if let (Some(obj), Some(previous)) =
+                        (next.as_object_mut(), self.applied.get("web"))
+                    {
+                        obj.insert("web".to_string(), previous.clone());
+                    }
+                }
+            }
+        }
+        if next.get("clipboard") != self.applied.get("clipboard") {
+            if let Err(e) = apply_clipboard_config(app, &config) {
+                show_config_error(app, &e);
+            }
+        }
+
+        if without_runtime_sections(&next) != without_runtime_sections(&self.applied) {
+            if let Err(e) = app.emit_to(WINDOW_NAME, CONFIG_CHANGED_EVENT, &config) {
+                eprintln!("[mclocks] failed to emit {}: {}", CONFIG_CHANGED_EVENT, e);
+            }
+        }
+        self.applied = next;
+    }
+}
+
+/// Watches the config file of `identifier` and applies changes as they are saved.
+/// The parent directory is watched so editors that save via rename are picked up too.
+pub fn spawn_config_watcher(
+    app: AppHandle,
+    identifier: String,
+    web_servers: Arc<Mutex<WebServers>>,
+) {
+    thread::spawn(move || {
+        let config_path = match config_file_path(&identifier) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("[mclocks] config watch: {}", e);
+                return;
+            }
+        };
+        let (Some(watch_dir), Some(file_name)) = (config_path.parent(), config_path.file_name())
+        else {
+            eprintln!(
+                "[mclocks] config watch: invalid path {}",
+                config_path.display()
+            );
+            return;
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = match RecommendedWatcher::new(tx, Config::default()) {
+            Ok(w) => w,
+            Err(e) => {
+                eprintln!("[mclocks] config watch: watcher create failed: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(Path::new(watch_dir), RecursiveMode::NonRecursive) {
+            eprintln!(
+                "[mclocks] config watch: failed to watch {}: {}",
+                watch_dir.display(),
+                e
+            );
+            return;
+        }
+
+        let applied = load_app_config_for_identifier(&identifier)
+            .ok()
+            .and_then(|config| serde_json::to_value(&config).ok())
+            .unwrap_or(serde_json::Value::Null);
+        let mut state = ReloadState { applied };
+        while let Ok(res) = rx.recv() {
+            match res {
+                Ok(event) if event_targets_file(&event, file_name) => {
+                    wait_until_settled(&rx);
+                    state.reload(&app, &identifier, &web_servers);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[mclocks] config watch: notify error: {}", e),
+            }
+        }
+    });
+}
@@ -2,6 +2,8 @@ mod about_env;
 mod calendar;
 mod cbhist;
 mod config;
+mod config_schema;
+mod config_watch;
 mod sticky;
 mod todo;
 mod tray;
@@ -14,16 +16,15 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use tauri::Manager;
 use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_notification::NotificationExt;
 use util::open_text_in_editor;
 use web::dd_publish::{
     build_temp_file_url, build_temp_share_url, clear_temp_shares, register_temp_file,
     register_temp_root,
 };
 use web::markdown_live_reload::start_markdown_live_reload_server;
-use web_server::{
-    WebServerListenKind, default_web_server_config, load_web_config, open_url_in_browser,
-    start_web_server,
-};
+use web::request_bin::set_bin_notifier;
+use web_server::{WebServers, default_web_server_config, load_web_config, open_url_in_browser};
 
 /// Global lock to serialize all saveWindowState calls across windows.
 /// Prevents potential deadlocks in the window-state plugin when multiple
@@ -111,10 +112,23 @@ pub fn run() {
 
     let context: tauri::Context<tauri::Wry> = tauri::generate_context!();
     let identifier: String = context.config().identifier.clone();
-    let app_config = config::load_app_config_for_identifier(&identifier).unwrap_or_else(|e| {
-        eprintln!("[mclocks] config load failed (using defaults): {}", e);
-        serde_json::from_str("{}").expect("default AppConfig")
-    });
+    // (message, is_error) shown in a dialog once the app is up.
+    let mut config_problem: Option<(String, bool)> = None;
+    let app_config = match config::load_app_config_with_issues(&identifier) {
+        Ok((config, issues)) => {
+            if !issues.is_empty() {
+                let message = config_schema::format_config_issues(&issues);
+                eprintln!("[mclocks] config warnings:\n{}", message);
+                config_problem = Some((message, false));
+            }
+            config
+        }
+        Err(e) => {
+            eprintln!("[mclocks] config load failed (using defaults): {}", e);
+            config_problem = Some((format!("{}\n\nDefault settings are used.", e), true));
+            serde_json::from_str("{}").expect("default AppConfig")
+        }
+    };
     let clipboard_disabled = app_config.clipboard.disabled;
     let cbhist_max_entries = clamp_cbhist_max_entries(app_config.clipboard.max_clip_number);
     let cbhist_panel_w = clamp_cbhist_window_px(app_config.clipboard.window_width);
@@ -146,66 +160,37 @@ pub fn run() {
         }
     };
 
-    let port_to_open = web_config_for_startup
-        .as_ref()
-        .map(|config| {
-            let mut markdown_live_reload_ws_port = config.markdown_live_reload_ws_port;
-            if let Some(ws_port) = markdown_live_reload_ws_port {
-                if !start_markdown_live_reload_server(ws_port) {
-                    markdown_live_reload_ws_port = None;
-                }
-            }
-            start_web_server(
-                config.root.clone(),
-                config.port,
-                config.dump,
-                config.slow,
-                config.status,
-                config.allow_html_in_md,
-                config.markdown_open_external_link_in_new_tab,
-                config.markdown_highlight.clone(),
-                config.editor_repos_dir.clone(),
-                config.editor_include_host,
-                config.editor_command.clone(),
-                config.editor_args.clone(),
-                markdown_live_reload_ws_port,
-                Some(config.local_preview_api_enabled.clone()),
-                WebServerListenKind::Main,
-            );
-            if let Some(assets_server) = &config.assets_server {
-                start_web_server(
-                    assets_server.root.clone(),
-                    assets_server.port,
-                    false,
-                    false,
-                    false,
-                    false,
-                    true,
-                    None,
-                    None,
-                    false,
-                    "code".to_string(),
-                    vec!["-g".to_string(), "{file}:{line}".to_string()],
-                    None,
-                    None,
-                    WebServerListenKind::Assets,
-                );
+    let web_servers = Arc::new(Mutex::new(WebServers::default()));
+    let mut web_main_port_at_startup: Option<u16> = None;
+    let mut port_to_open: Option<u16> = None;
+    if let Some(mut config) = web_config_for_startup {
+        if let Some(ws_port) = config.markdown_live_reload_ws_port {
+            if !start_markdown_live_reload_server(ws_port) {
+                config.markdown_live_reload_ws_port = None;
             }
-            if config.open_browser_at_start {
-                Some(config.port)
-            } else {
-                None
+        }
+        let applied = web_servers
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|mut servers| servers.apply(&config));
+        match applied {
+            Ok(()) => {
+                web_main_port_at_startup = Some(config.port);
+                if config.open_browser_at_start {
+                    port_to_open = Some(config.port);
+                }
             }
-        })
-        .flatten();
+            Err(e) => web_error = Some(e),
+        }
+    }
 
     let error_msg = web_error.clone();
-    let web_main_port_at_startup = web_config_for_startup.as_ref().map(|c| c.port);
     let clipboard_disabled_setup = clipboard_disabled;
     let cbhist_max_entries_setup = cbhist_max_entries;
     let cbhist_panel_w_setup = cbhist_panel_w;
     let cbhist_panel_h_setup = cbhist_panel_h;
     let clipboard_history_enabled_setup = clipboard_history_enabled;
+    let identifier_setup = identifier.clone();
     tbr = tbr.setup(move |app| {
         #[cfg(target_os = "macos")]
         app.set_activation_policy(tauri::ActivationPolicy::Accessory);
@@ -235,10 +220,21 @@ pub fn run() {
             )?;
         }
 
+        let notify_handle = app.handle().clone();
+        set_bin_notifier(move |request| {
+            let _ = notify_handle
+                .notification()
+                .builder()
+                .title(format!("Request bin: {}", request.bin))
+                .body(format!("{} {}", request.method, request.path))
+                .show();
+        });
+
         let store = app.state::<WebMainPortStore>();
         if let Ok(mut guard) = store.0.lock() {
             *guard = web_main_port_at_startup;
         }
+        config_watch::spawn_config_watcher(app.handle().clone(), identifier_setup, web_servers);
         if IS_DEV {
             let _window = app.get_webview_window(WINDOW_NAME).unwrap();
             #[cfg(debug_assertions)]
@@ -247,6 +243,19 @@ pub fn run() {
             }
         }
 
+        if let Some((message, is_error)) = config_problem {
+            let (kind, title) = if is_error {
+                (MessageDialogKind::Error, "Config Error")
+            } else {
+                (MessageDialogKind::Warning, "Config Warning")
+            };
+            app.dialog()
+                .message(&message)
+                .kind(kind)
+                .title(title)
+                .blocking_show();
+        }
+
         if let Some(err) = error_msg {
             app.dialog()
                 .message(&err)
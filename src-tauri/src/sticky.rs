@@ -7,7 +7,7 @@ use std::thread;
 use base64::{Engine as _, engine::general_purpose};
 use directories::BaseDirs;
 use tauri::webview::Url;
-use tauri::{AppHandle, State, WebviewUrl, WebviewWindowBuilder};
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder};
 use uuid::Uuid;
 
 use serde::{Deserialize, Serialize};
@@ -532,6 +532,10 @@ pub fn restore_stickies(
     }
 
     for (label, sticky_data) in notes {
+        // The clock window reloads after config edits; keep stickies that are already open.
+        if app.get_webview_window(&label).is_some() {
+            continue;
+        }
         // Use per-sticky forefront if persisted, otherwise fall back to main clock config
         let forefront = sticky_data.forefront.unwrap_or(cfg.forefront);
         spawn_sticky_window(app.clone(), label, forefront);
@@ -0,0 +1,408 @@
+//! Host/Origin allowlisting and the optional access token for the main web server.
+//!
+//! Loopback clients on a loopback `Host` are never asked for anything. Pages served from other
+//! names (DNS rebinding) are refused outright, cross-origin pages can't reach the endpoints that
+//! act on the machine, and when the server is reachable from other hosts (see
+//! `MCLOCKS_MAIN_HTTP_BIND_ALL`) those clients need `web.security.token` for them.
+use std::net::IpAddr;
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+use super::dd_publish::{TEMP_DIR_PREFIX, TEMP_FILE_PREFIX};
+
+/// Cookie holding the access token once a browser has presented it via [`TOKEN_QUERY_PARAM`].
+pub const TOKEN_COOKIE: &str = "mclocks_token";
+/// Query parameter that turns a token into a cookie, for opening URLs in a browser.
+pub const TOKEN_QUERY_PARAM: &str = "mclocks_token";
+
+/// Set from `web.security`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessGuardSettings {
+    /// Extra `Host` names (`example.test`, `*.example.test`, `host:port`); `*` allows any.
+    pub allowed_hosts: Vec<String>,
+    /// Extra origins (`http://192.168.0.10:5173`) allowed to call the sensitive endpoints.
+    pub allowed_origins: Vec<String>,
+    /// Required from non-loopback clients when set.
+    pub token: Option<String>,
+}
+
+/// The request fields the checks look at.
+#[derive(Debug, Default)]
+pub struct AccessRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    pub host: Option<&'a str>,
+    pub origin: Option<&'a str>,
+    pub authorization: Option<&'a str>,
+    pub cookie: Option<&'a str>,
+    pub is_preflight: bool,
+    pub client_is_loopback: bool,
+}
+
+/// What to do with a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccessDecision {
+    Allow,
+    Deny(&'static str),
+    /// A valid token arrived in the query: store it in a cookie and reload without it.
+    SetTokenCookie {
+        location: String,
+    },
+}
+
+fn split_host_port(host: &str) -> (&str, Option<&str>) {
+    if let Some(rest) = host.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((ip, port)) => (ip, port.strip_prefix(':')),
+            None => (host, None),
+        };
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.contains(':') => (name, Some(port)),
+        _ => (host, None),
+    }
+}
+
+fn is_loopback_name(name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    name == "localhost"
+        || name.ends_with(".localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+fn host_pattern_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    let (name, _) = split_host_port(&host);
+    if pattern == "*" || pattern == host || pattern == name {
+        return true;
+    }
+    pattern
+        .strip_prefix("*.")
+        .is_some_and(|suffix| name.ends_with(&format!(".{}", suffix)))
+}
+
+/// A `Host` a DNS-rebinding page can't produce: loopback names, IP literals, and allowed hosts.
+fn is_allowed_host(settings: &AccessGuardSettings, host: &str) -> bool {
+    let (name, _) = split_host_port(host);
+    is_loopback_name(name)
+        || name.parse::<IpAddr>().is_ok()
+        || settings
+            .allowed_hosts
+            .iter()
+            .any(|pattern| host_pattern_matches(pattern, host))
+}
+
+/// Loopback pages, the server's own origin, and the allowed origins and hosts.
+fn is_allowed_origin(settings: &AccessGuardSettings, origin: &str, host: Option<&str>) -> bool {
+    let origin = origin.trim().trim_end_matches('/');
+    let Some((_, origin_host)) = origin.split_once("://") else {
+        return false;
+    };
+    let (origin_name, _) = split_host_port(origin_host);
+    is_loopback_name(origin_name)
+        || host.is_some_and(|host| host.eq_ignore_ascii_case(origin_host))
+        || settings
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+        || settings
+            .allowed_hosts
+            .iter()
+            .any(|pattern| pattern != "*" && host_pattern_matches(pattern, origin_host))
+}
+
+/// Endpoints that act on the machine, expose files outside `web.root`, or show captured
+/// requests (the `/_mclocks/` pages, whose captures include credential headers).
+fn is_sensitive_request(method: &str, path: &str) -> bool {
+    let safe_method = matches!(method, "GET" | "HEAD" | "OPTIONS");
+    let path_lower = path.to_ascii_lowercase();
+    !safe_method
+        || path_lower == "/editor"
+        || path_lower.starts_with("/editor/")
+        || path_lower.trim_end_matches('/') == "/preview"
+        || path_lower.trim_end_matches('/') == "/_mclocks"
+        || path_lower.starts_with("/_mclocks/")
+        || path.starts_with(TEMP_DIR_PREFIX)
+        || path.starts_with(TEMP_FILE_PREFIX)
+}
+
+/// Compares without an early exit so response timing doesn't leak the token.
+fn token_eq(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    expected.len() == given.len()
+        && expected
+            .iter()
+            .zip(given)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn cookie_value<'a>(cookie_header: &'a str, name: &str) -> Option<&'a str> {
+    cookie_header
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.trim())
+}
+
+/// Splits the token parameter out of `url`, returning it and the URL without it.
+fn take_query_token(url: &str) -> Option<(String, String)> {
+    let (path, query) = url.split_once('?')?;
+    let mut token = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some((TOKEN_QUERY_PARAM, value)) => {
+                token = urlencoding::decode(value).ok().map(|v| v.into_owned());
+            }
+            _ => rest.push(pair),
+        }
+    }
+    let location = if rest.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, rest.join("&"))
+    };
+    Some((token?, location))
+}
+
+/// `url` without the token query parameter, so logs and captures never hold the token.
+pub fn strip_query_token(url: &str) -> String {
+    let Some((path, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some(TOKEN_QUERY_PARAM))
+        .collect();
+    if rest.len() == query.split('&').count() {
+        url.to_string()
+    } else if rest.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, rest.join("&"))
+    }
+}
+
+pub fn check_access(settings: &AccessGuardSettings, req: &AccessRequest) -> AccessDecision {
+    if let Some(host) = req.host
+        && !is_allowed_host(settings, host)
+    {
+        return AccessDecision::Deny("Host not allowed");
+    }
+    let path = req.url.split('?').next().unwrap_or("/");
+    let sensitive = is_sensitive_request(req.method, path);
+    if sensitive
+        && let Some(origin) = req.origin
+        && !is_allowed_origin(settings, origin, req.host)
+    {
+        return AccessDecision::Deny("Origin not allowed");
+    }
+    // Preflights carry no credentials; the actual request is checked when it arrives.
+    if req.client_is_loopback || req.is_preflight {
+        return AccessDecision::Allow;
+    }
+    let Some(expected) = settings.token.as_deref() else {
+        return if sensitive {
+            AccessDecision::Deny("Access from other hosts requires web.security.token")
+        } else {
+            AccessDecision::Allow
+        };
+    };
+    let bearer = req
+        .authorization
+        .and_then(|v| v.trim().strip_prefix("Bearer "))
+        .map(str::trim);
+    let cookie = req.cookie.and_then(|c| cookie_value(c, TOKEN_COOKIE));
+    if bearer
+        .or(cookie)
+        .is_some_and(|given| token_eq(expected, given))
+    {
+        return AccessDecision::Allow;
+    }
+    match take_query_token(req.url) {
+        Some((given, location)) if token_eq(expected, &given) => {
+            AccessDecision::SetTokenCookie { location }
+        }
+        _ => AccessDecision::Deny("Missing or invalid access token"),
+    }
+}
+
+fn request_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Returns the response for a request that must not reach the routes, or `None` to go on.
+pub fn guard_request(request: &Request, settings: &AccessGuardSettings) -> Option<ResponseBox> {
+    let method = request.method().to_string();
+    let access = AccessRequest {
+        method: &method,
+        url: request.url(),
+        host: request_header(request, "Host"),
+        origin: request_header(request, "Origin"),
+        authorization: request_header(request, "Authorization"),
+        cookie: request_header(request, "Cookie"),
+        is_preflight: *request.method() == Method::Options
+            && request_header(request, "Access-Control-Request-Method").is_some(),
+        client_is_loopback: request
+            .remote_addr()
+            .is_none_or(|addr| addr.ip().is_loopback()),
+    };
+    match check_access(settings, &access) {
+        AccessDecision::Allow => None,
+        AccessDecision::Deny(reason) => {
+            Some(create_error_response(StatusCode(403), reason).boxed())
+        }
+        AccessDecision::SetTokenCookie { location } => {
+            let token = settings.token.as_deref().unwrap_or_default();
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Strict",
+                TOKEN_COOKIE, token
+            );
+            let mut response = Response::empty(StatusCode(302)).boxed();
+            for (name, value) in [("Location", location.as_str()), ("Set-Cookie", &cookie)] {
+                if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                    response.add_header(header);
+                }
+            }
+            Some(response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(method: &'a str, url: &'a str, host: &'a str) -> AccessRequest<'a> {
+        AccessRequest {
+            method,
+            url,
+            host: Some(host),
+            client_is_loopback: true,
+            ..AccessRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_check_access_host_and_origin() {
+        let settings = AccessGuardSettings {
+            allowed_hosts: vec!["*.dev.test".to_string()],
+            allowed_origins: vec!["http://192.168.0.10:5173".to_string()],
+            token: None,
+        };
+        for host in [
+            "localhost:3030",
+            "127.0.0.1:3030",
+            "[::1]:3030",
+            "app.localhost",
+            "172.20.0.1:3030",
+            "api.dev.test:3030",
+        ] {
+            assert_eq!(
+                check_access(&settings, &request("GET", "/", host)),
+                AccessDecision::Allow,
+                "{}",
+                host
+            );
+        }
+        assert_eq!(
+            check_access(&settings, &request("GET", "/", "evil.example:3030")),
+            AccessDecision::Deny("Host not allowed")
+        );
+
+        let post = |origin: &'static str| AccessRequest {
+            origin: Some(origin),
+            ..request("POST", "/preview", "localhost:3030")
+        };
+        assert_eq!(
+            check_access(&settings, &post("http://localhost:5173")),
+            AccessDecision::Allow
+        );
+        assert_eq!(
+            check_access(&settings, &post("http://192.168.0.10:5173/")),
+            AccessDecision::Allow
+        );
+        assert_eq!(
+            check_access(&settings, &post("http://1.2.3.4")),
+            AccessDecision::Deny("Origin not allowed")
+        );
+        // Plain reads are left to the browser's CORS rules.
+        let read = AccessRequest {
+            origin: Some("http://1.2.3.4"),
+            ..request("GET", "/data.json", "localhost:3030")
+        };
+        assert_eq!(check_access(&settings, &read), AccessDecision::Allow);
+    }
+
+    #[test]
+    fn test_check_access_token_for_remote_clients() {
+        let mut settings = AccessGuardSettings::default();
+        let remote = |method: &'static str, url: &'static str| AccessRequest {
+            client_is_loopback: false,
+            ..request(method, url, "172.20.0.1:3030")
+        };
+        assert_eq!(
+            check_access(&settings, &remote("GET", "/notes.md")),
+            AccessDecision::Allow
+        );
+        assert!(matches!(
+            check_access(&settings, &remote("POST", "/preview")),
+            AccessDecision::Deny(_)
+        ));
+        assert!(matches!(
+            check_access(&settings, &remote("GET", "/_mclocks/proxy/exchanges.har")),
+            AccessDecision::Deny(_)
+        ));
+
+        settings.token = Some("s3cret".to_string());
+        assert!(matches!(
+            check_access(&settings, &remote("GET", "/notes.md")),
+            AccessDecision::Deny(_)
+        ));
+        let bearer = AccessRequest {
+            authorization: Some("Bearer s3cret"),
+            ..remote("POST", "/preview")
+        };
+        assert_eq!(check_access(&settings, &bearer), AccessDecision::Allow);
+        let cookie = AccessRequest {
+            cookie: Some("theme=dark; mclocks_token=s3cret"),
+            ..remote("GET", "/notes.md")
+        };
+        assert_eq!(check_access(&settings, &cookie), AccessDecision::Allow);
+        let wrong = AccessRequest {
+            authorization: Some("Bearer s3cre"),
+            ..remote("GET", "/notes.md")
+        };
+        assert!(matches!(
+            check_access(&settings, &wrong),
+            AccessDecision::Deny(_)
+        ));
+        assert_eq!(
+            check_access(
+                &settings,
+                &remote("GET", "/notes.md?mode=raw&mclocks_token=s3cret")
+            ),
+            AccessDecision::SetTokenCookie {
+                location: "/notes.md?mode=raw".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_strip_query_token() {
+        assert_eq!(
+            strip_query_token("/a?mode=raw&mclocks_token=s3cret"),
+            "/a?mode=raw"
+        );
+        assert_eq!(strip_query_token("/a?mclocks_token=s3cret"), "/a");
+        assert_eq!(strip_query_token("/a?mclocks_token"), "/a");
+        assert_eq!(strip_query_token("/a?x=1&&y=2"), "/a?x=1&&y=2");
+        assert_eq!(strip_query_token("/a"), "/a");
+    }
+}
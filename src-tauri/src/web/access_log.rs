@@ -0,0 +1,413 @@
+//! Per-request access log: JSON lines in a rotating file plus the `/_mclocks/access-log` viewer.
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+const VIEWER_PATH: &str = "/_mclocks/access-log";
+const ENTRIES_PATH: &str = "/_mclocks/access-log/entries";
+/// Entries kept in memory for the viewer.
+const RECENT_CAPACITY: usize = 1000;
+/// How much of an existing log is read back at startup to fill the viewer.
+const SEED_TAIL_BYTES: u64 = 256 * 1024;
+
+/// Where and how much to log. Set from `web.accessLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogSettings {
+    pub path: PathBuf,
+    /// The file is rotated once it grows past this size.
+    pub max_bytes: u64,
+    /// Rotated files kept next to the active one (`access.log.1` is the newest).
+    pub max_files: usize,
+}
+
+/// One line of the access log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    pub time: String,
+    pub listener: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// `None` for streamed (chunked or compressed) bodies.
+    pub bytes: Option<u64>,
+    pub duration_ms: u64,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Default)]
+struct AccessLogStore {
+    settings: Option<AccessLogSettings>,
+    file: Option<File>,
+    file_len: u64,
+    /// Recent entries with their sequence number, oldest first.
+    recent: VecDeque<(u64, AccessLogEntry)>,
+    next_seq: u64,
+}
+
+fn store() -> &'static Mutex<AccessLogStore> {
+    static STORE: OnceLock<Mutex<AccessLogStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(AccessLogStore::default()))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn read_tail_entries(path: &Path) -> Vec<AccessLogEntry> {
+    let Ok(mut file) = File::open(path) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let start = len.saturating_sub(SEED_TAIL_BYTES);
+    let mut tail = String::new();
+    if file.seek(SeekFrom::Start(start)).is_err() || file.read_to_string(&mut tail).is_err() {
+        return Vec::new();
+    }
+    // The first line is usually cut in half when reading from an offset.
+    let skip_first = usize::from(start > 0);
+    tail.lines()
+        .skip(skip_first)
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+impl AccessLogStore {
+    fn push_recent(&mut self, entry: AccessLogEntry) {
+        if self.recent.len() >= RECENT_CAPACITY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((self.next_seq, entry));
+        self.next_seq += 1;
+    }
+
+    fn open_file(&mut self) -> Result<(), String> {
+        let Some(settings) = &self.settings else {
+            return Ok(());
+        };
+        if let Some(parent) = settings.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&settings.path)
+            .map_err(|e| format!("{}: {}", settings.path.display(), e))?;
+        self.file_len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Shifts `access.log.N` up by one, dropping the oldest, and starts a new file.
+    fn rotate(&mut self) -> Result<(), String> {
+        let Some(settings) = self.settings.clone() else {
+            return Ok(());
+        };
+        self.file = None;
+        if settings.max_files == 0 {
+            let _ = fs::remove_file(&settings.path);
+        } else {
+            let _ = fs::remove_file(rotated_path(&settings.path, settings.max_files));
+            for index in (1..settings.max_files).rev() {
+                let from = rotated_path(&settings.path, index);
+                if from.exists() {
+                    let _ = fs::rename(&from, rotated_path(&settings.path, index + 1));
+                }
+            }
+            let _ = fs::rename(&settings.path, rotated_path(&settings.path, 1));
+        }
+        self.open_file()
+    }
+
+    fn write(&mut self, entry: &AccessLogEntry) -> Result<(), String> {
+        let Some(max_bytes) = self.settings.as_ref().map(|s| s.max_bytes) else {
+            return Ok(());
+        };
+        if self.file.is_none() {
+            self.open_file()?;
+        }
+        if self.file_len >= max_bytes {
+            self.rotate()?;
+        }
+        let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let file = self.file.as_mut().ok_or("access log is not open")?;
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        self.file_len += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Starts, re-targets or (with `None`) stops the access log.
+pub fn configure_access_log(settings: Option<AccessLogSettings>) {
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    if store.settings == settings {
+        return;
+    }
+    let path_changed =
+        store.settings.as_ref().map(|s| &s.path) != settings.as_ref().map(|s| &s.path);
+    store.settings = settings;
+    store.file = None;
+    if path_changed {
+        store.recent.clear();
+        if let Some(path) = store.settings.as_ref().map(|s| s.path.clone()) {
+            for entry in read_tail_entries(&path) {
+                store.push_recent(entry);
+            }
+        }
+    }
+}
+
+/// Appends `entry` to the log file and the viewer's buffer. Does nothing while logging is off.
+pub fn record_access(entry: AccessLogEntry) {
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    if store.settings.is_none() {
+        return;
+    }
+    if let Err(e) = store.write(&entry) {
+        eprintln!("access log: {}", e);
+    }
+    store.push_recent(entry);
+}
+
+/// Requests for the viewer itself, which are answered by the main server and never logged.
+pub fn is_access_log_request(url: &str) -> bool {
+    let path = url.split('?').next().unwrap_or(url);
+    path == VIEWER_PATH || path == ENTRIES_PATH
+}
+
+/// `status` accepts an exact code (`404`) or a class (`4xx`).
+fn status_matches(filter: &str, status: u16) -> bool {
+    let filter = filter.trim().to_ascii_lowercase();
+    if filter.is_empty() {
+        return true;
+    }
+    if let Some(class) = filter.strip_suffix("xx") {
+        return class.parse::<u16>().is_ok_and(|c| status / 100 == c);
+    }
+    filter.parse::<u16>().is_ok_and(|code| code == status)
+}
+
+/// Entries after `since` that match the filters, and the sequence number to poll from next.
+pub fn recent_access_entries(
+    since: u64,
+    status_filter: &str,
+    path_filter: &str,
+) -> (Vec<AccessLogEntry>, u64) {
+    let Ok(store) = store().lock() else {
+        return (Vec::new(), since);
+    };
+    let entries = store
+        .recent
+        .iter()
+        .filter(|(seq, entry)| {
+            *seq >= since
+                && status_matches(status_filter, entry.status)
+                && entry.path.contains(path_filter)
+        })
+        .map(|(_, entry)| entry.clone())
+        .collect();
+    (entries, store.next_seq)
+}
+
+fn query_param(url: &str, key: &str) -> String {
+    let query = url.split_once('?').map(|(_, q)| q).unwrap_or("");
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, v)| decode(&v.replace('+', " ")).ok().map(|v| v.into_owned()))
+        .unwrap_or_default()
+}
+
+/// Serves the viewer page and the JSON feed it polls.
+pub fn handle_access_log_request(request: &Request) -> ResponseBox {
+    let url = request.url();
+    if url.split('?').next() == Some(ENTRIES_PATH) {
+        let since = query_param(url, "since").parse().unwrap_or(0);
+        let (entries, next) = recent_access_entries(
+            since,
+            &query_param(url, "status"),
+            &query_param(url, "path"),
+        );
+        let body = serde_json::json!({ "next": next, "entries": entries }).to_string();
+        let mut response = Response::from_string(body).boxed();
+        if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+            response.add_header(header);
+        }
+        return response;
+    }
+    let mut response = Response::from_string(ACCESS_LOG_VIEWER_HTML)
+        .with_status_code(StatusCode(200))
+        .boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+const ACCESS_LOG_VIEWER_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Access Log - mclocks</title>
+<style>
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 13px; margin: 0; color: #222; background: #fafafa; }
+header { position: sticky; top: 0; display: flex; gap: 12px; align-items: center; padding: 8px 12px; background: #fff; border-bottom: 1px solid #ddd; }
+header h1 { font-size: 14px; margin: 0 12px 0 0; }
+input { font: inherit; padding: 2px 6px; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 3px 8px; border-bottom: 1px solid #eee; white-space: nowrap; }
+td.path { white-space: normal; word-break: break-all; }
+tr.s2 td.status { color: #1a7f37; }
+tr.s3 td.status { color: #0969da; }
+tr.s4 td.status { color: #9a6700; }
+tr.s5 td.status { color: #cf222e; font-weight: bold; }
+@media (prefers-color-scheme: dark) {
+  body { color: #ddd; background: #1e1e1e; }
+  header { background: #252526; border-color: #333; }
+  th, td { border-color: #333; }
+}
+</style>
+</head>
+<body>
+<header>
+<h1>Access Log</h1>
+<label>status <input id="status" size="5" placeholder="4xx"></label>
+<label>path <input id="path" size="30" placeholder="/dump"></label>
+<label><input id="follow" type="checkbox" checked> follow</label>
+<span id="count"></span>
+</header>
+<table>
+<thead><tr><th>time</th><th>listener</th><th>method</th><th>status</th><th>bytes</th><th>ms</th><th>path</th><th>user agent</th></tr></thead>
+<tbody id="rows"></tbody>
+</table>
+<script>
+const MAX_ROWS = 1000;
+const rows = document.getElementById("rows");
+const statusInput = document.getElementById("status");
+const pathInput = document.getElementById("path");
+const follow = document.getElementById("follow");
+const count = document.getElementById("count");
+let next = 0;
+let generation = 0;
+
+const cell = (text, className) => {
+  const td = document.createElement("td");
+  td.textContent = text;
+  if (className) td.className = className;
+  return td;
+};
+
+const render = (entry) => {
+  const tr = document.createElement("tr");
+  tr.className = "s" + String(entry.status).charAt(0);
+  tr.append(
+    cell(entry.time),
+    cell(entry.listener),
+    cell(entry.method),
+    cell(entry.status, "status"),
+    cell(entry.bytes == null ? "-" : entry.bytes),
+    cell(entry.durationMs),
+    cell(entry.path, "path"),
+    cell(entry.userAgent || "-"),
+  );
+  return tr;
+};
+
+const poll = async () => {
+  const current = generation;
+  const params = new URLSearchParams({ since: next, status: statusInput.value, path: pathInput.value });
+  try {
+    const res = await fetch("/_mclocks/access-log/entries?" + params);
+    const data = await res.json();
+    if (current !== generation) return;
+    next = data.next;
+    for (const entry of data.entries) rows.append(render(entry));
+    while (rows.children.length > MAX_ROWS) rows.firstElementChild.remove();
+    count.textContent = rows.children.length + " entries";
+    if (follow.checked && data.entries.length) window.scrollTo(0, document.body.scrollHeight);
+  } catch (_) {
+    // The server may be restarting; keep polling.
+  }
+};
+
+const reset = () => {
+  generation += 1;
+  next = 0;
+  rows.replaceChildren();
+  poll();
+};
+statusInput.addEventListener("input", reset);
+pathInput.addEventListener("input", reset);
+poll();
+setInterval(poll, 1000);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_matches() {
+        assert!(status_matches("", 200));
+        assert!(status_matches("404", 404));
+        assert!(!status_matches("404", 400));
+        assert!(status_matches("4xx", 418));
+        assert!(status_matches("5XX", 503));
+        assert!(!status_matches("4xx", 500));
+        assert!(!status_matches("abc", 200));
+    }
+
+    #[test]
+    fn test_rotate_keeps_max_files() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("logs").join("access.log");
+        let mut store = AccessLogStore {
+            settings: Some(AccessLogSettings {
+                path: path.clone(),
+                max_bytes: 1,
+                max_files: 2,
+            }),
+            ..AccessLogStore::default()
+        };
+        for status in [200, 201, 202, 203] {
+            let entry = AccessLogEntry {
+                time: "2026-01-01T00:00:00.000+00:00".to_string(),
+                listener: "Main Server".to_string(),
+                method: "GET".to_string(),
+                path: "/".to_string(),
+                status,
+                bytes: Some(0),
+                duration_ms: 0,
+                user_agent: None,
+            };
+            store.write(&entry).expect("write entry");
+        }
+        let status_in = |p: &Path| {
+            read_tail_entries(p)
+                .iter()
+                .map(|e| e.status)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(status_in(&path), [203]);
+        assert_eq!(status_in(&rotated_path(&path, 1)), [202]);
+        assert_eq!(status_in(&rotated_path(&path, 2)), [201]);
+        assert!(!rotated_path(&path, 3).exists());
+    }
+}
@@ -0,0 +1,201 @@
+//! `Accept-Encoding` negotiation and on-the-fly gzip/brotli compression of text-like responses.
+use flate2::Compression;
+use flate2::read::GzEncoder;
+use std::io::Read;
+use tiny_http::{Header, Request, Response, ResponseBox};
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+
+/// Response compression settings for one listener. Set from `web.compression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionSettings {
+    pub enabled: bool,
+    /// Responses with a body smaller than this are sent uncompressed.
+    pub min_bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+}
+
+impl ContentEncoding {
+    fn token(self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// Picks the encoding the client prefers by q-value; brotli wins ties.
+pub fn negotiate_encoding(accept_encoding: &str) -> Option<ContentEncoding> {
+    let mut brotli_q: Option<f32> = None;
+    let mut gzip_q: Option<f32> = None;
+    let mut wildcard_q: Option<f32> = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|v| v.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match coding.as_str() {
+            "br" => brotli_q = Some(q),
+            "gzip" | "x-gzip" => gzip_q = Some(q),
+            "*" => wildcard_q = Some(q),
+            _ => {}
+        }
+    }
+    let brotli_q = brotli_q.or(wildcard_q).unwrap_or(0.0);
+    let gzip_q = gzip_q.or(wildcard_q).unwrap_or(0.0);
+    if brotli_q <= 0.0 && gzip_q <= 0.0 {
+        return None;
+    }
+    if brotli_q >= gzip_q {
+        Some(ContentEncoding::Brotli)
+    } else {
+        Some(ContentEncoding::Gzip)
+    }
+}
+
+pub fn is_compressible_content_type(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/javascript"
+                | "application/xml"
+                | "application/yaml"
+                | "application/toml"
+                | "image/svg+xml"
+        )
+}
+
+fn find_header<'a>(headers: &'a [Header], name: &'static str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Compresses `response` for `request` when the client accepts it and the body is text-like and
+/// large enough. Partial, empty and already-encoded responses pass through untouched.
+pub fn compress_response(
+    request: &Request,
+    response: ResponseBox,
+    settings: CompressionSettings,
+) -> ResponseBox {
+    if !settings.enabled {
+        return response;
+    }
+    let status = response.status_code();
+    let headers = response.headers();
+    let is_compressible = find_header(headers, "Content-Type")
+        .is_some_and(is_compressible_content_type)
+        && find_header(headers, "Content-Encoding").is_none()
+        && find_header(headers, "Content-Range").is_none()
+        && !matches!(status.0, 204 | 206 | 304);
+    if !is_compressible {
+        return response;
+    }
+    let mut response = response;
+    if let Ok(vary) = Header::from_bytes(&b"Vary"[..], &b"Accept-Encoding"[..]) {
+        response.add_header(vary);
+    }
+    let large_enough = response
+        .data_length()
+        .is_some_and(|len| len >= settings.min_bytes.max(1));
+    let encoding = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Accept-Encoding"))
+        .and_then(|h| negotiate_encoding(h.value.as_str()));
+    let Some(encoding) = encoding.filter(|_| large_enough) else {
+        return response;
+    };
+
+    let mut headers: Vec<Header> = Vec::new();
+    for header in response.headers() {
+        // Ranges would address the unencoded file, not the encoded body sent here.
+        if header.field.equiv("Content-Length") || header.field.equiv("Accept-Ranges") {
+            continue;
+        }
+        if header.field.equiv("ETag") {
+            // The encoded body differs byte-wise, so the validator can only be weak.
+            let value = header.value.as_str();
+            let weak = if value.starts_with("W/") {
+                value.to_string()
+            } else {
+                format!("W/{}", value)
+            };
+            if let Ok(etag) = Header::from_bytes(&b"ETag"[..], weak.as_bytes()) {
+                headers.push(etag);
+            }
+            continue;
+        }
+        headers.push(header.clone());
+    }
+    if let Ok(header) = Header::from_bytes(&b"Content-Encoding"[..], encoding.token().as_bytes()) {
+        headers.push(header);
+    }
+    let reader = response.into_reader();
+    let body: Box<dyn Read + Send> = match encoding {
+        ContentEncoding::Brotli => Box::new(brotli::CompressorReader::new(
+            reader,
+            BROTLI_BUFFER_SIZE,
+            BROTLI_QUALITY,
+            BROTLI_LG_WINDOW_SIZE,
+        )),
+        ContentEncoding::Gzip => Box::new(GzEncoder::new(reader, Compression::default())),
+    };
+    Response::new(status, headers, body, None, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_encoding() {
+        assert_eq!(
+            negotiate_encoding("gzip, deflate, br"),
+            Some(ContentEncoding::Brotli)
+        );
+        assert_eq!(negotiate_encoding("gzip"), Some(ContentEncoding::Gzip));
+        assert_eq!(
+            negotiate_encoding("br;q=0.5, gzip;q=0.8"),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            negotiate_encoding("br;q=0, gzip"),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(negotiate_encoding("*"), Some(ContentEncoding::Brotli));
+        assert_eq!(negotiate_encoding("gzip;q=0, *;q=0"), None);
+        assert_eq!(negotiate_encoding("identity"), None);
+        assert_eq!(negotiate_encoding(""), None);
+    }
+
+    #[test]
+    fn test_is_compressible_content_type() {
+        assert!(is_compressible_content_type("text/html; charset=utf-8"));
+        assert!(is_compressible_content_type("application/javascript"));
+        assert!(is_compressible_content_type("application/ld+json"));
+        assert!(is_compressible_content_type("image/svg+xml"));
+        assert!(!is_compressible_content_type("image/png"));
+        assert!(!is_compressible_content_type("video/mp4"));
+        assert!(!is_compressible_content_type("application/octet-stream"));
+    }
+}
@@ -0,0 +1,327 @@
+//! `web.auth` test endpoints that ask for and check credentials, for exercising client auth
+//! middleware: `/basic-auth/{user}/{pass}`, `/bearer` and `/digest-auth/{qop}/{user}/{pass}`.
+//! They don't protect anything; see `access_guard` for that.
+use base64::{Engine as _, engine::general_purpose};
+use md5::Md5;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+
+const REALM: &str = "mclocks";
+/// Largest body hashed for `qop=auth-int`.
+const MAX_AUTH_INT_BODY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Set from `web.auth`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthEndpointSettings {
+    /// The token `/bearer` accepts; any token is accepted and echoed when `None`.
+    pub bearer_token: Option<String>,
+}
+
+pub fn is_auth_request(path: &str) -> bool {
+    path == "/bearer" || path.starts_with("/basic-auth/") || path.starts_with("/digest-auth/")
+}
+
+fn request_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+/// The credentials after `scheme` in `Authorization`, e.g. the token of `Bearer abc`.
+fn authorization_credentials<'a>(request: &'a Request, scheme: &str) -> Option<&'a str> {
+    let value = request_header(request, "Authorization")?.trim();
+    let (given, credentials) = value.split_once(' ')?;
+    given
+        .eq_ignore_ascii_case(scheme)
+        .then(|| credentials.trim())
+}
+
+fn json_response(value: serde_json::Value) -> ResponseBox {
+    let body = serde_json::to_string_pretty(&value).unwrap_or_default() + "\n";
+    let mut response = Response::from_string(body).boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+fn unauthorized(challenge: &str) -> ResponseBox {
+    let mut response = create_error_response(StatusCode(401), "Unauthorized").boxed();
+    if let Ok(header) = Header::from_bytes(&b"WWW-Authenticate"[..], challenge.as_bytes()) {
+        response.add_header(header);
+    }
+    response
+}
+
+fn path_segments(path: &str, prefix: &str) -> Vec<String> {
+    path[prefix.len()..]
+        .split('/')
+        .map(|segment| {
+            urlencoding::decode(segment)
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| segment.to_string())
+        })
+        .collect()
+}
+
+fn handle_basic_auth(request: &Request, path: &str) -> ResponseBox {
+    let segments = path_segments(path, "/basic-auth/");
+    let [user, pass] = segments.as_slice() else {
+        return create_error_response(StatusCode(404), "Use /basic-auth/{user}/{pass}").boxed();
+    };
+    let given = authorization_credentials(request, "Basic")
+        .and_then(|encoded| general_purpose::STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    match given.as_deref().and_then(|given| given.split_once(':')) {
+        Some((given_user, given_pass)) if given_user == user && given_pass == pass => {
+            json_response(json!({ "authenticated": true, "user": user }))
+        }
+        _ => unauthorized(&format!("Basic realm=\"{}\"", REALM)),
+    }
+}
+
+fn handle_bearer(request: &Request, settings: &AuthEndpointSettings) -> ResponseBox {
+    let Some(token) = authorization_credentials(request, "Bearer").filter(|t| !t.is_empty()) else {
+        return unauthorized(&format!("Bearer realm=\"{}\"", REALM));
+    };
+    match &settings.bearer_token {
+        Some(expected) if expected != token => unauthorized(&format!(
+            "Bearer realm=\"{}\", error=\"invalid_token\"",
+            REALM
+        )),
+        _ => json_response(json!({ "authenticated": true, "token": token })),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Self::Md5),
+            "SHA-256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> String {
+        let bytes: Vec<u8> = match self {
+            Self::Md5 => Md5::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+        };
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Parses `username="a", qop=auth, nc=00000001, …` from a Digest `Authorization` header.
+fn parse_digest_params(credentials: &str) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    let mut rest = credentials.trim();
+    while !rest.is_empty() {
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, remainder) = if let Some(quoted) = after.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after.find(',') {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            }
+        };
+        params.insert(key, value.trim().to_string());
+        rest = remainder.trim_start().trim_start_matches(',').trim_start();
+    }
+    params
+}
+
+/// The `response` a client must send for `params`, or `None` when required fields are missing.
+fn expected_digest_response(
+    algorithm: DigestAlgorithm,
+    params: &BTreeMap<String, String>,
+    method: &str,
+    realm: &str,
+    pass: &str,
+    body: &[u8],
+) -> Option<String> {
+    let field = |name: &str| params.get(name).map(String::as_str);
+    let ha1 = algorithm.hash(format!("{}:{}:{}", field("username")?, realm, pass).as_bytes());
+    let qop = field("qop")?;
+    let ha2 = if qop == "auth-int" {
+        algorithm.hash(format!("{}:{}:{}", method, field("uri")?, algorithm.hash(body)).as_bytes())
+    } else {
+        algorithm.hash(format!("{}:{}", method, field("uri")?).as_bytes())
+    };
+    Some(
+        algorithm.hash(
+            format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1,
+                field("nonce")?,
+                field("nc")?,
+                field("cnonce")?,
+                qop,
+                ha2
+            )
+            .as_bytes(),
+        ),
+    )
+}
+
+fn random_hex() -> String {
+    (0..16)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+/// Serves `/digest-auth/{qop}/{user}/{pass}[/{algorithm}]`. Any nonce is accepted, since the
+/// endpoint checks the client's hashing rather than replay protection.
+fn handle_digest_auth(request: &mut Request, path: &str) -> ResponseBox {
+    let segments = path_segments(path, "/digest-auth/");
+    let (qop, user, pass, algorithm) = match segments.as_slice() {
+        [qop, user, pass] => (qop, user, pass, DigestAlgorithm::Md5),
+        [qop, user, pass, algorithm] => match DigestAlgorithm::parse(algorithm) {
+            Some(algorithm) => (qop, user, pass, algorithm),
+            None => {
+                return create_error_response(
+                    StatusCode(400),
+                    "Invalid algorithm (expected MD5 or SHA-256)",
+                )
+                .boxed();
+            }
+        },
+        _ => {
+            return create_error_response(
+                StatusCode(404),
+                "Use /digest-auth/{qop}/{user}/{pass}[/{algorithm}]",
+            )
+            .boxed();
+        }
+    };
+    if qop != "auth" && qop != "auth-int" {
+        return create_error_response(StatusCode(400), "Invalid qop (expected auth or auth-int)")
+            .boxed();
+    }
+    let challenge = format!(
+        "Digest realm=\"{}\", qop=\"{}\", nonce=\"{}\", opaque=\"{}\", algorithm={}",
+        REALM,
+        qop,
+        random_hex(),
+        random_hex(),
+        algorithm.name()
+    );
+
+    let Some(params) = authorization_credentials(request, "Digest").map(parse_digest_params) else {
+        return unauthorized(&challenge);
+    };
+    let mut body = Vec::new();
+    if qop == "auth-int"
+        && request
+            .as_reader()
+            .take(MAX_AUTH_INT_BODY_BYTES)
+            .read_to_end(&mut body)
+            .is_err()
+    {
+        return create_error_response(StatusCode(400), "Failed to read request body").boxed();
+    }
+    let method = request.method().to_string();
+    let matches = params.get("username") == Some(user)
+        && params.get("qop") == Some(qop)
+        && params
+            .get("algorithm")
+            .is_none_or(|name| DigestAlgorithm::parse(name) == Some(algorithm))
+        && expected_digest_response(algorithm, &params, &method, REALM, pass, &body).as_ref()
+            == params.get("response");
+    if matches {
+        json_response(json!({ "authenticated": true, "user": user }))
+    } else {
+        unauthorized(&challenge)
+    }
+}
+
+/// Serves the `web.auth` endpoints; returns 401 with a challenge until the credentials match.
+pub fn handle_auth_request(
+    request: &mut Request,
+    path: &str,
+    settings: &AuthEndpointSettings,
+) -> ResponseBox {
+    if path == "/bearer" {
+        handle_bearer(request, settings)
+    } else if path.starts_with("/basic-auth/") {
+        handle_basic_auth(request, path)
+    } else {
+        handle_digest_auth(request, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_digest_params() {
+        let params = parse_digest_params(
+            r#"username="user", realm="mclocks", nonce="abc", uri="/digest-auth/auth/user/pass", qop=auth, nc=00000001, cnonce="xyz", response="0123""#,
+        );
+        assert_eq!(params["username"], "user");
+        assert_eq!(params["uri"], "/digest-auth/auth/user/pass");
+        assert_eq!(params["qop"], "auth");
+        assert_eq!(params["nc"], "00000001");
+        assert_eq!(params["response"], "0123");
+    }
+
+    #[test]
+    fn test_expected_digest_response_matches_rfc_example() {
+        // RFC 2617 section 3.5.
+        let params: BTreeMap<String, String> = [
+            ("username", "Mufasa"),
+            ("nonce", "dcd98b7102dd2f0e8b11d0f600bfb0c093"),
+            ("uri", "/dir/index.html"),
+            ("qop", "auth"),
+            ("nc", "00000001"),
+            ("cnonce", "0a4f113b"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(
+            expected_digest_response(
+                DigestAlgorithm::Md5,
+                &params,
+                "GET",
+                "testrealm@host.com",
+                "Circle Of Life",
+                b""
+            )
+            .as_deref(),
+            Some("6629fae49393a05397450978507c4ef1")
+        );
+        assert_eq!(
+            DigestAlgorithm::Sha256.hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
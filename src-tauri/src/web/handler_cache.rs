@@ -0,0 +1,126 @@
+use chrono::{Local, SecondsFormat};
+use serde_json::json;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+use super::http_conditional::{ConditionalRequest, FileValidators, is_not_modified};
+
+/// Longest `max-age` `/cache/{seconds}` accepts (one year).
+pub const MAX_CACHE_SECONDS: u64 = 365 * 24 * 60 * 60;
+
+pub fn is_cache_request(path: &str) -> bool {
+    path.starts_with("/cache/") || path.starts_with("/etag/")
+}
+
+/// `Last-Modified` of every `/cache` and `/etag` response: the first use, in whole seconds, so
+/// `If-Modified-Since` keeps matching until the app restarts.
+fn last_modified() -> SystemTime {
+    static TIME: OnceLock<SystemTime> = OnceLock::new();
+    *TIME.get_or_init(|| {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        UNIX_EPOCH + Duration::from_secs(secs)
+    })
+}
+
+fn add_header(response: &mut ResponseBox, name: &str, value: &str) {
+    if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+        response.add_header(header);
+    }
+}
+
+/// A JSON body, or `304 Not Modified` when the client's copy matches `validators`.
+fn conditional_response(
+    request: &Request,
+    validators: &FileValidators,
+    cache_control: Option<&str>,
+    body: serde_json::Value,
+) -> ResponseBox {
+    let conditional = ConditionalRequest::from_request(request);
+    let mut response = if is_not_modified(&conditional, validators) {
+        Response::empty(StatusCode(304)).boxed()
+    } else {
+        let body = serde_json::to_string_pretty(&body).unwrap_or_default() + "\n";
+        let mut response = Response::from_string(body).boxed();
+        add_header(&mut response, "Content-Type", "application/json");
+        response
+    };
+    add_header(&mut response, "ETag", &validators.etag);
+    if let Some(last_modified) = validators.last_modified.as_deref() {
+        add_header(&mut response, "Last-Modified", last_modified);
+    }
+    if let Some(cache_control) = cache_control {
+        add_header(&mut response, "Cache-Control", cache_control);
+    }
+    response
+}
+
+fn parse_cache_seconds(path: &str) -> Result<u64, String> {
+    let segment = path["/cache/".len()..].split('/').next().unwrap_or("");
+    match segment.parse::<u64>() {
+        Ok(seconds) if seconds <= MAX_CACHE_SECONDS => Ok(seconds),
+        Ok(_) => Err(format!("Seconds exceed maximum ({})", MAX_CACHE_SECONDS)),
+        Err(_) => Err("Invalid seconds parameter".to_string()),
+    }
+}
+
+/// The `{etag}` segment, which becomes the quoted `ETag` value.
+fn parse_etag(path: &str) -> Option<String> {
+    let segment = path["/etag/".len()..].split('/').next().unwrap_or("");
+    let etag = urlencoding::decode(segment).ok()?.into_owned();
+    let valid = !etag.is_empty() && etag.chars().all(|c| c.is_ascii_graphic() && c != '"');
+    valid.then_some(etag)
+}
+
+/// Serves `/cache/{seconds}` (`Cache-Control: public, max-age={seconds}`) and `/etag/{etag}`.
+/// Both answer `304` to a matching `If-None-Match` or a current `If-Modified-Since`.
+pub fn handle_cache_request(request: &Request, path: &str) -> ResponseBox {
+    let generated_at = Local::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    if path.starts_with("/cache/") {
+        let seconds = match parse_cache_seconds(path) {
+            Ok(seconds) => seconds,
+            Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+        };
+        let validators =
+            FileValidators::new(format!("\"cache-{}\"", seconds), Some(last_modified()));
+        return conditional_response(
+            request,
+            &validators,
+            Some(&format!("public, max-age={}", seconds)),
+            json!({ "maxAge": seconds, "generatedAt": generated_at }),
+        );
+    }
+
+    let Some(etag) = parse_etag(path) else {
+        return create_error_response(StatusCode(400), "Invalid etag").boxed();
+    };
+    let validators = FileValidators::new(format!("\"{}\"", etag), Some(last_modified()));
+    conditional_response(
+        request,
+        &validators,
+        None,
+        json!({ "etag": etag, "generatedAt": generated_at }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache_seconds_and_etag() {
+        assert_eq!(parse_cache_seconds("/cache/60"), Ok(60));
+        assert_eq!(parse_cache_seconds("/cache/0"), Ok(0));
+        assert!(parse_cache_seconds("/cache/abc").is_err());
+        assert!(parse_cache_seconds(&format!("/cache/{}", MAX_CACHE_SECONDS + 1)).is_err());
+
+        assert_eq!(parse_etag("/etag/v1"), Some("v1".to_string()));
+        assert_eq!(parse_etag("/etag/a%2Db"), Some("a-b".to_string()));
+        assert_eq!(parse_etag("/etag/%22quoted%22"), None);
+        assert_eq!(parse_etag("/etag/"), None);
+    }
+}
@@ -0,0 +1,157 @@
+use serde_json::json;
+use std::collections::BTreeMap;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+use super::common::create_error_response;
+
+const COOKIES_PATH: &str = "/cookies";
+
+pub fn is_cookies_request(path: &str) -> bool {
+    path == COOKIES_PATH || path == "/cookies/set" || path == "/cookies/delete"
+}
+
+/// Cookies sent in the `Cookie` header(s), by name.
+fn request_cookies(request: &Request) -> BTreeMap<String, String> {
+    request
+        .headers()
+        .iter()
+        .filter(|h| h.field.equiv("Cookie"))
+        .flat_map(|h| h.value.as_str().split(';'))
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// RFC 6265 `cookie-name`: an HTTP token.
+fn is_valid_cookie_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+/// RFC 6265 `cookie-octet`s: printable ASCII except `"`, `,`, `;` and `\`.
+fn is_valid_cookie_value(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_graphic() && !matches!(c, '"' | ',' | ';' | '\\'))
+}
+
+fn parse_query_pairs(query: &str) -> Result<Vec<(String, String)>, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode_part = |part: &str| {
+                decode(&part.replace('+', " "))
+                    .map(|v| v.into_owned())
+                    .map_err(|_| format!("Invalid cookie parameter: {}", pair))
+            };
+            Ok((decode_part(name)?, decode_part(value)?))
+        })
+        .collect()
+}
+
+/// `302` back to `/cookies` with one `Set-Cookie` per cookie, like httpbin.
+fn redirect_with_cookies(set_cookies: &[String]) -> ResponseBox {
+    let mut response = Response::empty(StatusCode(302)).boxed();
+    let headers = std::iter::once(("Location", COOKIES_PATH.to_string()))
+        .chain(set_cookies.iter().map(|c| ("Set-Cookie", c.clone())));
+    for (name, value) in headers {
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+/// Serves `/cookies` (echo), `/cookies/set?name=value` and `/cookies/delete?name`.
+pub fn handle_cookies_request(request: &Request, path: &str, query: &str) -> ResponseBox {
+    if path == COOKIES_PATH {
+        let body = json!({ "cookies": request_cookies(request) });
+        let body = serde_json::to_string_pretty(&body).unwrap_or_default() + "\n";
+        let mut response = Response::from_string(body).boxed();
+        for (name, value) in [
+            ("Content-Type", "application/json"),
+            ("Cache-Control", "no-store"),
+        ] {
+            if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                response.add_header(header);
+            }
+        }
+        return response;
+    }
+
+    let pairs = match parse_query_pairs(query) {
+        Ok(pairs) => pairs,
+        Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+    };
+    if pairs.is_empty() {
+        return create_error_response(
+            StatusCode(400),
+            "Specify cookies in the query, e.g. ?name=value",
+        )
+        .boxed();
+    }
+    let deleting = path == "/cookies/delete";
+    let mut set_cookies = Vec::new();
+    for (name, value) in pairs {
+        if !is_valid_cookie_name(&name) {
+            return create_error_response(
+                StatusCode(400),
+                &format!("Invalid cookie name: {}", name),
+            )
+            .boxed();
+        }
+        if deleting {
+            set_cookies.push(format!(
+                "{}=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+                name
+            ));
+        } else if is_valid_cookie_value(&value) {
+            set_cookies.push(format!("{}={}; Path=/", name, value));
+        } else {
+            return create_error_response(
+                StatusCode(400),
+                &format!("Invalid value for cookie {}", name),
+            )
+            .boxed();
+        }
+    }
+    redirect_with_cookies(&set_cookies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_name_and_value_validation() {
+        assert!(is_valid_cookie_name("session_id"));
+        assert!(!is_valid_cookie_name("bad name"));
+        assert!(!is_valid_cookie_name("a=b"));
+        assert!(!is_valid_cookie_name(""));
+
+        assert!(is_valid_cookie_value("abc-123"));
+        assert!(is_valid_cookie_value(""));
+        assert!(!is_valid_cookie_value("a;b"));
+        assert!(!is_valid_cookie_value("a b"));
+    }
+
+    #[test]
+    fn test_parse_query_pairs() {
+        assert_eq!(
+            parse_query_pairs("a=1&b=x%2Fy&c").unwrap(),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "x/y".to_string()),
+                ("c".to_string(), String::new()),
+            ]
+        );
+    }
+}
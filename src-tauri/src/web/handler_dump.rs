@@ -1,9 +1,17 @@
+use base64::{Engine as _, engine::general_purpose};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::Read;
+use std::thread;
+use std::time::Duration;
 use tiny_http::{Header, Response, StatusCode};
+use urlencoding::decode;
 
 use super::common::create_error_response;
+use super::handler_slow::MAX_SLOW_SECONDS;
+use super::handler_static::create_json_value_response;
+use crate::web::status_code::should_have_response_body;
+use crate::web_server::WebMarkdownHighlightConfig;
 
 const MAX_DUMP_BODY_BYTES: usize = 10 * 1024 * 1024; // 10MB
 
@@ -11,16 +19,230 @@ const MAX_DUMP_BODY_BYTES: usize = 10 * 1024 * 1024; // 10MB
 pub struct DumpResponse {
     pub method: String,
     pub path: String,
+    #[serde(default)]
     pub query: Option<Vec<HashMap<String, String>>>,
+    #[serde(default)]
     pub headers: Vec<HashMap<String, String>>,
+    #[serde(default)]
     pub body: Option<String>,
+    /// `"base64"` when the body is not valid UTF-8 and `body` holds it base64-encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_encoding: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parsed_body: Option<serde_json::Value>,
 }
 
-pub fn handle_dump_request(request: &mut tiny_http::Request) -> Response<std::io::Cursor<Vec<u8>>> {
+/// One part of a `multipart/form-data` body in `parsed_body`.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct MultipartPart {
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub size: usize,
+    /// Content of a UTF-8 part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Content of a binary part, base64-encoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+}
+
+fn decode_form_component(value: &str) -> String {
+    let value = value.replace('+', " ");
+    decode(&value).map(|v| v.into_owned()).unwrap_or(value)
+}
+
+/// Parses an `application/x-www-form-urlencoded` body into `[{"name": "value"}, ...]` (order preserved).
+fn parse_form_urlencoded(body: &str) -> serde_json::Value {
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let mut map = serde_json::Map::new();
+            map.insert(
+                decode_form_component(name),
+                serde_json::Value::String(decode_form_component(value)),
+            );
+            serde_json::Value::Object(map)
+        })
+        .collect()
+}
+
+/// Reads a parameter such as `boundary` or `filename` from a header value, unquoting it.
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case(name) {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        Some(value.replace("\\\"", "\""))
+    })
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Splits a `multipart/form-data` body on `boundary`.
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<MultipartPart>, String> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let start = find_bytes(body, &delimiter).ok_or("boundary not found in body")?;
+    let mut rest = &body[start + delimiter.len()..];
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or("missing line break after boundary")?;
+        let header_end = find_bytes(rest, b"\r\n\r\n").ok_or("part headers are not terminated")?;
+        let headers = String::from_utf8_lossy(&rest[..header_end]);
+        rest = &rest[header_end + 4..];
+        let mut next_delimiter = b"\r\n".to_vec();
+        next_delimiter.extend_from_slice(&delimiter);
+        let content_end = find_bytes(rest, &next_delimiter).ok_or("closing boundary not found")?;
+        let content = &rest[..content_end];
+        rest = &rest[content_end + next_delimiter.len()..];
+
+        let mut part = MultipartPart {
+            name: None,
+            filename: None,
+            content_type: None,
+            size: content.len(),
+            text: None,
+            base64: None,
+        };
+        for line in headers.split("\r\n") {
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            if field.trim().eq_ignore_ascii_case("content-disposition") {
+                part.name = header_param(value, "name");
+                part.filename = header_param(value, "filename");
+            } else if field.trim().eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(value.trim().to_string());
+            }
+        }
+        match std::str::from_utf8(content) {
+            Ok(text) => part.text = Some(text.to_string()),
+            Err(_) => part.base64 = Some(general_purpose::STANDARD.encode(content)),
+        }
+        parts.push(part);
+    }
+}
+
+/// `parsed_body` for JSON, URL-encoded form and multipart bodies; `None` for other types.
+fn parse_body(content_type: &str, body: &[u8]) -> Option<serde_json::Value> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    if mime.contains("json") {
+        let parsed = std::str::from_utf8(body)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str(text).map_err(|e| e.to_string()));
+        return Some(parsed.unwrap_or_else(|e| {
+            serde_json::Value::String(format!("ERROR: Failed to parse JSON body: {}", e))
+        }));
+    }
+    if mime == "application/x-www-form-urlencoded" {
+        return Some(match std::str::from_utf8(body) {
+            Ok(text) => parse_form_urlencoded(text),
+            Err(e) => serde_json::Value::String(format!("ERROR: Failed to parse form body: {}", e)),
+        });
+    }
+    if mime == "multipart/form-data" {
+        let parsed = header_param(content_type, "boundary")
+            .ok_or_else(|| "missing boundary parameter".to_string())
+            .and_then(|boundary| parse_multipart(body, &boundary))
+            .and_then(|parts| serde_json::to_value(parts).map_err(|e| e.to_string()));
+        return Some(parsed.unwrap_or_else(|e| {
+            serde_json::Value::String(format!("ERROR: Failed to parse multipart body: {}", e))
+        }));
+    }
+    None
+}
+
+/// How the echo is answered, from `?status=`, `?header=Name:value` (repeatable), `?delay=` and
+/// `?format=html|json`. Values that don't fit are ignored, so such queries are only echoed.
+#[derive(Debug, PartialEq, Eq)]
+struct DumpShaping {
+    status: u16,
+    headers: Vec<(String, String)>,
+    delay: Duration,
+    format: Option<String>,
+}
+
+/// RFC 7230 `token`, the only characters allowed in a header name.
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+fn parse_dump_shaping(query: &str) -> DumpShaping {
+    let mut shaping = DumpShaping {
+        status: 200,
+        headers: Vec::new(),
+        delay: Duration::ZERO,
+        format: None,
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode_form_component(value);
+        match key {
+            "status" => {
+                if let Ok(code) = value.parse::<u16>()
+                    && (100..=599).contains(&code)
+                {
+                    shaping.status = code;
+                }
+            }
+            "header" => {
+                // Control characters would let the value inject extra headers.
+                if let Some((name, header_value)) = value.split_once(':')
+                    && is_header_name(name.trim())
+                    && !header_value.chars().any(|c| c.is_control())
+                {
+                    shaping
+                        .headers
+                        .push((name.trim().to_string(), header_value.trim().to_string()));
+                }
+            }
+            "delay" => {
+                if let Ok(seconds) = value.parse::<u64>()
+                    && seconds <= MAX_SLOW_SECONDS
+                {
+                    shaping.delay = Duration::from_secs(seconds);
+                }
+            }
+            "format" if value == "html" || value == "json" => shaping.format = Some(value),
+            _ => {}
+        }
+    }
+    shaping
+}
+
+pub fn handle_dump_request(
+    request: &mut tiny_http::Request,
+    markdown_highlight: Option<&WebMarkdownHighlightConfig>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
     let method = request.method().to_string();
     let full_url = request.url();
+    let display_url = full_url.to_string();
 
     // Extract path and query string
     let (full_path, query_string) = match full_url.split_once('?') {
@@ -28,6 +250,8 @@ pub fn handle_dump_request(request: &mut tiny_http::Request) -> Response<std::io
         None => (full_url, None),
     };
 
+    let shaping = parse_dump_shaping(query_string.unwrap_or(""));
+
     // Extract path part after /dump or /dump/
     let path = if full_path.starts_with("/dump/") {
         format!("/{}", &full_path[6..])
@@ -75,41 +299,42 @@ pub fn handle_dump_request(request: &mut tiny_http::Request) -> Response<std::io
 
     // Read body if present (capped at 10MB)
     let mut body_content = Vec::new();
-    let body = if request
+    if request
         .as_reader()
         .take(MAX_DUMP_BODY_BYTES as u64 + 1)
         .read_to_end(&mut body_content)
-        .is_ok()
-        && !body_content.is_empty()
+        .is_err()
     {
-        if body_content.len() > MAX_DUMP_BODY_BYTES {
-            return create_error_response(StatusCode(413), "Request body too large");
-        }
-        String::from_utf8(body_content).ok()
-    } else {
+        body_content.clear();
+    }
+    if body_content.len() > MAX_DUMP_BODY_BYTES {
+        return create_error_response(StatusCode(413), "Request body too large");
+    }
+
+    let content_type = headers
+        .iter()
+        .flat_map(|header_map| header_map.iter())
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+        .unwrap_or("");
+    let parsed_body = if body_content.is_empty() {
         None
+    } else {
+        parse_body(content_type, &body_content)
     };
 
-    // Check if Content-Type indicates JSON and parse body if so
-    let parsed_body = if let Some(ref body_str) = body {
-        let is_json = headers.iter().any(|header_map| {
-            header_map.iter().any(|(key, value)| {
-                key.eq_ignore_ascii_case("content-type") && value.contains("json")
-            })
-        });
-        if is_json {
-            match serde_json::from_str(body_str) {
-                Ok(value) => Some(value),
-                Err(e) => Some(serde_json::Value::String(format!(
-                    "ERROR: Failed to parse JSON body: {}",
-                    e
-                ))),
-            }
-        } else {
-            None
-        }
+    let body_size = body_content.len();
+    // Non-UTF-8 bodies are returned base64-encoded rather than lossily decoded
+    let (body, body_encoding) = if body_content.is_empty() {
+        (None, None)
     } else {
-        None
+        match String::from_utf8(body_content) {
+            Ok(text) => (Some(text), None),
+            Err(e) => (
+                Some(general_purpose::STANDARD.encode(e.into_bytes())),
+                Some("base64".to_string()),
+            ),
+        }
     };
 
     let dump_data = DumpResponse {
@@ -118,19 +343,146 @@ pub fn handle_dump_request(request: &mut tiny_http::Request) -> Response<std::io
         query,
         headers,
         body,
+        body_encoding,
         parsed_body,
     };
 
-    match serde_json::to_string_pretty(&dump_data) {
-        Ok(json) => {
-            if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], b"application/json") {
-                Response::from_string(json)
-                    .with_header(header)
-                    .with_status_code(StatusCode(200))
-            } else {
-                Response::from_string(json).with_status_code(StatusCode(200))
+    let wants_html = match shaping.format.as_deref() {
+        Some(format) => format == "html",
+        None => dump_data.headers.iter().any(|header_map| {
+            header_map.iter().any(|(key, value)| {
+                key.eq_ignore_ascii_case("accept") && value.contains("text/html")
+            })
+        }),
+    };
+
+    if !shaping.delay.is_zero() {
+        thread::sleep(shaping.delay);
+    }
+
+    let mut response = if !should_have_response_body(shaping.status) {
+        Response::from_data(Vec::new()).with_status_code(StatusCode(shaping.status))
+    } else if wants_html {
+        let value = match serde_json::to_value(&dump_data) {
+            Ok(value) => value,
+            Err(_) => return create_error_response(StatusCode(500), "Internal Server Error"),
+        };
+        let summary_fields = [
+            ("Method", dump_data.method.clone()),
+            ("Path", dump_data.path.clone()),
+            ("Headers", dump_data.headers.len().to_string()),
+            ("Body", format!("{}B", body_size)),
+            ("Status", shaping.status.to_string()),
+        ];
+        create_json_value_response(
+            &format!("{} {} - dump", dump_data.method, dump_data.path),
+            &display_url,
+            &value,
+            &summary_fields,
+            shaping.status,
+            markdown_highlight,
+        )
+    } else {
+        match serde_json::to_string_pretty(&dump_data) {
+            Ok(json) => {
+                if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], b"application/json") {
+                    Response::from_string(json)
+                        .with_header(header)
+                        .with_status_code(StatusCode(shaping.status))
+                } else {
+                    Response::from_string(json).with_status_code(StatusCode(shaping.status))
+                }
             }
+            Err(_) => return create_error_response(StatusCode(500), "Internal Server Error"),
+        }
+    };
+    for (name, value) in &shaping.headers {
+        // The length always comes from the echoed body
+        if name.eq_ignore_ascii_case("Content-Length") {
+            continue;
         }
-        Err(_) => create_error_response(StatusCode(500), "Internal Server Error"),
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dump_shaping() {
+        let shaping = parse_dump_shaping(
+            "status=201&header=X-Foo:bar&header=Cache-Control%3A+no-store&delay=0&format=html&k=v",
+        );
+        assert_eq!(shaping.status, 201);
+        assert_eq!(
+            shaping.headers,
+            vec![
+                ("X-Foo".to_string(), "bar".to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ]
+        );
+        assert_eq!(shaping.format.as_deref(), Some("html"));
+        // Values that don't fit are ignored rather than rejected.
+        let ignored = DumpShaping {
+            status: 200,
+            headers: Vec::new(),
+            delay: Duration::ZERO,
+            format: None,
+        };
+        for query in [
+            "status=abc",
+            "status=600",
+            "header=novalue",
+            "header=X-A:b%0d%0aSet-Cookie:evil=1",
+            "header=X%0d%0aA:b",
+            "header=Bad+Name:b",
+            "delay=99999",
+            "delay=soon",
+            "format=xml",
+        ] {
+            assert_eq!(parse_dump_shaping(query), ignored, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_parse_form_urlencoded() {
+        assert_eq!(
+            parse_form_urlencoded("name=J%C3%B6rg+M&tag=a&tag=b&flag"),
+            serde_json::json!([
+                { "name": "Jörg M" },
+                { "tag": "a" },
+                { "tag": "b" },
+                { "flag": "" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_multipart() {
+        let mut body =
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n".to_vec();
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n");
+        body.extend_from_slice(&[0xff, 0x00, 0x01]);
+        body.extend_from_slice(b"\r\n--XyZ--\r\n");
+        let parts = parse_multipart(&body, "XyZ").expect("parse multipart");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name.as_deref(), Some("title"));
+        assert_eq!(parts[0].text.as_deref(), Some("hello"));
+        assert_eq!(parts[1].filename.as_deref(), Some("a.bin"));
+        assert_eq!(
+            parts[1].content_type.as_deref(),
+            Some("application/octet-stream")
+        );
+        assert_eq!(parts[1].size, 3);
+        assert_eq!(parts[1].base64.as_deref(), Some("/wAB"));
+        assert!(parse_multipart(b"--XyZ\r\nbroken", "XyZ").is_err());
+        assert_eq!(
+            header_param("multipart/form-data; boundary=\"a b\"", "boundary").as_deref(),
+            Some("a b")
+        );
     }
 }
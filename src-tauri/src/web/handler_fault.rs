@@ -0,0 +1,422 @@
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{HTTPVersion, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+use super::handler_static::WebResponse;
+
+const FAULT_BODY_BYTES: usize = 1000;
+const DEFAULT_LONG_HEADER_BYTES: usize = 64 * 1024;
+const MAX_LONG_HEADER_BYTES: usize = 1024 * 1024;
+const MAX_REQUEST_HEAD_BYTES: usize = 64 * 1024;
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Fault listener connections handled at once; more are closed straight away.
+const MAX_FAULT_CONNECTIONS: usize = 16;
+
+/// A broken response written by `/fault/{name}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Close the connection without answering.
+    Close,
+    /// Cut the connection in the middle of a chunked body.
+    Reset,
+    /// Announce a longer `Content-Length` than the body sent.
+    ShortBody,
+    /// Send a chunk-size line that is not hexadecimal.
+    BadChunked,
+    /// Send a status line that is not HTTP.
+    BadStatusLine,
+    /// Send a well-formed response with a header value of this many bytes.
+    LongHeaders(usize),
+}
+
+pub const FAULT_NAMES: &[&str] = &[
+    "close",
+    "reset",
+    "short-body",
+    "bad-chunked",
+    "bad-status",
+    "long-headers",
+];
+
+/// Resolves `/fault/{name}?bytes=`; unknown names and bad parameters get an error response.
+pub fn parse_fault(path: &str, query: &str) -> Result<Fault, ResponseBox> {
+    let name = path.strip_prefix("/fault/").unwrap_or("");
+    let name = name.split('/').next().unwrap_or(name);
+    let fault = match name {
+        "close" => Fault::Close,
+        "reset" => Fault::Reset,
+        "short-body" => Fault::ShortBody,
+        "bad-chunked" => Fault::BadChunked,
+        "bad-status" => Fault::BadStatusLine,
+        "long-headers" => {
+            let bytes = query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == "bytes")
+                .map(|(_, value)| value.parse::<usize>());
+            match bytes {
+                None => Fault::LongHeaders(DEFAULT_LONG_HEADER_BYTES),
+                Some(Ok(bytes)) if (1..=MAX_LONG_HEADER_BYTES).contains(&bytes) => {
+                    Fault::LongHeaders(bytes)
+                }
+                Some(_) => {
+                    return Err(create_error_response(
+                        StatusCode(400),
+                        &format!("bytes must be between 1 and {}", MAX_LONG_HEADER_BYTES),
+                    )
+                    .boxed());
+                }
+            }
+        }
+        _ => {
+            return Err(create_error_response(
+                StatusCode(404),
+                &format!("Unknown fault. Available: {}", FAULT_NAMES.join(", ")),
+            )
+            .boxed());
+        }
+    };
+    Ok(fault)
+}
+
+fn fault_body(len: usize) -> Vec<u8> {
+    (0..len).map(|i| b'a' + (i % 26) as u8).collect()
+}
+
+impl Fault {
+    /// Whether the fault ends the connection itself, which tiny_http can't do: those faults
+    /// are sent on the fault listener (`web.faultPort`), the others in-band on the request's
+    /// connection.
+    fn needs_own_connection(self) -> bool {
+        matches!(self, Fault::Close | Fault::Reset)
+    }
+}
+
+/// Answers `/fault/{name}`. In-band faults are returned for the listener worker to write with
+/// [`write_fault_response`].
+/// tiny_http keeps keep-alive connections open and never hands out the socket, so `close` and
+/// `reset` only work on the fault listener at `web.faultPort`; here they answer with a 307 to
+/// it, for clients that follow redirects.
+pub fn handle_fault_request(request: &Request, path: &str, query: &str) -> WebResponse {
+    let fault = match parse_fault(path, query) {
+        Ok(fault) => fault,
+        Err(response) => return WebResponse::Handled(response),
+    };
+    if !fault.needs_own_connection() {
+        return WebResponse::Fault(fault);
+    }
+    let Some(port) = fault_listener_port() else {
+        return WebResponse::Handled(
+            create_error_response(StatusCode(503), "The fault listener is not running").boxed(),
+        );
+    };
+    let location = format!(
+        "http://{}:{}{}",
+        redirect_host(request),
+        port,
+        if query.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, query)
+        }
+    );
+    let body = if request.method() == &tiny_http::Method::Head {
+        String::new()
+    } else {
+        format!("Redirecting to {}\n", location)
+    };
+    let mut response = Response::from_string(body).with_status_code(StatusCode(307));
+    for (name, value) in [
+        ("Location", location.as_str()),
+        ("Content-Type", "text/plain; charset=utf-8"),
+        ("Cache-Control", "no-store"),
+    ] {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    WebResponse::Handled(response.boxed())
+}
+
+/// The host the client reached this server by, so clients on other machines are sent back to
+/// it rather than to their own loopback.
+fn redirect_host(request: &Request) -> String {
+    let host = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Host"))
+        .map(|h| h.value.as_str().trim())
+        .unwrap_or("");
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().map(|h| format!("[{}]", h)),
+        None => host.split(':').next().map(str::to_string),
+    };
+    match host {
+        Some(host)
+            if !host.is_empty()
+                && host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.[]:".contains(c)) =>
+        {
+            host
+        }
+        _ => "127.0.0.1".to_string(),
+    }
+}
+
+/// Writes an in-band fault straight to the request's connection. The connection itself is
+/// only closed when the client sent `Connection: close` (or used HTTP/1.0); otherwise a
+/// `short-body` client waits for the rest of the body until it gives up. Never called for
+/// `close` and `reset`, which are only sent by the fault listener at `web.faultPort`.
+pub fn write_fault_response(request: Request, fault: Fault) -> io::Result<()> {
+    let mut writer = request.into_writer();
+    write_fault_bytes(&mut writer, fault)?;
+    writer.flush()
+}
+
+/// The bytes of a fault that is a (broken) response rather than a connection event.
+fn write_fault_bytes(writer: &mut dyn Write, fault: Fault) -> io::Result<()> {
+    match fault {
+        Fault::Close | Fault::Reset => {}
+        Fault::ShortBody => {
+            let body = fault_body(FAULT_BODY_BYTES / 2);
+            writer.write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    FAULT_BODY_BYTES
+                )
+                .as_bytes(),
+            )?;
+            writer.write_all(&body)?;
+        }
+        Fault::BadChunked => {
+            writer.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            )?;
+            writer.write_all(b"5\r\nhello\r\nzz\r\nnot a chunk\r\n0\r\n\r\n")?;
+        }
+        Fault::BadStatusLine => {
+            writer.write_all(
+                b"MCLOCKS/9.9 ??? Not HTTP\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK",
+            )?;
+        }
+        Fault::LongHeaders(bytes) => {
+            let value = fault_body(bytes);
+            writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Long: ")?;
+            writer.write_all(&value)?;
+            writer.write_all(b"\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK")?;
+        }
+    }
+    Ok(())
+}
+
+/// The running fault listener; stopped when `web.fault` or `web.faultPort` changes.
+struct FaultListener {
+    port: u16,
+    bind_ip: String,
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl FaultListener {
+    fn start(bind_ip: &str, port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind((bind_ip, port)).map_err(|e| {
+            format!(
+                "Failed to start the fault listener on web.faultPort {}: {}",
+                port, e
+            )
+        })?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                // Over the limit the connection is just dropped, which is a `close` fault anyway.
+                if active.fetch_add(1, Ordering::SeqCst) >= MAX_FAULT_CONNECTIONS {
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let active = Arc::clone(&active);
+                thread::spawn(move || {
+                    let _ = handle_fault_connection(stream);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Self {
+            port,
+            bind_ip: bind_ip.to_string(),
+            stop,
+            thread,
+        })
+    }
+
+    /// Stops accepting and waits until the port is released, so it can be bound again.
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking `accept` so the thread sees the flag and drops the listener.
+        let wake_ip = if self.bind_ip == "0.0.0.0" {
+            "127.0.0.1"
+        } else {
+            self.bind_ip.as_str()
+        };
+        let _ = TcpStream::connect((wake_ip, self.port));
+        let _ = self.thread.join();
+    }
+}
+
+fn fault_listener() -> &'static Mutex<Option<FaultListener>> {
+    static LISTENER: OnceLock<Mutex<Option<FaultListener>>> = OnceLock::new();
+    LISTENER.get_or_init(|| Mutex::new(None))
+}
+
+/// Applies `web.fault` and `web.faultPort`: `port` is `None` when faults are disabled.
+/// The listener is re-bound when the port or the main server's bind address changed.
+pub fn configure_fault_listener(port: Option<u16>, bind_ip: &str) -> Result<(), String> {
+    let mut listener = fault_listener()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(running) = listener.as_ref()
+        && Some(running.port) == port
+        && running.bind_ip == bind_ip
+    {
+        return Ok(());
+    }
+    if let Some(running) = listener.take() {
+        running.stop();
+    }
+    if let Some(port) = port {
+        *listener = Some(FaultListener::start(bind_ip, port)?);
+    }
+    Ok(())
+}
+
+/// Port of the running fault listener, if `web.fault` is enabled.
+pub fn fault_listener_port() -> Option<u16> {
+    fault_listener()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_ref()
+        .map(|listener| listener.port)
+}
+
+/// Reads the request head and returns its target (`/fault/name?query`).
+fn read_request_target(stream: &mut TcpStream) -> io::Result<String> {
+    stream.set_read_timeout(Some(REQUEST_READ_TIMEOUT))?;
+    let mut head = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head too large",
+            ));
+        }
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let line = head.split(|b| *b == b'\n').next().unwrap_or_default();
+    String::from_utf8_lossy(line)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed request line"))
+}
+
+fn handle_fault_connection(mut stream: TcpStream) -> io::Result<()> {
+    let target = read_request_target(&mut stream)?;
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    match parse_fault(path, query) {
+        Ok(fault) => write_fault(&mut stream, fault),
+        Err(response) => {
+            response.raw_print(&mut stream, HTTPVersion(1, 0), &[], false, None)?;
+            stream.flush()?;
+            stream.shutdown(Shutdown::Both)
+        }
+    }
+}
+
+/// Writes `fault` to a fault listener connection and ends it: a normal close (FIN) for every
+/// fault except `reset`, which drops the socket with a zero linger so the peer gets a TCP RST.
+fn write_fault(stream: &mut TcpStream, fault: Fault) -> io::Result<()> {
+    if fault == Fault::Reset {
+        let body = fault_body(FAULT_BODY_BYTES);
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        )?;
+        stream.write_all(format!("{:x}\r\n", body.len()).as_bytes())?;
+        stream.write_all(&body[..body.len() / 2])?;
+        stream.flush()?;
+        // Give the client a moment to read the partial body before the reset discards it.
+        thread::sleep(Duration::from_millis(100));
+        socket2::SockRef::from(&*stream).set_linger(Some(Duration::ZERO))?;
+        return Ok(());
+    }
+    write_fault_bytes(stream, fault)?;
+    stream.flush()?;
+    stream.shutdown(Shutdown::Write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fault() {
+        assert_eq!(parse_fault("/fault/close", "").ok(), Some(Fault::Close));
+        assert_eq!(
+            parse_fault("/fault/short-body/extra", "").ok(),
+            Some(Fault::ShortBody)
+        );
+        assert_eq!(
+            parse_fault("/fault/long-headers", "").ok(),
+            Some(Fault::LongHeaders(DEFAULT_LONG_HEADER_BYTES))
+        );
+        assert_eq!(
+            parse_fault("/fault/long-headers", "bytes=100").ok(),
+            Some(Fault::LongHeaders(100))
+        );
+        assert_eq!(
+            parse_fault("/fault/long-headers", "bytes=0")
+                .err()
+                .map(|r| r.status_code().0),
+            Some(400)
+        );
+        assert_eq!(
+            parse_fault("/fault/teapot", "")
+                .err()
+                .map(|r| r.status_code().0),
+            Some(404)
+        );
+    }
+
+    #[test]
+    fn test_write_fault_bytes() {
+        let written = |fault: Fault| {
+            let mut bytes = Vec::new();
+            write_fault_bytes(&mut bytes, fault).expect("write");
+            String::from_utf8(bytes).expect("utf-8")
+        };
+        let short = written(Fault::ShortBody);
+        let (head, body) = short.split_once("\r\n\r\n").expect("head");
+        assert!(head.contains(&format!("Content-Length: {}", FAULT_BODY_BYTES)));
+        assert_eq!(body.len(), FAULT_BODY_BYTES / 2);
+        assert!(written(Fault::BadStatusLine).starts_with("MCLOCKS/"));
+        assert!(written(Fault::LongHeaders(10)).contains("X-Long: abcdefghij\r\n"));
+        assert!(written(Fault::Close).is_empty());
+    }
+}
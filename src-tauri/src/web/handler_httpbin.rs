@@ -0,0 +1,241 @@
+//! `web.httpbin` utility endpoints modelled on httpbin.org, so tests can run offline.
+use base64::{Engine as _, engine::general_purpose};
+use flate2::Compression;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::Write;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+
+/// Largest body `/bytes/{n}` sends.
+pub const MAX_HTTPBIN_BYTES: usize = 10 * 1024 * 1024;
+/// Most lines `/stream/{n}` sends.
+pub const MAX_HTTPBIN_STREAM_LINES: usize = 100;
+
+const EXACT_PATHS: &[&str] = &[
+    "/headers",
+    "/ip",
+    "/user-agent",
+    "/uuid",
+    "/gzip",
+    "/deflate",
+];
+const PREFIX_PATHS: &[&str] = &["/bytes/", "/stream/", "/base64/"];
+
+pub fn is_httpbin_request(path: &str) -> bool {
+    EXACT_PATHS.contains(&path) || PREFIX_PATHS.iter().any(|prefix| path.starts_with(prefix))
+}
+
+fn with_content_type(mut response: ResponseBox, content_type: &str) -> ResponseBox {
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+        response.add_header(header);
+    }
+    response
+}
+
+fn json_response(value: serde_json::Value) -> ResponseBox {
+    let body = serde_json::to_string_pretty(&value).unwrap_or_default() + "\n";
+    with_content_type(Response::from_string(body).boxed(), "application/json")
+}
+
+/// Request headers by name; repeated headers are joined with `, ` like httpbin does.
+fn request_headers(request: &Request) -> BTreeMap<String, String> {
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for header in request.headers() {
+        headers
+            .entry(header.field.as_str().to_string())
+            .and_modify(|value| {
+                value.push_str(", ");
+                value.push_str(header.value.as_str());
+            })
+            .or_insert_with(|| header.value.as_str().to_string());
+    }
+    headers
+}
+
+fn request_origin(request: &Request) -> String {
+    request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_count(path: &str, prefix: &str, max: usize) -> Result<usize, String> {
+    let segment = path[prefix.len()..].split('/').next().unwrap_or("");
+    match segment.parse::<usize>() {
+        Ok(n) if n <= max => Ok(n),
+        Ok(_) => Err(format!("Count exceeds maximum ({})", max)),
+        Err(_) => Err("Invalid count".to_string()),
+    }
+}
+
+fn query_seed(query: &str) -> Result<Option<u64>, String> {
+    match query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "seed")
+    {
+        Some((_, value)) => value
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|_| "Invalid seed parameter".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// `n` random bytes; the same `seed` always gives the same bytes.
+fn random_bytes(n: usize, seed: Option<u64>) -> Vec<u8> {
+    let mut bytes = vec![0u8; n];
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed).fill_bytes(&mut bytes),
+        None => rand::rng().fill_bytes(&mut bytes),
+    }
+    bytes
+}
+
+/// Decodes standard or URL-safe base64, with or without padding.
+fn decode_base64_value(value: &str) -> Option<Vec<u8>> {
+    let value = urlencoding::decode(value).ok()?;
+    let trimmed = value.trim_end_matches('=');
+    general_purpose::STANDARD_NO_PAD
+        .decode(trimmed)
+        .or_else(|_| general_purpose::URL_SAFE_NO_PAD.decode(trimmed))
+        .ok()
+}
+
+fn compressed_response(request: &Request, encoding: &str) -> ResponseBox {
+    let flag = if encoding == "gzip" {
+        "gzipped"
+    } else {
+        "deflated"
+    };
+    let value = json!({
+        flag: true,
+        "headers": request_headers(request),
+        "method": request.method().to_string(),
+        "origin": request_origin(request),
+    });
+    let body = serde_json::to_string_pretty(&value).unwrap_or_default() + "\n";
+    let compressed = if encoding == "gzip" {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(body.as_bytes())
+            .and_then(|_| encoder.finish())
+    } else {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(body.as_bytes())
+            .and_then(|_| encoder.finish())
+    };
+    let Ok(compressed) = compressed else {
+        return create_error_response(StatusCode(500), "Compression failed").boxed();
+    };
+    let mut response =
+        with_content_type(Response::from_data(compressed).boxed(), "application/json");
+    if let Ok(header) = Header::from_bytes(&b"Content-Encoding"[..], encoding.as_bytes()) {
+        response.add_header(header);
+    }
+    response
+}
+
+/// Serves `/headers`, `/ip`, `/user-agent`, `/uuid`, `/bytes/{n}`, `/stream/{n}`,
+/// `/base64/{value}`, `/gzip` and `/deflate`.
+pub fn handle_httpbin_request(request: &Request, path: &str, query: &str) -> ResponseBox {
+    match path {
+        "/headers" => return json_response(json!({ "headers": request_headers(request) })),
+        "/ip" => return json_response(json!({ "origin": request_origin(request) })),
+        "/user-agent" => {
+            let user_agent = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("User-Agent"))
+                .map(|h| h.value.as_str().to_string())
+                .unwrap_or_default();
+            return json_response(json!({ "user-agent": user_agent }));
+        }
+        "/uuid" => return json_response(json!({ "uuid": uuid::Uuid::new_v4().to_string() })),
+        "/gzip" => return compressed_response(request, "gzip"),
+        "/deflate" => return compressed_response(request, "deflate"),
+        _ => {}
+    }
+
+    if path.starts_with("/bytes/") {
+        let n = match parse_count(path, "/bytes/", MAX_HTTPBIN_BYTES) {
+            Ok(n) => n,
+            Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+        };
+        let seed = match query_seed(query) {
+            Ok(seed) => seed,
+            Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+        };
+        return with_content_type(
+            Response::from_data(random_bytes(n, seed)).boxed(),
+            "application/octet-stream",
+        );
+    }
+
+    if path.starts_with("/stream/") {
+        let n = match parse_count(path, "/stream/", MAX_HTTPBIN_STREAM_LINES) {
+            Ok(n) => n,
+            Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+        };
+        let headers = request_headers(request);
+        let origin = request_origin(request);
+        let body: String = (0..n)
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "url": request.url(),
+                    "headers": headers,
+                    "origin": origin,
+                })
+                .to_string()
+                    + "\n"
+            })
+            .collect();
+        return with_content_type(Response::from_string(body).boxed(), "application/json");
+    }
+
+    if let Some(value) = path.strip_prefix("/base64/") {
+        return match decode_base64_value(value) {
+            Some(decoded) => with_content_type(
+                Response::from_data(decoded).boxed(),
+                "text/plain; charset=utf-8",
+            ),
+            None => create_error_response(StatusCode(400), "Incorrect Base64 data").boxed(),
+        };
+    }
+
+    create_error_response(StatusCode(404), "Not Found").boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_bytes_are_seeded() {
+        assert_eq!(random_bytes(64, Some(1)), random_bytes(64, Some(1)));
+        assert_ne!(random_bytes(64, Some(1)), random_bytes(64, Some(2)));
+        assert_eq!(random_bytes(0, None).len(), 0);
+        assert_eq!(query_seed("a=1&seed=5"), Ok(Some(5)));
+        assert!(query_seed("seed=x").is_err());
+        assert_eq!(parse_count("/bytes/16", "/bytes/", 100), Ok(16));
+        assert!(parse_count("/bytes/101", "/bytes/", 100).is_err());
+    }
+
+    #[test]
+    fn test_decode_base64_value() {
+        assert_eq!(
+            decode_base64_value("aGVsbG8gbWNsb2Nrcw=="),
+            Some(b"hello mclocks".to_vec())
+        );
+        assert_eq!(decode_base64_value("aGVsbG8"), Some(b"hello".to_vec()));
+        assert_eq!(decode_base64_value("_-8"), Some(vec![0xff, 0xef]));
+        assert_eq!(decode_base64_value("!!"), None);
+    }
+}
@@ -0,0 +1,226 @@
+//! Redirect chains, targets and loops served next to `/status/{code}`.
+
+use tiny_http::{Request, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+use super::common::create_error_response;
+use super::handler_status::{create_status_response, create_status_response_with_overrides};
+use super::status_code::StatusHeaderOverrides;
+
+/// Longest chain or loop accepted by `/redirect/{n}` and `/redirect-loop/{n}`.
+pub const MAX_REDIRECT_HOPS: u32 = 100;
+const DEFAULT_REDIRECT_STATUS: u16 = 302;
+const CHAIN_PREFIXES: &[&str] = &["/redirect/", "/relative-redirect/", "/absolute-redirect/"];
+const LOOP_PATH: &str = "/redirect-loop";
+
+pub fn is_redirect_request(path: &str) -> bool {
+    path == "/redirect-to"
+        || path == LOOP_PATH
+        || path.starts_with("/redirect-loop/")
+        || CHAIN_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct RedirectOptions {
+    status: u16,
+    absolute: bool,
+    url: Option<String>,
+    hop: u32,
+}
+
+fn parse_redirect_options(query: &str) -> Result<RedirectOptions, String> {
+    let mut options = RedirectOptions {
+        status: DEFAULT_REDIRECT_STATUS,
+        absolute: false,
+        url: None,
+        hop: 1,
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = value.replace('+', " ");
+        let value = decode(&value)
+            .map(|v| v.into_owned())
+            .map_err(|_| format!("Invalid {} parameter", key))?;
+        match key {
+            "status" => match value.parse::<u16>() {
+                Ok(code) if (300..=399).contains(&code) && code != 304 => options.status = code,
+                _ => return Err("Invalid status parameter (expected a 3xx redirect)".to_string()),
+            },
+            "absolute" => match value.as_str() {
+                "true" | "1" => options.absolute = true,
+                "false" | "0" => options.absolute = false,
+                _ => return Err("Invalid absolute parameter (expected true or false)".to_string()),
+            },
+            "url" => {
+                if value.is_empty() || value.chars().any(|c| c.is_control()) {
+                    return Err("Invalid url parameter".to_string());
+                }
+                options.url = Some(value);
+            }
+            "hop" => match value.parse::<u32>() {
+                Ok(hop) if hop >= 1 => options.hop = hop,
+                _ => return Err("Invalid hop parameter".to_string()),
+            },
+            _ => {}
+        }
+    }
+    Ok(options)
+}
+
+/// Parses the `{n}` segment after `prefix`.
+fn parse_hops(path: &str, prefix: &str) -> Result<u32, String> {
+    let segment = path[prefix.len()..].split('/').next().unwrap_or("");
+    match segment.parse::<u32>() {
+        Ok(n) if n <= MAX_REDIRECT_HOPS => Ok(n),
+        Ok(_) => Err(format!(
+            "Redirect count exceeds maximum ({})",
+            MAX_REDIRECT_HOPS
+        )),
+        Err(_) => Err("Invalid redirect count".to_string()),
+    }
+}
+
+/// `query` without its `hop` parameter, so loop hops do not pile up.
+fn query_without_hop(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some("hop"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn request_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+fn build_location(request: &Request, absolute: bool, path: &str, query: &str) -> String {
+    let target = if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query)
+    };
+    if !absolute {
+        return target;
+    }
+    let scheme = if request.secure() { "https" } else { "http" };
+    let host = request_header(request, "Host").unwrap_or("localhost");
+    format!("{}://{}{}", scheme, host, target)
+}
+
+fn redirect_response(status: u16, location: String) -> ResponseBox {
+    let overrides = StatusHeaderOverrides {
+        location: Some(location),
+        ..StatusHeaderOverrides::default()
+    };
+    create_status_response_with_overrides(status, &overrides).boxed()
+}
+
+/// Serves `/redirect/{n}`, `/relative-redirect/{n}`, `/absolute-redirect/{n}`, `/redirect-to`
+/// and `/redirect-loop[/{n}]`. Chains count down to `/…/0`, which answers 200.
+pub fn handle_redirect_request(request: &Request, path: &str, query: &str) -> ResponseBox {
+    let options = match parse_redirect_options(query) {
+        Ok(options) => options,
+        Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+    };
+
+    if path == "/redirect-to" {
+        return match options.url {
+            Some(url) => redirect_response(options.status, url),
+            None => create_error_response(StatusCode(400), "Missing url parameter").boxed(),
+        };
+    }
+
+    if path == LOOP_PATH || path.starts_with("/redirect-loop/") {
+        let hops = if path == LOOP_PATH {
+            1
+        } else {
+            match parse_hops(path, "/redirect-loop/") {
+                Ok(0) => {
+                    return create_error_response(StatusCode(400), "Invalid redirect count")
+                        .boxed();
+                }
+                Ok(hops) => hops,
+                Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+            }
+        };
+        let location = if hops == 1 {
+            build_location(request, options.absolute, path, query)
+        } else {
+            let rest = query_without_hop(query);
+            let next = format!("hop={}", options.hop % hops + 1);
+            let next_query = if rest.is_empty() {
+                next
+            } else {
+                format!("{}&{}", rest, next)
+            };
+            build_location(request, options.absolute, path, &next_query)
+        };
+        return redirect_response(options.status, location);
+    }
+
+    let Some(prefix) = CHAIN_PREFIXES
+        .iter()
+        .find(|prefix| path.starts_with(*prefix))
+    else {
+        return create_error_response(StatusCode(404), "Not Found").boxed();
+    };
+    let hops = match parse_hops(path, prefix) {
+        Ok(hops) => hops,
+        Err(message) => return create_error_response(StatusCode(400), &message).boxed(),
+    };
+    if hops == 0 {
+        return create_status_response(200).boxed();
+    }
+    let absolute = match *prefix {
+        "/relative-redirect/" => false,
+        "/absolute-redirect/" => true,
+        _ => options.absolute,
+    };
+    let next_path = format!("{}{}", prefix, hops - 1);
+    redirect_response(
+        options.status,
+        build_location(request, absolute, &next_path, query),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirect_options() {
+        let options = parse_redirect_options("").unwrap();
+        assert_eq!(options.status, DEFAULT_REDIRECT_STATUS);
+        assert!(!options.absolute);
+
+        let options =
+            parse_redirect_options("url=https%3A%2F%2Fexample.test%2Fa%3Fb%3D1&status=307")
+                .unwrap();
+        assert_eq!(options.url.as_deref(), Some("https://example.test/a?b=1"));
+        assert_eq!(options.status, 307);
+
+        assert!(parse_redirect_options("status=200").is_err());
+        assert!(parse_redirect_options("status=304").is_err());
+        assert!(parse_redirect_options("absolute=maybe").is_err());
+        assert!(parse_redirect_options("url=a%0d%0aSet-Cookie:x").is_err());
+        assert!(parse_redirect_options("hop=0").is_err());
+    }
+
+    #[test]
+    fn test_parse_hops_and_query_without_hop() {
+        assert_eq!(parse_hops("/redirect/3", "/redirect/"), Ok(3));
+        assert_eq!(parse_hops("/redirect/0/extra", "/redirect/"), Ok(0));
+        assert!(parse_hops("/redirect/abc", "/redirect/").is_err());
+        assert!(parse_hops("/redirect/101", "/redirect/").is_err());
+
+        assert_eq!(
+            query_without_hop("status=307&hop=2&absolute=1"),
+            "status=307&absolute=1"
+        );
+        assert_eq!(query_without_hop("hop=3"), "");
+    }
+}
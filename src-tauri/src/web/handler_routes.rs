@@ -0,0 +1,288 @@
+//! `web.routes`: fake endpoints declared in config.json, with `:param` path segments and a
+//! `{{name}}` body template.
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+use super::common::{create_error_response, get_web_content_type};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteSegment {
+    Literal(String),
+    /// `:name`, matching one non-empty path segment.
+    Param(String),
+    /// A trailing `*`, matching the rest of the path (possibly empty).
+    Rest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteBody {
+    Inline(String),
+    /// Read on every request, so edits to the file show up without reloading the config.
+    File(PathBuf),
+}
+
+/// A resolved `web.routes` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRoute {
+    /// Upper-case method, or `*` for any. `GET` routes also answer `HEAD`.
+    pub method: String,
+    pub segments: Vec<RouteSegment>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RouteBody,
+    pub delay: Duration,
+}
+
+/// Parses a route path such as `/api/users/:id` or `/files/*`.
+pub fn parse_route_path(path: &str) -> Result<Vec<RouteSegment>, String> {
+    let Some(rest) = path.trim().strip_prefix('/') else {
+        return Err(format!("path must start with '/': {:?}", path));
+    };
+    let parts: Vec<&str> = rest
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut segments = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let segment = if *part == "*" {
+            if i + 1 != parts.len() {
+                return Err(format!("'*' must be the last segment: {:?}", path));
+            }
+            RouteSegment::Rest
+        } else if let Some(name) = part.strip_prefix(':') {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid parameter name {:?} in {:?}", part, path));
+            }
+            RouteSegment::Param(name.to_string())
+        } else {
+            RouteSegment::Literal(part.to_string())
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+impl MockRoute {
+    fn accepts_method(&self, method: &Method) -> bool {
+        let method = method.as_str();
+        self.method == "*"
+            || self.method.eq_ignore_ascii_case(method)
+            || (self.method == "GET" && method.eq_ignore_ascii_case("HEAD"))
+    }
+
+    /// Path parameters when `path` matches, keyed by name (`*` for the rest of the path).
+    fn match_path(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = path
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        let mut params = HashMap::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                RouteSegment::Rest => {
+                    params.insert("*".to_string(), parts.get(i..)?.join("/"));
+                    return Some(params);
+                }
+                RouteSegment::Literal(literal) => {
+                    if parts.get(i) != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                RouteSegment::Param(name) => {
+                    params.insert(name.clone(), (*parts.get(i)?).to_string());
+                }
+            }
+        }
+        (parts.len() == self.segments.len()).then_some(params)
+    }
+}
+
+fn decode_component(value: &str) -> String {
+    let value = value.replace('+', " ");
+    decode(&value).map(|v| v.into_owned()).unwrap_or(value)
+}
+
+/// Replaces `{{name}}` with a path parameter and `{{query.name}}` with a query value.
+/// Unknown names become empty strings.
+pub fn render_template(
+    template: &str,
+    params: &HashMap<String, String>,
+    query: &HashMap<String, String>,
+) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = match name.strip_prefix("query.") {
+            Some(key) => query.get(key),
+            None => params.get(name),
+        };
+        rendered.push_str(value.map(String::as_str).unwrap_or(""));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn default_content_type(route: &MockRoute, body: &str) -> String {
+    match &route.body {
+        RouteBody::File(path) => {
+            let content_type = get_web_content_type(path);
+            if content_type.starts_with("text/") || content_type == "application/json" {
+                format!("{}; charset=utf-8", content_type)
+            } else {
+                content_type
+            }
+        }
+        RouteBody::Inline(_) if serde_json::from_str::<serde_json::Value>(body).is_ok() => {
+            "application/json".to_string()
+        }
+        RouteBody::Inline(_) => "text/plain; charset=utf-8".to_string(),
+    }
+}
+
+/// Answers `request` from the first route matching its method and path.
+pub fn handle_route_request(
+    request: &Request,
+    path: &str,
+    query: &str,
+    routes: &[MockRoute],
+) -> Option<ResponseBox> {
+    let decoded_path = decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    let (route, params) = routes.iter().find_map(|route| {
+        if !route.accepts_method(request.method()) {
+            return None;
+        }
+        route
+            .match_path(&decoded_path)
+            .map(|params| (route, params))
+    })?;
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(name), decode_component(value))
+        })
+        .collect();
+
+    if !route.delay.is_zero() {
+        thread::sleep(route.delay);
+    }
+    let template = match &route.body {
+        RouteBody::Inline(body) => body.clone(),
+        RouteBody::File(file) => match fs::read_to_string(file) {
+            Ok(body) => body,
+            Err(e) => {
+                return Some(
+                    create_error_response(
+                        StatusCode(500),
+                        &format!("Failed to read bodyFile {}: {}", file.display(), e),
+                    )
+                    .boxed(),
+                );
+            }
+        },
+    };
+    let body = render_template(&template, &params, &query);
+    let mut headers: Vec<(String, String)> = route
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), render_template(value, &params, &query)))
+        .collect();
+    if let Some((name, _)) = headers
+        .iter()
+        .find(|(_, value)| value.chars().any(|c| c.is_control()))
+    {
+        return Some(
+            create_error_response(
+                StatusCode(400),
+                &format!("Invalid value for header {}", name),
+            )
+            .boxed(),
+        );
+    }
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+    {
+        headers.push((
+            "Content-Type".to_string(),
+            default_content_type(route, &body),
+        ));
+    }
+    let mut response = Response::from_string(body)
+        .with_status_code(StatusCode(route.status))
+        .boxed();
+    for (name, value) in headers {
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_path() {
+        let route = MockRoute {
+            method: "GET".to_string(),
+            segments: parse_route_path("/api/users/:id/files/*").expect("parse"),
+            status: 200,
+            headers: Vec::new(),
+            body: RouteBody::Inline(String::new()),
+            delay: Duration::ZERO,
+        };
+        let params = route
+            .match_path("/api/users/42/files/a/b.txt")
+            .expect("match");
+        assert_eq!(params["id"], "42");
+        assert_eq!(params["*"], "a/b.txt");
+        assert_eq!(
+            route.match_path("/api/users/42/files").expect("match")["*"],
+            ""
+        );
+        assert!(route.match_path("/api/users/42").is_none());
+        assert!(route.match_path("/api/groups/42/files/x").is_none());
+        assert!(route.accepts_method(&Method::Head));
+        assert!(!route.accepts_method(&Method::Post));
+
+        assert!(parse_route_path("api/users").is_err());
+        assert!(parse_route_path("/a/*/b").is_err());
+        assert!(parse_route_path("/a/:").is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let params = HashMap::from([("id".to_string(), "42".to_string())]);
+        let query = HashMap::from([("lang".to_string(), "ja".to_string())]);
+        assert_eq!(
+            render_template(
+                r#"{"id": "{{id}}", "lang": "{{ query.lang }}", "x": "{{missing}}"}"#,
+                &params,
+                &query
+            ),
+            r#"{"id": "42", "lang": "ja", "x": ""}"#
+        );
+        assert_eq!(
+            render_template("open {{ only", &params, &query),
+            "open {{ only"
+        );
+    }
+}
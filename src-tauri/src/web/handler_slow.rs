@@ -1,10 +1,22 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 use std::time::Duration;
-use tiny_http::{Response, StatusCode};
+use tiny_http::{HTTPVersion, Header, Method, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
 
 use super::common::create_error_response;
 
-const MAX_SLOW_SECONDS: u64 = 901; // 15 minutes + 1 second
+pub const MAX_SLOW_SECONDS: u64 = 901; // 15 minutes + 1 second
+pub const DRIP_PATH: &str = "/slow/drip";
+const MAX_DRIP_BYTES: u64 = 100 * 1024 * 1024;
+const MAX_DRIP_CHUNK_BYTES: usize = 1024 * 1024;
+const MAX_DRIP_JITTER_MS: u64 = 60_000;
+/// How long a `/slow/drip?stall=` connection is held open before it is dropped mid-body.
+const DRIP_STALL: Duration = Duration::from_secs(MAX_SLOW_SECONDS);
+/// Stalled drip connections held at once; more are dropped as soon as they stall.
+const MAX_STALLED_DRIPS: usize = 32;
+const DRIP_PATTERN: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ+/\n";
 
 pub fn handle_slow_request(request: &tiny_http::Request) -> Response<std::io::Cursor<Vec<u8>>> {
     let url = request.url();
@@ -40,3 +52,216 @@ pub fn handle_slow_request(request: &tiny_http::Request) -> Response<std::io::Cu
     thread::sleep(Duration::from_secs(seconds));
     Response::from_string("OK").with_status_code(StatusCode(200))
 }
+
+/// `/slow/drip` options: `bytes` sent in `chunk`-sized pieces spread over `duration` seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DripSettings {
+    pub bytes: u64,
+    pub chunk: usize,
+    pub duration: Duration,
+    /// Seconds before the status line, as with `/slow/{seconds}`.
+    pub delay: Duration,
+    /// Each pause is moved randomly by up to this much either way.
+    pub jitter: Duration,
+    /// Stop sending after this many bytes and hold the connection open.
+    pub stall_after: Option<u64>,
+}
+
+fn parse_drip_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} parameter", name))
+}
+
+/// Reads `/slow/drip?bytes=&duration=&chunk=&delay=&jitter=&stall=` from the request URL.
+pub fn parse_drip_settings(url: &str) -> Result<DripSettings, String> {
+    let mut settings = DripSettings {
+        bytes: 1000,
+        chunk: 100,
+        duration: Duration::from_secs(10),
+        delay: Duration::ZERO,
+        jitter: Duration::ZERO,
+        stall_after: None,
+    };
+    let query = url.split_once('?').map(|(_, q)| q).unwrap_or("");
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value)
+            .map(|v| v.into_owned())
+            .unwrap_or_else(|_| value.to_string());
+        match key {
+            "bytes" => settings.bytes = parse_drip_number(key, &value)?,
+            "chunk" => settings.chunk = parse_drip_number(key, &value)?,
+            "duration" => settings.duration = Duration::from_secs(parse_drip_number(key, &value)?),
+            "delay" => settings.delay = Duration::from_secs(parse_drip_number(key, &value)?),
+            "jitter" => settings.jitter = Duration::from_millis(parse_drip_number(key, &value)?),
+            "stall" => settings.stall_after = Some(parse_drip_number(key, &value)?),
+            _ => {}
+        }
+    }
+    if settings.bytes > MAX_DRIP_BYTES {
+        return Err(format!("bytes exceeds maximum ({})", MAX_DRIP_BYTES));
+    }
+    if settings.chunk == 0 || settings.chunk > MAX_DRIP_CHUNK_BYTES {
+        return Err(format!(
+            "chunk must be between 1 and {}",
+            MAX_DRIP_CHUNK_BYTES
+        ));
+    }
+    if settings.duration.as_secs() > MAX_SLOW_SECONDS || settings.delay.as_secs() > MAX_SLOW_SECONDS
+    {
+        return Err(format!(
+            "duration and delay must not exceed {} seconds",
+            MAX_SLOW_SECONDS
+        ));
+    }
+    if settings.jitter.as_millis() > MAX_DRIP_JITTER_MS as u128 {
+        return Err(format!(
+            "jitter exceeds maximum ({} ms)",
+            MAX_DRIP_JITTER_MS
+        ));
+    }
+    Ok(settings)
+}
+
+/// The head of a `/slow/drip` response. Its body is written by [`stream_drip_response`], since
+/// tiny_http buffers response bodies and would send the drip in bursts.
+pub fn create_drip_head() -> ResponseBox {
+    let mut response = Response::empty(StatusCode(200)).boxed();
+    for (name, value) in [
+        ("Content-Type", "text/plain; charset=utf-8"),
+        ("Cache-Control", "no-store"),
+    ] {
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+fn drip_pause(settings: &DripSettings, chunks: u64) -> Duration {
+    let interval = settings.duration / chunks.max(1).min(u32::MAX as u64) as u32;
+    if settings.jitter.is_zero() {
+        return interval;
+    }
+    let jitter_ms = settings.jitter.as_millis() as i64;
+    let offset = rand::random_range(-jitter_ms..=jitter_ms);
+    let pause_ms = interval.as_millis() as i64 + offset;
+    Duration::from_millis(pause_ms.max(0) as u64)
+}
+
+/// Writes `head` and then the drip body straight to the connection, flushing every chunk.
+/// Returns the number of body bytes sent.
+pub fn stream_drip_response(
+    request: Request,
+    head: ResponseBox,
+    settings: &DripSettings,
+) -> io::Result<u64> {
+    if !settings.delay.is_zero() {
+        thread::sleep(settings.delay);
+    }
+    let chunked = *request.http_version() >= HTTPVersion(1, 1);
+    let send_body = *request.method() != Method::Head;
+    let mut writer = request.into_writer();
+
+    let status = head.status_code();
+    let mut head_text = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.0,
+        status.default_reason_phrase()
+    );
+    for header in head.headers() {
+        head_text.push_str(&format!("{}: {}\r\n", header.field, header.value));
+    }
+    if chunked {
+        head_text.push_str("Transfer-Encoding: chunked\r\n");
+    }
+    head_text.push_str("Connection: close\r\n\r\n");
+    writer.write_all(head_text.as_bytes())?;
+    writer.flush()?;
+    if !send_body {
+        return Ok(0);
+    }
+
+    let chunks = settings.bytes.div_ceil(settings.chunk as u64);
+    let mut sent = 0u64;
+    while sent < settings.bytes {
+        if settings.stall_after.is_some_and(|stall| sent >= stall) {
+            hold_stalled_connection(writer);
+            return Ok(sent);
+        }
+        thread::sleep(drip_pause(settings, chunks));
+        let mut len = (settings.bytes - sent).min(settings.chunk as u64);
+        if let Some(stall) = settings.stall_after {
+            len = len.min(stall - sent);
+        }
+        let data: Vec<u8> = (sent..sent + len)
+            .map(|i| DRIP_PATTERN[(i % DRIP_PATTERN.len() as u64) as usize])
+            .collect();
+        if chunked {
+            writer.write_all(format!("{:x}\r\n", data.len()).as_bytes())?;
+            writer.write_all(&data)?;
+            writer.write_all(b"\r\n")?;
+        } else {
+            writer.write_all(&data)?;
+        }
+        writer.flush()?;
+        sent += len;
+    }
+    if chunked {
+        writer.write_all(b"0\r\n\r\n")?;
+    }
+    writer.flush()?;
+    Ok(sent)
+}
+
+/// Keeps a stalled drip connection open for [`DRIP_STALL`] on its own thread, so the stall
+/// does not hold a listener worker, then drops it mid-body.
+fn hold_stalled_connection(writer: Box<dyn Write + Send>) {
+    static STALLED: AtomicUsize = AtomicUsize::new(0);
+    if STALLED.fetch_add(1, Ordering::SeqCst) >= MAX_STALLED_DRIPS {
+        STALLED.fetch_sub(1, Ordering::SeqCst);
+        return;
+    }
+    thread::spawn(move || {
+        thread::sleep(DRIP_STALL);
+        drop(writer);
+        STALLED.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_drip_settings() {
+        let settings = parse_drip_settings(
+            "/slow/drip?bytes=10000&duration=30&chunk=100&jitter=250&stall=500",
+        )
+        .expect("parse drip");
+        assert_eq!(settings.bytes, 10000);
+        assert_eq!(settings.chunk, 100);
+        assert_eq!(settings.duration, Duration::from_secs(30));
+        assert_eq!(settings.jitter, Duration::from_millis(250));
+        assert_eq!(settings.stall_after, Some(500));
+        assert_eq!(
+            parse_drip_settings("/slow/drip").expect("defaults").bytes,
+            1000
+        );
+        assert!(parse_drip_settings("/slow/drip?chunk=0").is_err());
+        assert!(parse_drip_settings("/slow/drip?duration=1000").is_err());
+        assert!(parse_drip_settings("/slow/drip?bytes=x").is_err());
+    }
+
+    #[test]
+    fn test_drip_pause_stays_within_jitter() {
+        let mut settings = parse_drip_settings("/slow/drip?duration=10").expect("parse drip");
+        assert_eq!(drip_pause(&settings, 10), Duration::from_secs(1));
+        settings.jitter = Duration::from_millis(200);
+        for _ in 0..50 {
+            let pause = drip_pause(&settings, 10);
+            assert!(pause >= Duration::from_millis(800) && pause <= Duration::from_millis(1200));
+        }
+    }
+}
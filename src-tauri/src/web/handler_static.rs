@@ -5,11 +5,10 @@ use chardetng::EncodingDetector;
 use encoding_rs::{Encoding, UTF_8};
 use std::fmt::Write;
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tiny_http::{Header, Response, StatusCode};
+use tiny_http::{Header, Method, Response, ResponseBox, StatusCode};
 use urlencoding::{decode, encode};
 
 #[path = "handler_static_source/ini.rs"]
@@ -31,17 +30,36 @@ mod xml;
 #[path = "handler_static_source/yaml.rs"]
 mod yaml;
 
+pub use self::json::create_json_value_response;
 use self::md::{create_markdown_response, is_markdown_file};
 use self::structured_dispatcher::{create_structured_data_response, is_structured_data_file};
 use self::template_common::ContentMode;
 use super::common::{create_error_response, format_display_path, get_web_content_type};
+use super::handler_auth::{handle_auth_request, is_auth_request};
+use super::handler_cache::{handle_cache_request, is_cache_request};
+use super::handler_cookies::{handle_cookies_request, is_cookies_request};
 use super::handler_dump::handle_dump_request;
 use super::handler_editor::handle_editor_request;
+use super::handler_fault::{Fault, handle_fault_request};
+use super::handler_httpbin::{handle_httpbin_request, is_httpbin_request};
 use super::handler_local_preview::{is_preview_route_request, try_handle_local_preview_request};
+use super::handler_redirect::{handle_redirect_request, is_redirect_request};
 use super::handler_resource_meta::{handle_resource_meta_request, is_resource_meta_request};
-use super::handler_slow::handle_slow_request;
+use super::handler_routes::handle_route_request;
+use super::handler_slow::{
+    DRIP_PATH, DripSettings, create_drip_head, handle_slow_request, parse_drip_settings,
+};
 use super::handler_status::handle_status_request;
-use crate::web_server::WebMarkdownHighlightConfig;
+use super::handler_status_random::{
+    RANDOM_STATUS_COUNTERS_PATH, RANDOM_STATUS_PATH, handle_random_status_counters_request,
+    handle_random_status_request,
+};
+use super::http_conditional::{
+    ConditionalRequest, FileValidators, RangeOutcome, is_not_modified, resolve_range,
+};
+use crate::web_server::{
+    WebListenerSettings, WebMarkdownHighlightConfig, WebMount, WebMountMode, WebRootOptions,
+};
 
 const DIRECTORY_LISTING_TEMPLATE: &str = r##"<!DOCTYPE html>
 <html>
@@ -604,10 +622,11 @@ fn encode_path_segment_for_href(segment: &str) -> String {
     encoded.replace("%2E", ".").replace("%2e", ".")
 }
 
-fn parse_content_mode(url: &str) -> ContentMode {
+/// The `?mode=` of `url`, or `None` when the query does not set one.
+fn parse_content_mode(url: &str) -> Option<ContentMode> {
     let query = match url.split('?').nth(1) {
         Some(q) => q.split('#').next().unwrap_or(q),
-        None => return ContentMode::Content,
+        None => return None,
     };
     for pair in query.split('&') {
         if pair.is_empty() {
@@ -620,14 +639,54 @@ fn parse_content_mode(url: &str) -> ContentMode {
             continue;
         }
         let v = value.to_ascii_lowercase();
-        return match v.as_str() {
+        return Some(match v.as_str() {
             "raw" => ContentMode::Raw,
             "source" => ContentMode::Source,
             "content" => ContentMode::Content,
             _ => ContentMode::Content,
-        };
+        });
+    }
+    None
+}
+
+fn mount_content_mode(mode: WebMountMode) -> ContentMode {
+    match mode {
+        WebMountMode::Content => ContentMode::Content,
+        WebMountMode::Source => ContentMode::Source,
+        WebMountMode::Raw => ContentMode::Raw,
     }
-    ContentMode::Content
+}
+
+/// Finds the longest `web.mounts` prefix matching `path` and returns it with the rest of the path,
+/// which stays percent-encoded like any other request path.
+fn resolve_mount<'m>(path: &str, mounts: &'m [WebMount]) -> Option<(&'m WebMount, String)> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    mounts
+        .iter()
+        .filter_map(|mount| {
+            let prefix_segments: Vec<&str> =
+                mount.prefix.trim_start_matches('/').split('/').collect();
+            if segments.len() < prefix_segments.len() {
+                return None;
+            }
+            let matches = prefix_segments
+                .iter()
+                .zip(&segments)
+                .all(|(prefix, segment)| decode(segment).is_ok_and(|d| d == *prefix));
+            if !matches {
+                return None;
+            }
+            let rest = segments[prefix_segments.len()..].join("/");
+            Some((mount, format!("/{}", rest)))
+        })
+        .max_by_key(|(mount, _)| mount.prefix.len())
+}
+
+/// True when a segment of `path` names a dot-file. The `.resource-meta` endpoint is not a file.
+fn has_hidden_segment(path: &str) -> bool {
+    let path = path.strip_suffix("/.resource-meta").unwrap_or(path);
+    path.split('/')
+        .any(|segment| decode(segment).is_ok_and(|d| d.starts_with('.')))
 }
 
 /// `tiny_http::Request::url()` may be origin-form (`/path?…`) or absolute-form (`http://host:port/path?…`).
@@ -735,6 +794,7 @@ fn create_directory_listing(
     url_path: &str,
     url_query: &str,
     markdown_highlight: Option<&WebMarkdownHighlightConfig>,
+    show_hidden: bool,
 ) -> Response<std::io::Cursor<Vec<u8>>> {
     let directory_mode = ContentMode::Source;
     // Decode URL path for display (each segment separately)
@@ -780,6 +840,9 @@ fn create_directory_listing(
                 if let Ok(entry) = entry {
                     let file_name = entry.file_name();
                     if let Some(name) = file_name.to_str() {
+                        if !show_hidden && name.starts_with('.') {
+                            continue;
+                        }
                         let metadata = entry.metadata();
                         if let Ok(meta) = metadata {
                             if meta.is_dir() {
@@ -901,9 +964,12 @@ fn detect_encoding(content: &[u8]) -> &'static Encoding {
         return encoding_rs::UTF_16BE;
     }
 
-    // Try UTF-8 decoding first (fast path for common case)
-    if std::str::from_utf8(content).is_ok() {
-        return UTF_8;
+    // Try UTF-8 decoding first (fast path for common case).
+    // A sniffed prefix may end in the middle of a multi-byte sequence, which still counts as UTF-8.
+    match std::str::from_utf8(content) {
+        Ok(_) => return UTF_8,
+        Err(e) if e.error_len().is_none() => return UTF_8,
+        Err(_) => {}
     }
 
     // Use chardetng to detect encoding for non-UTF-8 content
@@ -1014,6 +1080,7 @@ fn create_source_text_response(
     parent_directory_href: &str,
     markdown_highlight: Option<&WebMarkdownHighlightConfig>,
     mode_switch_html: &str,
+    status: &str,
 ) -> Response<std::io::Cursor<Vec<u8>>> {
     let page_title = file_path
         .file_name()
@@ -1022,11 +1089,8 @@ fn create_source_text_response(
         .unwrap_or_else(|| "Source".to_string());
     let absolute_path = format_display_path(file_path);
     let language_class = sanitize_language_class(file_path);
-    let summary_items = render_source_summary_items(
-        source_size_bytes,
-        get_last_modified_ms(file_path),
-        "Syntax Highlight",
-    );
+    let summary_items =
+        render_source_summary_items(source_size_bytes, get_last_modified_ms(file_path), status);
     let (main_css_link, main_js_script, highlight_css_link, highlight_js_script) =
         match markdown_highlight {
             Some(cfg) => (
@@ -1948,7 +2012,10 @@ fn render_source_media_summary_items(
         | SourceMediaKind::VideoM4v
         | SourceMediaKind::VideoMov
         | SourceMediaKind::VideoWebm
-        | SourceMediaKind::VideoOgv => {
+        | SourceMediaKind::VideoOgv
+            // Durations are derived from the whole file; skip them when only a prefix was read.
+            if content.len() == size_bytes =>
+        {
             let duration = match media_kind {
                 SourceMediaKind::AudioMp3 => estimate_mp3_duration_seconds(content),
                 SourceMediaKind::AudioM4a => parse_mp4_duration_seconds(content),
@@ -1970,6 +2037,7 @@ fn render_source_media_summary_items(
                 fields.push(("Duration", duration_text));
             }
         }
+        _ => {}
     }
     for (label, value) in fields {
         html.push_str("<li><span class=\"label\">");
@@ -2068,6 +2136,43 @@ fn make_attachment_disposition(file_path: &Path) -> String {
     format!("attachment; filename=\"{}\"", filename)
 }
 
+/// Bytes read from the head of a file to sniff binary content and text encoding.
+const CONTENT_SNIFF_BYTES: u64 = 64 * 1024;
+/// Served for directories when `index` is enabled for the root.
+const INDEX_FILE_NAME: &str = "index.html";
+
+fn read_file_prefix(file_path: &Path, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    fs::File::open(file_path)?
+        .take(limit)
+        .read_to_end(&mut content)?;
+    Ok(content)
+}
+
+fn create_source_too_large_response(
+    file_path: &Path,
+    file_len: u64,
+    source_max_bytes: u64,
+    parent_directory_href: &str,
+    markdown_highlight: Option<&WebMarkdownHighlightConfig>,
+    mode_switch_html: &str,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let notice = format!(
+        "This file is too large for the source view ({} > {} limit).\nOpen it in raw or content mode instead.",
+        human_bytes(file_len as usize),
+        human_bytes(source_max_bytes as usize)
+    );
+    create_source_text_response(
+        file_path,
+        &notice,
+        file_len as usize,
+        parent_directory_href,
+        markdown_highlight,
+        mode_switch_html,
+        "Too Large",
+    )
+}
+
 fn create_file_response(
     file_path: &PathBuf,
     allow_html_in_md: bool,
@@ -2077,138 +2182,251 @@ fn create_file_response(
     url_path: &str,
     url_query: &str,
     markdown_live_reload_ws_port: Option<u16>,
-) -> Response<std::io::Cursor<Vec<u8>>> {
-    match fs::read(file_path) {
-        Ok(content) => {
-            let mode_switch_html = template_common::build_mode_switch_html(
+    source_max_bytes: u64,
+    conditional: &ConditionalRequest,
+) -> ResponseBox {
+    let metadata = match fs::metadata(file_path) {
+        Ok(metadata) => metadata,
+        Err(_) => return create_error_response(StatusCode(500), "Internal Server Error").boxed(),
+    };
+    let file_len = metadata.len();
+    // Only the source views need the whole file; everything else works from a bounded prefix.
+    let fits_source_view = file_len <= source_max_bytes;
+    let read_limit = if content_mode == ContentMode::Source && fits_source_view {
+        file_len
+    } else {
+        CONTENT_SNIFF_BYTES
+    };
+    let content = match read_file_prefix(file_path, read_limit) {
+        Ok(content) => content,
+        Err(_) => return create_error_response(StatusCode(500), "Internal Server Error").boxed(),
+    };
+    let mode_switch_html =
+        template_common::build_mode_switch_html(url_path, url_query, content_mode, "content-mode");
+    let is_source_text_view = content_mode == ContentMode::Source
+        && (is_markdown_file(file_path.as_path()) || is_structured_data_file(file_path.as_path()));
+    if is_source_text_view && !fits_source_view {
+        let parent_directory_href = resolve_source_parent_directory_href(
+            url_path,
+            url_query,
+            content_mode,
+            file_path.as_path(),
+        );
+        return create_source_too_large_response(
+            file_path.as_path(),
+            file_len,
+            source_max_bytes,
+            &parent_directory_href,
+            markdown_highlight,
+            &mode_switch_html,
+        )
+        .boxed();
+    }
+    if is_markdown_file(file_path.as_path()) && content_mode == ContentMode::Source {
+        let encoding = detect_encoding(&content);
+        let (decoded, _, _) = encoding.decode(&content);
+        let parent_directory_href = resolve_source_parent_directory_href(
+            url_path,
+            url_query,
+            content_mode,
+            file_path.as_path(),
+        );
+        let live_reload = markdown_live_reload_ws_port.and_then(|port| {
+            super::markdown_live_reload::register_markdown_live_reload_session(file_path.as_path())
+                .map(|token| (port, token))
+        });
+        return create_markdown_response(
+            file_path.as_path(),
+            &decoded,
+            content.len(),
+            &parent_directory_href,
+            allow_html_in_md,
+            markdown_open_external_link_in_new_tab,
+            markdown_highlight,
+            &mode_switch_html,
+            live_reload.as_ref(),
+        )
+        .boxed();
+    }
+    if is_structured_data_file(file_path.as_path()) && content_mode == ContentMode::Source {
+        let encoding = detect_encoding(&content);
+        let (decoded, _, _) = encoding.decode(&content);
+        let parent_directory_href = resolve_source_parent_directory_href(
+            url_path,
+            url_query,
+            content_mode,
+            file_path.as_path(),
+        );
+        return create_structured_data_response(
+            file_path.as_path(),
+            &decoded,
+            &parent_directory_href,
+            markdown_highlight,
+            &mode_switch_html,
+            content.len(),
+        )
+        .boxed();
+    }
+    let (base_content_type, should_download) =
+        resolve_content_type_and_download(file_path.as_path(), &content);
+    let is_binary_content = is_probably_binary(&content);
+    if content_mode == ContentMode::Source && !should_download {
+        if let Some(media_kind) = detect_source_media_kind(
+            file_path.as_path(),
+            &base_content_type,
+            &content,
+            is_binary_content,
+        ) {
+            let parent_directory_href = resolve_source_parent_directory_href(
                 url_path,
                 url_query,
                 content_mode,
-                "content-mode",
+                file_path.as_path(),
             );
-            if is_markdown_file(file_path.as_path()) && content_mode == ContentMode::Source {
-                let encoding = detect_encoding(&content);
-                let (decoded, _, _) = encoding.decode(&content);
-                let parent_directory_href = resolve_source_parent_directory_href(
-                    url_path,
-                    url_query,
-                    content_mode,
-                    file_path.as_path(),
-                );
-                let live_reload = markdown_live_reload_ws_port.and_then(|port| {
-                    super::markdown_live_reload::register_markdown_live_reload_session(
-                        file_path.as_path(),
-                    )
-                    .map(|token| (port, token))
-                });
-                return create_markdown_response(
-                    file_path.as_path(),
-                    &decoded,
-                    content.len(),
-                    &parent_directory_href,
-                    allow_html_in_md,
-                    markdown_open_external_link_in_new_tab,
-                    markdown_highlight,
-                    &mode_switch_html,
-                    live_reload.as_ref(),
-                );
-            }
-            if is_structured_data_file(file_path.as_path()) && content_mode == ContentMode::Source {
-                let encoding = detect_encoding(&content);
-                let (decoded, _, _) = encoding.decode(&content);
-                let parent_directory_href = resolve_source_parent_directory_href(
-                    url_path,
-                    url_query,
-                    content_mode,
-                    file_path.as_path(),
-                );
-                return create_structured_data_response(
-                    file_path.as_path(),
-                    &decoded,
-                    &parent_directory_href,
-                    markdown_highlight,
-                    &mode_switch_html,
-                    content.len(),
-                );
-            }
-            let (base_content_type, should_download) =
-                resolve_content_type_and_download(file_path.as_path(), &content);
-            let is_binary_content = is_probably_binary(&content);
-            if content_mode == ContentMode::Source && !should_download {
-                if let Some(media_kind) = detect_source_media_kind(
-                    file_path.as_path(),
-                    &base_content_type,
-                    &content,
-                    is_binary_content,
-                ) {
-                    let parent_directory_href = resolve_source_parent_directory_href(
-                        url_path,
-                        url_query,
-                        content_mode,
-                        file_path.as_path(),
-                    );
-                    let raw_href =
-                        template_common::build_mode_href(url_path, url_query, ContentMode::Raw);
-                    return create_source_media_response(
-                        file_path.as_path(),
-                        &content,
-                        content.len(),
-                        &parent_directory_href,
-                        markdown_highlight,
-                        &mode_switch_html,
-                        media_kind,
-                        &raw_href,
-                    );
-                }
-            }
-            if content_mode == ContentMode::Source && !should_download && !is_binary_content {
-                let encoding = detect_encoding(&content);
-                let (decoded, _, _) = encoding.decode(&content);
-                let parent_directory_href = resolve_source_parent_directory_href(
-                    url_path,
-                    url_query,
-                    content_mode,
-                    file_path.as_path(),
-                );
-                return create_source_text_response(
-                    file_path.as_path(),
-                    &decoded,
-                    content.len(),
-                    &parent_directory_href,
-                    markdown_highlight,
-                    &mode_switch_html,
+            let raw_href = template_common::build_mode_href(url_path, url_query, ContentMode::Raw);
+            return create_source_media_response(
+                file_path.as_path(),
+                &content,
+                file_len as usize,
+                &parent_directory_href,
+                markdown_highlight,
+                &mode_switch_html,
+                media_kind,
+                &raw_href,
+            )
+            .boxed();
+        }
+    }
+    if content_mode == ContentMode::Source && !should_download && !is_binary_content {
+        let parent_directory_href = resolve_source_parent_directory_href(
+            url_path,
+            url_query,
+            content_mode,
+            file_path.as_path(),
+        );
+        if !fits_source_view {
+            return create_source_too_large_response(
+                file_path.as_path(),
+                file_len,
+                source_max_bytes,
+                &parent_directory_href,
+                markdown_highlight,
+                &mode_switch_html,
+            )
+            .boxed();
+        }
+        let encoding = detect_encoding(&content);
+        let (decoded, _, _) = encoding.decode(&content);
+        return create_source_text_response(
+            file_path.as_path(),
+            &decoded,
+            content.len(),
+            &parent_directory_href,
+            markdown_highlight,
+            &mode_switch_html,
+            "Syntax Highlight",
+        )
+        .boxed();
+    }
+    let content_type = if content_mode == ContentMode::Raw && !is_binary_content {
+        let encoding = detect_encoding(&content);
+        let charset = encoding.name();
+        format!("text/plain; charset={}", charset)
+    } else if is_text_type(&base_content_type) {
+        let encoding = detect_encoding(&content);
+        let charset = encoding.name();
+        format!("{}; charset={}", base_content_type, charset)
+    } else {
+        base_content_type
+    };
+    create_raw_file_response(
+        file_path.as_path(),
+        &metadata,
+        &content_type,
+        should_download,
+        conditional,
+    )
+}
+
+fn with_header_if_valid<R: Read>(response: Response<R>, name: &str, value: &str) -> Response<R> {
+    match Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(_) => response,
+    }
+}
+
+/// Streams the file from disk with a known `Content-Length`, honouring
+/// `If-None-Match`/`If-Modified-Since` (304) and a single `Range` (206/416).
+fn create_raw_file_response(
+    file_path: &Path,
+    metadata: &fs::Metadata,
+    content_type: &str,
+    should_download: bool,
+    conditional: &ConditionalRequest,
+) -> ResponseBox {
+    let validators = FileValidators::from_metadata(metadata);
+    let with_validators = |mut response: ResponseBox| {
+        response = with_header_if_valid(response, "ETag", &validators.etag);
+        if let Some(last_modified) = validators.last_modified.as_deref() {
+            response = with_header_if_valid(response, "Last-Modified", last_modified);
+        }
+        response
+    };
+    if is_not_modified(conditional, &validators) {
+        return with_validators(
+            Response::from_data(Vec::new())
+                .with_status_code(StatusCode(304))
+                .boxed(),
+        );
+    }
+    let total_len = metadata.len();
+    let (start, body_len, status, content_range) =
+        match resolve_range(conditional, &validators, total_len) {
+            RangeOutcome::Full => (0, total_len, StatusCode(200), None),
+            RangeOutcome::Partial(start, end) => (
+                start,
+                end - start + 1,
+                StatusCode(206),
+                Some(format!("bytes {}-{}/{}", start, end, total_len)),
+            ),
+            RangeOutcome::Unsatisfiable => {
+                let response = create_error_response(StatusCode(416), "Range Not Satisfiable");
+                return with_header_if_valid(
+                    with_validators(response.boxed()),
+                    "Content-Range",
+                    &format!("bytes */{}", total_len),
                 );
             }
-            let content_type = if content_mode == ContentMode::Raw && !is_binary_content {
-                let encoding = detect_encoding(&content);
-                let charset = encoding.name();
-                format!("text/plain; charset={}", charset)
-            } else if is_text_type(&base_content_type) {
-                let encoding = detect_encoding(&content);
-                let charset = encoding.name();
-                format!("{}; charset={}", base_content_type, charset)
-            } else {
-                base_content_type
-            };
-            if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
-                let mut response = Response::from_data(content)
-                    .with_header(header)
-                    .with_status_code(StatusCode(200));
-                if should_download {
-                    if let Ok(disposition) = Header::from_bytes(
-                        &b"Content-Disposition"[..],
-                        make_attachment_disposition(file_path.as_path()).as_bytes(),
-                    ) {
-                        response = response.with_header(disposition);
-                    }
-                }
-                response
-            } else {
-                Response::from_data(content).with_status_code(StatusCode(200))
-            }
-        }
-        Err(_) => create_error_response(StatusCode(500), "Internal Server Error"),
+        };
+    let mut file = match fs::File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return create_error_response(StatusCode(500), "Internal Server Error").boxed(),
+    };
+    if start > 0 && file.seek(SeekFrom::Start(start)).is_err() {
+        return create_error_response(StatusCode(500), "Internal Server Error").boxed();
+    }
+    let body: Box<dyn Read + Send> = Box::new(file.take(body_len));
+    let mut response = Response::new(
+        status,
+        Vec::new(),
+        body,
+        usize::try_from(body_len).ok(),
+        None,
+    )
+    .with_chunked_threshold(usize::MAX);
+    response = with_header_if_valid(response, "Content-Type", content_type);
+    response = with_header_if_valid(response, "Accept-Ranges", "bytes");
+    if let Some(content_range) = content_range {
+        response = with_header_if_valid(response, "Content-Range", &content_range);
+    }
+    if should_download {
+        response = with_header_if_valid(
+            response,
+            "Content-Disposition",
+            &make_attachment_disposition(file_path),
+        );
     }
+    with_validators(response)
 }
 
 fn is_text_type(content_type: &str) -> bool {
@@ -2219,42 +2437,101 @@ fn is_text_type(content_type: &str) -> bool {
         || content_type == "image/svg+xml"
 }
 
+/// Directory listing, unless the mount serving `dir_path` turned listings off.
+fn create_directory_response(
+    dir_path: &Path,
+    url_path: &str,
+    url_query: &str,
+    markdown_highlight: Option<&WebMarkdownHighlightConfig>,
+    mount: Option<&WebMount>,
+) -> ResponseBox {
+    if mount.is_some_and(|m| !m.listing) {
+        return create_error_response(StatusCode(403), "Directory listing is disabled").boxed();
+    }
+    create_directory_listing(
+        dir_path,
+        url_path,
+        url_query,
+        markdown_highlight,
+        mount.is_none_or(|m| m.show_hidden),
+    )
+    .boxed()
+}
+
+/// Redirects a directory URL without a trailing slash so relative links in its index resolve.
+fn create_trailing_slash_redirect(url_path: &str, url_query: &str) -> ResponseBox {
+    let location = if url_query.is_empty() {
+        format!("{}/", url_path)
+    } else {
+        format!("{}/?{}", url_path, url_query)
+    };
+    let response = Response::empty(StatusCode(301));
+    with_header_if_valid(response, "Location", &location).boxed()
+}
+
+/// Serves a configured `notFoundPage` with status 404.
+fn create_not_found_page_response(page: &Path) -> ResponseBox {
+    let content = match fs::read(page) {
+        Ok(content) => content,
+        Err(_) => return create_error_response(StatusCode(404), "Not Found").boxed(),
+    };
+    let (base_content_type, _) = resolve_content_type_and_download(page, &content);
+    let content_type = if is_text_type(&base_content_type) {
+        format!(
+            "{}; charset={}",
+            base_content_type,
+            detect_encoding(&content).name()
+        )
+    } else {
+        base_content_type
+    };
+    let response = Response::from_data(content).with_status_code(StatusCode(404));
+    with_header_if_valid(response, "Content-Type", &content_type).boxed()
+}
+
 pub fn get_content_type(path: &PathBuf) -> String {
     get_web_content_type(path.as_path())
 }
 
+/// What [`handle_web_request`] made of a request. `Drip` and `Fault` are written to the
+/// connection by the listener worker rather than sent by tiny_http.
+pub enum WebResponse {
+    Handled(ResponseBox),
+    /// The head of a `/slow/drip` response; the worker streams the body.
+    Drip(ResponseBox, DripSettings),
+    /// An in-band `/fault/{name}`; the worker writes it to the connection.
+    Fault(Fault),
+}
+
 pub fn handle_web_request(
     request: &mut tiny_http::Request,
-    root_path: &PathBuf,
-    dump_enabled: bool,
-    slow_enabled: bool,
-    status_enabled: bool,
-    allow_html_in_md: bool,
-    markdown_open_external_link_in_new_tab: bool,
-    markdown_highlight: Option<&WebMarkdownHighlightConfig>,
-    editor_repos_dir: &Option<String>,
-    editor_include_host: bool,
-    editor_command: &str,
-    editor_args: &[String],
-    markdown_live_reload_ws_port: Option<u16>,
-    local_preview_api: Option<&Arc<AtomicBool>>,
+    settings: &WebListenerSettings,
     server_port: u16,
-) -> Response<std::io::Cursor<Vec<u8>>> {
-    if let Some(flag) = local_preview_api {
+) -> WebResponse {
+    let allow_html_in_md = settings.allow_html_in_md;
+    let markdown_open_external_link_in_new_tab = settings.markdown_open_external_link_in_new_tab;
+    let markdown_highlight = settings.markdown_highlight.as_ref();
+    let markdown_live_reload_ws_port = settings.markdown_live_reload_ws_port;
+    let source_max_bytes = settings.source_max_bytes;
+    if let Some(flag) = &settings.local_preview_api {
         if let Some(resp) = try_handle_local_preview_request(request, flag, server_port) {
-            return resp;
+            return WebResponse::Handled(resp.boxed());
         }
     } else if is_preview_route_request(request) {
-        return create_error_response(
+        return WebResponse::Handled(create_error_response(
             StatusCode(404),
             "POST /preview is only on the main server HTTP port (see startup log line starting with \"Main Server:\"). This listener does not serve the local preview API.",
-        );
+        )
+        .boxed());
     }
+    let conditional = ConditionalRequest::from_request(request);
+    let is_get_or_head = matches!(request.method(), Method::Get | Method::Head);
     let url = request.url();
     let (path, request_query) = split_url_path_and_query(url);
-    let content_mode = parse_content_mode(url);
+    let requested_mode = parse_content_mode(url);
+    let content_mode = requested_mode.unwrap_or(ContentMode::Content);
     if let Some(shared_file_path) = resolve_temp_file(path) {
-        return create_file_response(
+        return WebResponse::Handled(create_file_response(
             &shared_file_path,
             allow_html_in_md,
             markdown_open_external_link_in_new_tab,
@@ -2263,10 +2540,20 @@ pub fn handle_web_request(
             path,
             request_query,
             markdown_live_reload_ws_port,
-        );
+            source_max_bytes,
+            &conditional,
+        ));
+    }
+    // Declared routes take precedence over mounts, debug endpoints and files.
+    if let Some(response) = handle_route_request(request, path, request_query, &settings.routes) {
+        return WebResponse::Handled(response);
     }
+    let mut mount: Option<&WebMount> = None;
+    let temp_share_options = WebRootOptions::default();
+    let mut active_options = &settings.root_options;
     let (active_root_path, active_path, public_url_path) = match resolve_temp_share(path) {
         Some((temp_root, temp_relative_path, temp_public_prefix)) => {
+            active_options = &temp_share_options;
             let public_path = if temp_relative_path == "/" {
                 format!("{}/", temp_public_prefix)
             } else {
@@ -2274,44 +2561,128 @@ pub fn handle_web_request(
             };
             (temp_root, temp_relative_path, public_path)
         }
-        None => (root_path.clone(), path.to_string(), path.to_string()),
+        None => match resolve_mount(path, &settings.mounts) {
+            Some((matched, mount_relative_path)) => {
+                mount = Some(matched);
+                active_options = &matched.options;
+                (matched.root.clone(), mount_relative_path, path.to_string())
+            }
+            None => (settings.root.clone(), path.to_string(), path.to_string()),
+        },
     };
+    let content_mode = match mount {
+        Some(m) => requested_mode.unwrap_or(mount_content_mode(m.default_mode)),
+        None => content_mode,
+    };
+    if mount.is_some_and(|m| !m.show_hidden) && has_hidden_segment(active_path.as_str()) {
+        return WebResponse::Handled(create_error_response(StatusCode(404), "Not Found").boxed());
+    }
 
     if is_resource_meta_request(active_path.as_str()) {
-        return handle_resource_meta_request(url, &active_root_path, active_path.as_str());
+        return WebResponse::Handled(
+            handle_resource_meta_request(url, &active_root_path, active_path.as_str()).boxed(),
+        );
     }
 
+    // Mounted paths are always files: debug endpoints are only routed outside mounts.
     // Check if this is a /editor request
-    if editor_repos_dir.is_some() {
+    if settings.editor_repos_dir.is_some() && mount.is_none() {
         if active_path == "/editor" || active_path.starts_with("/editor/") {
-            return handle_editor_request(
-                request,
-                editor_repos_dir,
-                editor_include_host,
-                editor_command,
-                editor_args,
+            return WebResponse::Handled(
+                handle_editor_request(
+                    request,
+                    &settings.editor_repos_dir,
+                    settings.editor_include_host,
+                    &settings.editor_command,
+                    &settings.editor_args,
+                )
+                .boxed(),
             );
         }
     }
 
     // Check if this is a /status request (including /status/ and any subpaths)
-    if status_enabled {
+    if settings.status && mount.is_none() {
+        if active_path == RANDOM_STATUS_PATH || active_path.starts_with("/status/random/") {
+            return WebResponse::Handled(handle_random_status_request(
+                active_path.as_str(),
+                request_query,
+            ));
+        }
+        if active_path == RANDOM_STATUS_COUNTERS_PATH {
+            return WebResponse::Handled(handle_random_status_counters_request(request));
+        }
         if active_path.starts_with("/status/") {
-            return handle_status_request(request, active_path.as_str());
+            return WebResponse::Handled(
+                handle_status_request(request, active_path.as_str(), request_query).boxed(),
+            );
+        }
+        if is_redirect_request(active_path.as_str()) {
+            return WebResponse::Handled(handle_redirect_request(
+                request,
+                active_path.as_str(),
+                request_query,
+            ));
         }
     }
 
+    // Check if this is a /fault request; the broken response is written by the listener worker
+    if settings.fault && mount.is_none() && active_path.starts_with("/fault/") {
+        return handle_fault_request(request, active_path.as_str(), request_query);
+    }
+
+    // Check if this is an httpbin-style utility request (/headers, /bytes/{n}, ...)
+    if settings.httpbin && mount.is_none() && is_httpbin_request(active_path.as_str()) {
+        return WebResponse::Handled(handle_httpbin_request(
+            request,
+            active_path.as_str(),
+            request_query,
+        ));
+    }
+
+    // Check if this is a /basic-auth, /bearer or /digest-auth request
+    if let Some(auth) = settings
+        .auth
+        .as_ref()
+        .filter(|_| mount.is_none() && is_auth_request(active_path.as_str()))
+    {
+        return WebResponse::Handled(handle_auth_request(request, active_path.as_str(), auth));
+    }
+
     // Check if this is a /slow request (including /slow/ and any subpaths)
-    if slow_enabled {
+    if settings.slow && mount.is_none() {
+        // The drip body is streamed by the listener worker.
+        if active_path == DRIP_PATH {
+            return match parse_drip_settings(url) {
+                Ok(drip) => WebResponse::Drip(create_drip_head(), drip),
+                Err(message) => {
+                    WebResponse::Handled(create_error_response(StatusCode(400), &message).boxed())
+                }
+            };
+        }
         if active_path == "/slow" || active_path.starts_with("/slow/") {
-            return handle_slow_request(request);
+            return WebResponse::Handled(handle_slow_request(request).boxed());
+        }
+    }
+
+    // Check if this is a /cookies, /cache/{seconds} or /etag/{etag} request
+    if settings.cookies_and_caching && mount.is_none() {
+        if is_cookies_request(active_path.as_str()) {
+            return WebResponse::Handled(handle_cookies_request(
+                request,
+                active_path.as_str(),
+                request_query,
+            ));
+        }
+        if is_cache_request(active_path.as_str()) {
+            return WebResponse::Handled(handle_cache_request(request, active_path.as_str()));
         }
     }
 
     // Check if this is a /dump request (including /dump/ and any subpaths)
-    if dump_enabled {
+    if settings.dump && mount.is_none() {
         if active_path == "/dump" || active_path.starts_with("/dump/") {
-            return handle_dump_request(request);
+            return WebResponse::Handled(handle_dump_request(request, markdown_highlight).boxed());
         }
     }
 
@@ -2319,7 +2690,7 @@ pub fn handle_web_request(
 
     // Security: Check for directory traversal attempts (pre-decode)
     if url_path.contains("..") || url_path.contains("//") {
-        return create_error_response(StatusCode(400), "Bad Request");
+        return WebResponse::Handled(create_error_response(StatusCode(400), "Bad Request").boxed());
     }
 
     // Determine the actual file path
@@ -2328,7 +2699,9 @@ pub fn handle_web_request(
     } else {
         let relative_path = url_path.trim_start_matches('/');
         if relative_path.starts_with('/') || (cfg!(windows) && relative_path.contains(':')) {
-            return create_error_response(StatusCode(400), "Bad Request");
+            return WebResponse::Handled(
+                create_error_response(StatusCode(400), "Bad Request").boxed(),
+            );
         }
         // Decode URL-encoded path components (each segment separately)
         let mut decoded_segments = Vec::new();
@@ -2337,11 +2710,17 @@ pub fn handle_web_request(
                 Ok(decoded) => {
                     // Security: Reject traversal after URL decoding (%2e%2e bypass)
                     if decoded.contains("..") {
-                        return create_error_response(StatusCode(400), "Bad Request");
+                        return WebResponse::Handled(
+                            create_error_response(StatusCode(400), "Bad Request").boxed(),
+                        );
                     }
                     decoded_segments.push(decoded.into_owned());
                 }
-                Err(_) => return create_error_response(StatusCode(400), "Bad Request"),
+                Err(_) => {
+                    return WebResponse::Handled(
+                        create_error_response(StatusCode(400), "Bad Request").boxed(),
+                    );
+                }
             }
         }
         active_root_path.join(decoded_segments.join("/"))
@@ -2353,10 +2732,70 @@ pub fn handle_web_request(
         Err(_) => active_root_path.clone(),
     };
 
+    // Directories: index.html when enabled (`?mode=source` still lists), else the listing.
+    let directory_response = |dir_path: &Path| -> ResponseBox {
+        if active_options.index && content_mode != ContentMode::Source {
+            let index_path = dir_path.join(INDEX_FILE_NAME);
+            let index_in_root = index_path
+                .canonicalize()
+                .is_ok_and(|p| p.starts_with(&normalized_root) && p.is_file());
+            if index_in_root {
+                if !public_url_path.ends_with('/') {
+                    return create_trailing_slash_redirect(public_url_path.as_str(), request_query);
+                }
+                return create_file_response(
+                    &index_path,
+                    allow_html_in_md,
+                    markdown_open_external_link_in_new_tab,
+                    markdown_highlight,
+                    content_mode,
+                    public_url_path.as_str(),
+                    request_query,
+                    markdown_live_reload_ws_port,
+                    source_max_bytes,
+                    &conditional,
+                );
+            }
+        }
+        create_directory_response(
+            dir_path,
+            public_url_path.as_str(),
+            request_query,
+            markdown_highlight,
+            mount,
+        )
+    };
+    // Unknown paths: the SPA fallback (200), then the custom 404 page, then the plain 404.
+    let not_found_response = || -> ResponseBox {
+        if let Some(fallback) = &active_options.spa_fallback
+            && is_get_or_head
+            && requested_mode != Some(ContentMode::Source)
+        {
+            return create_file_response(
+                fallback,
+                allow_html_in_md,
+                markdown_open_external_link_in_new_tab,
+                markdown_highlight,
+                ContentMode::Content,
+                public_url_path.as_str(),
+                request_query,
+                markdown_live_reload_ws_port,
+                source_max_bytes,
+                &conditional,
+            );
+        }
+        match &active_options.not_found_page {
+            Some(page) => create_not_found_page_response(page),
+            None => create_error_response(StatusCode(404), "Not Found").boxed(),
+        }
+    };
+
     let normalized_path = match file_path.canonicalize() {
         Ok(p) => {
             if !p.starts_with(&normalized_root) {
-                return create_error_response(StatusCode(404), "Not Found");
+                return WebResponse::Handled(
+                    create_error_response(StatusCode(404), "Not Found").boxed(),
+                );
             }
             p
         }
@@ -2365,22 +2804,21 @@ pub fn handle_web_request(
             // Special case: if url_path is "/", show directory listing
             if url_path == "/" {
                 if active_root_path.exists() && active_root_path.is_dir() {
-                    return create_directory_listing(
-                        active_root_path.as_path(),
-                        public_url_path.as_str(),
-                        request_query,
-                        markdown_highlight,
-                    );
+                    return WebResponse::Handled(directory_response(active_root_path.as_path()));
                 }
-                return create_error_response(StatusCode(404), "Not Found");
+                return WebResponse::Handled(
+                    create_error_response(StatusCode(404), "Not Found").boxed(),
+                );
             }
             // Check if it's a file
             if file_path.exists() && file_path.is_file() {
                 // Security: Verify file is within root_path
                 if !file_path.starts_with(active_root_path.as_path()) {
-                    return create_error_response(StatusCode(404), "Not Found");
+                    return WebResponse::Handled(
+                        create_error_response(StatusCode(404), "Not Found").boxed(),
+                    );
                 }
-                return create_file_response(
+                return WebResponse::Handled(create_file_response(
                     &file_path,
                     allow_html_in_md,
                     markdown_open_external_link_in_new_tab,
@@ -2389,38 +2827,32 @@ pub fn handle_web_request(
                     public_url_path.as_str(),
                     request_query,
                     markdown_live_reload_ws_port,
-                );
+                    source_max_bytes,
+                    &conditional,
+                ));
             }
             // Check if it's a directory request
             if file_path.exists() && file_path.is_dir() {
                 // Security: Verify directory is within root_path
                 if !file_path.starts_with(active_root_path.as_path()) {
-                    return create_error_response(StatusCode(404), "Not Found");
+                    return WebResponse::Handled(
+                        create_error_response(StatusCode(404), "Not Found").boxed(),
+                    );
                 }
                 // Generate directory listing
-                return create_directory_listing(
-                    &file_path,
-                    public_url_path.as_str(),
-                    request_query,
-                    markdown_highlight,
-                );
+                return WebResponse::Handled(directory_response(&file_path));
             }
-            return create_error_response(StatusCode(404), "Not Found");
+            return WebResponse::Handled(not_found_response());
         }
     };
 
     // If the normalized path is a directory, show directory listing
     if normalized_path.is_dir() {
-        return create_directory_listing(
-            &normalized_path,
-            public_url_path.as_str(),
-            request_query,
-            markdown_highlight,
-        );
+        return WebResponse::Handled(directory_response(&normalized_path));
     }
 
     // It's a file, serve it
-    create_file_response(
+    WebResponse::Handled(create_file_response(
         &normalized_path,
         allow_html_in_md,
         markdown_open_external_link_in_new_tab,
@@ -2429,5 +2861,7 @@ pub fn handle_web_request(
         public_url_path.as_str(),
         request_query,
         markdown_live_reload_ws_port,
-    )
+        source_max_bytes,
+        &conditional,
+    ))
 }
@@ -1,13 +1,13 @@
 use super::structured_renderer::{
     JSON_COLORIZE_LIMIT_BYTES, StructuredViewKind, build_html_response, child_path, classify_json,
     get_last_modified_ms, html_escape, push_indent, render_json_notice_items, render_outline_items,
-    render_summary_items, wrap_json_node,
+    render_summary_fields, render_summary_items, wrap_json_node,
 };
 use crate::web::common::format_display_path;
 use crate::web_server::WebMarkdownHighlightConfig;
 use serde_json::Value;
 use std::path::Path;
-use tiny_http::Response;
+use tiny_http::{Response, StatusCode};
 
 pub fn is_json_file(path: &Path) -> bool {
     match path.extension().and_then(|s| s.to_str()) {
@@ -222,3 +222,28 @@ pub fn create_json_response(
         StructuredViewKind::Json,
     )
 }
+
+/// Shows a JSON value that has no backing file, such as the `/dump` echo, in the structured view.
+pub fn create_json_value_response(
+    page_title: &str,
+    display_path: &str,
+    value: &Value,
+    summary_fields: &[(&str, String)],
+    status: u16,
+    markdown_highlight: Option<&WebMarkdownHighlightConfig>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    build_html_response(
+        &html_escape(page_title),
+        display_path,
+        "",
+        &render_colorized_json(value),
+        &render_outline_items(Some(value)),
+        true,
+        "",
+        &render_summary_fields(summary_fields),
+        "",
+        markdown_highlight,
+        StructuredViewKind::Json,
+    )
+    .with_status_code(StatusCode(status))
+}
@@ -116,7 +116,6 @@ pub fn render_summary_items(
     last_modified_ms: Option<u64>,
     view_status: &str,
 ) -> String {
-    let mut html = String::new();
     let _ = root_type;
     let fields = [
         ("Raw Size", raw_size_display(size_bytes)),
@@ -128,11 +127,16 @@ pub fn render_summary_items(
         ),
         ("Status", view_status.to_string()),
     ];
+    render_summary_fields(&fields)
+}
+
+pub fn render_summary_fields(fields: &[(&str, String)]) -> String {
+    let mut html = String::new();
     for (label, value) in fields {
         html.push_str("<li><span class=\"label\">");
         html.push_str(label);
         html.push_str("</span><span class=\"value\">");
-        html.push_str(&html_escape(&value));
+        html.push_str(&html_escape(value));
         html.push_str("</span></li>");
     }
     html
@@ -384,7 +388,7 @@ pub fn build_html_response(
             "__COMMON_HEADER_HTML__",
             &template_common::render_main_header_html(
                 absolute_path,
-                Some(parent_directory_href),
+                (!parent_directory_href.is_empty()).then_some(parent_directory_href),
                 None,
             ),
         )
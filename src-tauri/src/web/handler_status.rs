@@ -1,11 +1,38 @@
 use tiny_http::{Header, Response, StatusCode};
+use urlencoding::decode;
 
 use super::common::create_error_response;
-use crate::web::status_code::{apply_status_headers, get_status_phrase, should_have_response_body};
+use crate::web::status_code::{
+    StatusHeaderOverrides, apply_status_headers, get_status_phrase, should_have_response_body,
+};
+
+/// Reads `location`, `retry-after` and `www-authenticate` from a `/status/{code}` query.
+pub fn parse_status_overrides(query: &str) -> Result<StatusHeaderOverrides, String> {
+    let mut overrides = StatusHeaderOverrides::default();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let slot = match key {
+            "location" => &mut overrides.location,
+            "retry-after" => &mut overrides.retry_after,
+            "www-authenticate" => &mut overrides.www_authenticate,
+            _ => continue,
+        };
+        let value = value.replace('+', " ");
+        let value = decode(&value)
+            .map(|v| v.into_owned())
+            .map_err(|_| format!("Invalid {} parameter", key))?;
+        if value.chars().any(|c| c.is_control()) {
+            return Err(format!("Invalid {} parameter", key));
+        }
+        *slot = Some(value);
+    }
+    Ok(overrides)
+}
 
 pub fn handle_status_request(
     _request: &tiny_http::Request,
     path: &str,
+    query: &str,
 ) -> Response<std::io::Cursor<Vec<u8>>> {
     // Extract status code from path: /status/{code}
     if !path.starts_with("/status/") {
@@ -22,6 +49,21 @@ pub fn handle_status_request(
         }
     };
 
+    match parse_status_overrides(query) {
+        Ok(overrides) => create_status_response_with_overrides(status_code, &overrides),
+        Err(message) => create_error_response(StatusCode(400), &message),
+    }
+}
+
+/// The `/status/{code}` response for a code in `100..=599`, also used by proxy status overrides.
+pub fn create_status_response(status_code: u16) -> Response<std::io::Cursor<Vec<u8>>> {
+    create_status_response_with_overrides(status_code, &StatusHeaderOverrides::default())
+}
+
+pub fn create_status_response_with_overrides(
+    status_code: u16,
+    overrides: &StatusHeaderOverrides,
+) -> Response<std::io::Cursor<Vec<u8>>> {
     let status = StatusCode(status_code);
 
     // Handle status codes that don't allow response body
@@ -57,5 +99,5 @@ pub fn handle_status_request(
     };
 
     // Apply status-specific headers
-    apply_status_headers(response, status_code, status, has_body)
+    apply_status_headers(response, status_code, status, has_body, overrides)
 }
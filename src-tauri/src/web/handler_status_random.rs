@@ -0,0 +1,331 @@
+//! `/status/random`: a status picked per request from weights, for chaos testing retry logic.
+//! Weights come from the query (`?200=80&500=20`) or a `web.statusProfiles` entry, and every
+//! weight set keeps counters at `/_mclocks/status/random`.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+
+use super::common::create_error_response;
+use super::handler_status::create_status_response;
+
+pub const RANDOM_STATUS_PATH: &str = "/status/random";
+pub const RANDOM_STATUS_COUNTERS_PATH: &str = "/_mclocks/status/random";
+/// Ad-hoc weight sets tracked at once; the oldest is dropped first.
+const MAX_ADHOC_PROFILES: usize = 100;
+const MAX_PROFILE_NAME_LEN: usize = 64;
+
+/// A `web.statusProfiles` entry, or weights given in the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusProfile {
+    /// Status code and relative weight, ordered by code.
+    pub weights: Vec<(u16, u32)>,
+    /// Seed for a reproducible sequence; unseeded profiles use the thread RNG.
+    pub seed: Option<u64>,
+}
+
+impl StatusProfile {
+    fn total_weight(&self) -> u64 {
+        self.weights.iter().map(|(_, w)| u64::from(*w)).sum()
+    }
+
+    /// `200=80&500=20&seed=1`, which names an ad-hoc profile in the counters.
+    fn spec(&self) -> String {
+        let mut parts: Vec<String> = self
+            .weights
+            .iter()
+            .map(|(code, weight)| format!("{}={}", code, weight))
+            .collect();
+        if let Some(seed) = self.seed {
+            parts.push(format!("seed={}", seed));
+        }
+        parts.join("&")
+    }
+}
+
+/// Checks weights from config or a query: codes in `100..=599`, at least one non-zero weight.
+pub fn build_status_profile(
+    weights: impl IntoIterator<Item = (String, u32)>,
+    seed: Option<u64>,
+) -> Result<StatusProfile, String> {
+    let mut by_code = BTreeMap::new();
+    for (code, weight) in weights {
+        match code.parse::<u16>() {
+            Ok(code) if (100..=599).contains(&code) => {
+                by_code.insert(code, weight);
+            }
+            _ => return Err(format!("Invalid status code in weights: {}", code)),
+        }
+    }
+    let profile = StatusProfile {
+        weights: by_code.into_iter().collect(),
+        seed,
+    };
+    if profile.total_weight() == 0 {
+        return Err("Weights must include at least one status with a non-zero weight".to_string());
+    }
+    Ok(profile)
+}
+
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_PROFILE_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[derive(Default)]
+struct ProfileState {
+    rng: Option<StdRng>,
+    counts: BTreeMap<u16, u64>,
+}
+
+impl ProfileState {
+    fn pick(&mut self, profile: &StatusProfile) -> u16 {
+        let total = profile.total_weight();
+        let mut roll = match profile.seed {
+            Some(seed) => self
+                .rng
+                .get_or_insert_with(|| StdRng::seed_from_u64(seed))
+                .random_range(0..total),
+            None => rand::random_range(0..total),
+        };
+        let code = profile
+            .weights
+            .iter()
+            .find(|(_, weight)| {
+                let weight = u64::from(*weight);
+                if roll < weight {
+                    true
+                } else {
+                    roll -= weight;
+                    false
+                }
+            })
+            .map(|(code, _)| *code)
+            .unwrap_or(200);
+        *self.counts.entry(code).or_insert(0) += 1;
+        code
+    }
+}
+
+#[derive(Default)]
+struct RandomStatusStore {
+    profiles: BTreeMap<String, StatusProfile>,
+    named: BTreeMap<String, ProfileState>,
+    /// Ad-hoc states by spec, in first-use order.
+    adhoc: Vec<(StatusProfile, ProfileState)>,
+}
+
+fn store() -> &'static Mutex<RandomStatusStore> {
+    static STORE: OnceLock<Mutex<RandomStatusStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(RandomStatusStore::default()))
+}
+
+/// Installs the `web.statusProfiles` entries. Counters of profiles that did not change survive.
+pub fn configure_status_profiles(profiles: BTreeMap<String, StatusProfile>) {
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    let previous = std::mem::take(&mut store.profiles);
+    store.named.retain(|name, _| {
+        previous
+            .get(name)
+            .is_some_and(|p| profiles.get(name) == Some(p))
+    });
+    store.profiles = profiles;
+}
+
+fn parse_query_profile(query: &str) -> Result<StatusProfile, String> {
+    let mut weights = Vec::new();
+    let mut seed = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if key == "seed" {
+            seed = Some(
+                value
+                    .parse::<u64>()
+                    .map_err(|_| "Invalid seed parameter".to_string())?,
+            );
+            continue;
+        }
+        let weight = value
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid weight for {}", key))?;
+        weights.push((key.to_string(), weight));
+    }
+    if weights.is_empty() {
+        return Err(format!(
+            "Specify weights, e.g. {}?200=80&500=20",
+            RANDOM_STATUS_PATH
+        ));
+    }
+    build_status_profile(weights, seed)
+}
+
+fn pick_status(profile_name: Option<&str>, query: &str) -> Result<u16, (u16, String)> {
+    let Ok(mut store) = store().lock() else {
+        return Err((500, "Random status store is unavailable".to_string()));
+    };
+    let store = &mut *store;
+    match profile_name {
+        Some(name) => {
+            let Some(profile) = store.profiles.get(name) else {
+                return Err((404, format!("Unknown status profile: {}", name)));
+            };
+            Ok(store
+                .named
+                .entry(name.to_string())
+                .or_default()
+                .pick(profile))
+        }
+        None => {
+            let profile = parse_query_profile(query).map_err(|message| (400, message))?;
+            let index = match store.adhoc.iter().position(|(p, _)| *p == profile) {
+                Some(index) => index,
+                None => {
+                    if store.adhoc.len() >= MAX_ADHOC_PROFILES {
+                        store.adhoc.remove(0);
+                    }
+                    store.adhoc.push((profile, ProfileState::default()));
+                    store.adhoc.len() - 1
+                }
+            };
+            let (profile, state) = &mut store.adhoc[index];
+            Ok(state.pick(profile))
+        }
+    }
+}
+
+/// Serves `/status/random?{code}={weight}…[&seed=]` and `/status/random/{profile}`.
+pub fn handle_random_status_request(path: &str, query: &str) -> ResponseBox {
+    let profile_name = match path.strip_prefix(RANDOM_STATUS_PATH) {
+        Some("") | Some("/") => None,
+        Some(rest) => {
+            let name = rest.trim_start_matches('/').split('/').next().unwrap_or("");
+            if !is_valid_profile_name(name) {
+                return create_error_response(StatusCode(404), "Not Found").boxed();
+            }
+            Some(name)
+        }
+        None => return create_error_response(StatusCode(404), "Not Found").boxed(),
+    };
+    match pick_status(profile_name, query) {
+        Ok(code) => create_status_response(code).boxed(),
+        Err((status, message)) => create_error_response(StatusCode(status), &message).boxed(),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileCounters {
+    profile: String,
+    adhoc: bool,
+    seed: Option<u64>,
+    weights: BTreeMap<String, u32>,
+    total: u64,
+    counts: BTreeMap<String, u64>,
+}
+
+fn profile_counters(
+    name: String,
+    adhoc: bool,
+    profile: &StatusProfile,
+    state: Option<&ProfileState>,
+) -> ProfileCounters {
+    let counts: BTreeMap<String, u64> = state
+        .map(|s| {
+            s.counts
+                .iter()
+                .map(|(code, n)| (code.to_string(), *n))
+                .collect()
+        })
+        .unwrap_or_default();
+    ProfileCounters {
+        profile: name,
+        adhoc,
+        seed: profile.seed,
+        weights: profile
+            .weights
+            .iter()
+            .map(|(code, weight)| (code.to_string(), *weight))
+            .collect(),
+        total: counts.values().sum(),
+        counts,
+    }
+}
+
+/// Counters per profile as JSON; `DELETE` resets them and restarts seeded sequences.
+pub fn handle_random_status_counters_request(request: &Request) -> ResponseBox {
+    let Ok(mut store) = store().lock() else {
+        return create_error_response(StatusCode(500), "Random status store is unavailable")
+            .boxed();
+    };
+    if request.method() == &Method::Delete {
+        store.named.clear();
+        store.adhoc.clear();
+        return Response::empty(StatusCode(204)).boxed();
+    }
+    let mut profiles: Vec<ProfileCounters> = store
+        .profiles
+        .iter()
+        .map(|(name, profile)| {
+            profile_counters(name.clone(), false, profile, store.named.get(name))
+        })
+        .collect();
+    profiles.extend(
+        store
+            .adhoc
+            .iter()
+            .map(|(profile, state)| profile_counters(profile.spec(), true, profile, Some(state))),
+    );
+    let body = serde_json::json!({ "profiles": profiles }).to_string();
+    let mut response = Response::from_string(body).boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+        response.add_header(header);
+    }
+    if let Ok(header) = Header::from_bytes(&b"Cache-Control"[..], &b"no-store"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_profile() {
+        let profile = parse_query_profile("500=15&200=80&503=5&seed=7").unwrap();
+        assert_eq!(profile.weights, vec![(200, 80), (500, 15), (503, 5)]);
+        assert_eq!(profile.seed, Some(7));
+        assert_eq!(profile.spec(), "200=80&500=15&503=5&seed=7");
+
+        assert!(parse_query_profile("").is_err());
+        assert!(parse_query_profile("200=0").is_err());
+        assert!(parse_query_profile("99=1").is_err());
+        assert!(parse_query_profile("200=abc").is_err());
+        assert!(parse_query_profile("200=1&seed=x").is_err());
+    }
+
+    #[test]
+    fn test_seeded_profile_repeats_its_sequence() {
+        let profile =
+            build_status_profile([("200".to_string(), 50), ("500".to_string(), 50)], Some(42))
+                .unwrap();
+        let mut first = ProfileState::default();
+        let mut second = ProfileState::default();
+        let a: Vec<u16> = (0..50).map(|_| first.pick(&profile)).collect();
+        let b: Vec<u16> = (0..50).map(|_| second.pick(&profile)).collect();
+        assert_eq!(a, b);
+        assert!(a.contains(&200) && a.contains(&500));
+        assert_eq!(first.counts.values().sum::<u64>(), 50);
+
+        let only_503 =
+            build_status_profile([("503".to_string(), 1), ("200".to_string(), 0)], None).unwrap();
+        assert_eq!(ProfileState::default().pick(&only_503), 503);
+    }
+}
@@ -0,0 +1,302 @@
+//! Conditional (`If-None-Match` / `If-Modified-Since`) and `Range` request handling for files.
+use chrono::{DateTime, Utc};
+use std::fs::Metadata;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Method, Request};
+
+/// Request headers relevant to serving a file. Only collected for GET/HEAD.
+#[derive(Default, Debug, Clone)]
+pub struct ConditionalRequest {
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+    pub if_range: Option<String>,
+    pub range: Option<String>,
+}
+
+impl ConditionalRequest {
+    pub fn from_request(request: &Request) -> Self {
+        if !matches!(request.method(), Method::Get | Method::Head) {
+            return Self::default();
+        }
+        Self::from_headers(request.headers())
+    }
+
+    pub fn from_headers(headers: &[Header]) -> Self {
+        let find = |name: &'static str| {
+            headers
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str().trim().to_string())
+        };
+        Self {
+            if_none_match: find("If-None-Match"),
+            if_modified_since: find("If-Modified-Since"),
+            if_range: find("If-Range"),
+            range: find("Range"),
+        }
+    }
+}
+
+/// Validators derived from file metadata, sent as `ETag` and `Last-Modified`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileValidators {
+    pub etag: String,
+    pub last_modified: Option<String>,
+    modified_secs: Option<i64>,
+}
+
+impl FileValidators {
+    /// Validators for a generated response, such as `/etag/{etag}` or `/cache/{seconds}`.
+    pub fn new(etag: String, modified: Option<SystemTime>) -> Self {
+        let modified_secs = modified
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .and_then(|d| i64::try_from(d.as_secs()).ok());
+        Self {
+            etag,
+            last_modified: modified.map(format_http_date),
+            modified_secs,
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+        let etag = match modified {
+            Some(d) => format!("\"{:x}-{:x}\"", metadata.len(), d.as_nanos()),
+            None => format!("\"{:x}\"", metadata.len()),
+        };
+        let modified_secs = modified.and_then(|d| i64::try_from(d.as_secs()).ok());
+        Self {
+            etag,
+            last_modified: metadata.modified().ok().map(format_http_date),
+            modified_secs,
+        }
+    }
+}
+
+pub fn format_http_date(time: SystemTime) -> String {
+    let dt: DateTime<Utc> = time.into();
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn parse_http_date_secs(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+fn strip_weak_prefix(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+/// Weak comparison as used by `If-None-Match`.
+fn etag_list_matches(list: &str, etag: &str) -> bool {
+    let list = list.trim();
+    if list == "*" {
+        return true;
+    }
+    let want = strip_weak_prefix(etag);
+    list.split(',')
+        .map(str::trim)
+        .any(|candidate| strip_weak_prefix(candidate) == want)
+}
+
+/// True when the client's cached copy is still fresh and a `304 Not Modified` should be sent.
+/// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 9110 §13.2.2).
+pub fn is_not_modified(conditional: &ConditionalRequest, validators: &FileValidators) -> bool {
+    if let Some(list) = conditional.if_none_match.as_deref() {
+        return etag_list_matches(list, &validators.etag);
+    }
+    match (
+        conditional
+            .if_modified_since
+            .as_deref()
+            .and_then(parse_http_date_secs),
+        validators.modified_secs,
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeOutcome {
+    /// No usable `Range`; serve the whole representation.
+    Full,
+    /// Inclusive byte range `start..=end`.
+    Partial(u64, u64),
+    /// The range cannot be satisfied for a body of this length.
+    Unsatisfiable,
+}
+
+/// `If-Range` only lets the range through when it names the current strong ETag or date.
+fn if_range_allows(if_range: Option<&str>, validators: &FileValidators) -> bool {
+    let Some(value) = if_range else {
+        return true;
+    };
+    if value.starts_with('"') {
+        return value == validators.etag;
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+    match (parse_http_date_secs(value), validators.modified_secs) {
+        (Some(date), Some(modified)) => modified == date,
+        _ => false,
+    }
+}
+
+/// Resolves a single `bytes=` range. Multi-range and malformed values fall back to `Full`,
+/// which RFC 9110 permits.
+pub fn resolve_range(
+    conditional: &ConditionalRequest,
+    validators: &FileValidators,
+    len: u64,
+) -> RangeOutcome {
+    let Some(range) = conditional.range.as_deref() else {
+        return RangeOutcome::Full;
+    };
+    if !if_range_allows(conditional.if_range.as_deref(), validators) {
+        return RangeOutcome::Full;
+    }
+    parse_byte_range(range, len)
+}
+
+pub fn parse_byte_range(value: &str, len: u64) -> RangeOutcome {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeOutcome::Full;
+    };
+    if spec.contains(',') {
+        return RangeOutcome::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeOutcome::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() {
+        let Ok(suffix) = end.parse::<u64>() else {
+            return RangeOutcome::Full;
+        };
+        if suffix == 0 || len == 0 {
+            return RangeOutcome::Unsatisfiable;
+        }
+        return RangeOutcome::Partial(len.saturating_sub(suffix), len - 1);
+    }
+    let Ok(start) = start.parse::<u64>() else {
+        return RangeOutcome::Full;
+    };
+    let end = if end.is_empty() {
+        None
+    } else {
+        match end.parse::<u64>() {
+            Ok(e) if e >= start => Some(e),
+            _ => return RangeOutcome::Full,
+        }
+    };
+    if start >= len {
+        return RangeOutcome::Unsatisfiable;
+    }
+    let last = len - 1;
+    RangeOutcome::Partial(start, end.map(|e| e.min(last)).unwrap_or(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators() -> FileValidators {
+        FileValidators {
+            etag: "\"a-b\"".to_string(),
+            last_modified: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            modified_secs: Some(784111777),
+        }
+    }
+
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(
+            parse_byte_range("bytes=0-9", 100),
+            RangeOutcome::Partial(0, 9)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=90-", 100),
+            RangeOutcome::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=-10", 100),
+            RangeOutcome::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=-500", 100),
+            RangeOutcome::Partial(0, 99)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=50-500", 100),
+            RangeOutcome::Partial(50, 99)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=100-", 100),
+            RangeOutcome::Unsatisfiable
+        );
+        assert_eq!(
+            parse_byte_range("bytes=-0", 100),
+            RangeOutcome::Unsatisfiable
+        );
+        assert_eq!(parse_byte_range("bytes=5-1", 100), RangeOutcome::Full);
+        assert_eq!(parse_byte_range("bytes=0-1,5-6", 100), RangeOutcome::Full);
+        assert_eq!(parse_byte_range("items=0-1", 100), RangeOutcome::Full);
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let v = validators();
+        let by_etag = ConditionalRequest {
+            if_none_match: Some("\"x\", W/\"a-b\"".to_string()),
+            ..Default::default()
+        };
+        assert!(is_not_modified(&by_etag, &v));
+
+        let etag_wins = ConditionalRequest {
+            if_none_match: Some("\"other\"".to_string()),
+            if_modified_since: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            ..Default::default()
+        };
+        assert!(!is_not_modified(&etag_wins, &v));
+
+        let by_date = ConditionalRequest {
+            if_modified_since: Some("Mon, 07 Nov 1994 00:00:00 GMT".to_string()),
+            ..Default::default()
+        };
+        assert!(is_not_modified(&by_date, &v));
+
+        let older_date = ConditionalRequest {
+            if_modified_since: Some("Sat, 05 Nov 1994 00:00:00 GMT".to_string()),
+            ..Default::default()
+        };
+        assert!(!is_not_modified(&older_date, &v));
+    }
+
+    #[test]
+    fn test_resolve_range_respects_if_range() {
+        let v = validators();
+        let mut conditional = ConditionalRequest {
+            range: Some("bytes=0-1".to_string()),
+            if_range: Some("\"a-b\"".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_range(&conditional, &v, 10),
+            RangeOutcome::Partial(0, 1)
+        );
+        conditional.if_range = Some("\"stale\"".to_string());
+        assert_eq!(resolve_range(&conditional, &v, 10), RangeOutcome::Full);
+    }
+
+    #[test]
+    fn test_format_http_date() {
+        let t = UNIX_EPOCH + std::time::Duration::from_secs(784111777);
+        assert_eq!(format_http_date(t), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+}
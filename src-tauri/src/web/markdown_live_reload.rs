@@ -1,7 +1,8 @@
 //! WebSocket server for live-reloading rendered Markdown in the external browser.
+//! It also feeds `web.bin` history pages (`/bin/{name}`) with newly stored requests.
 use futures_util::{SinkExt, StreamExt};
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use std::thread;
@@ -13,7 +14,12 @@ use tungstenite::handshake::server::{ErrorResponse, Request, Response};
 use tungstenite::http::StatusCode;
 use uuid::Uuid;
 
+use super::request_bin::{is_valid_bin_name, subscribe_bin_requests};
+
 const LIVE_PATH_PREFIX: &str = "/live/";
+const BIN_PATH_PREFIX: &str = "/bin/";
+/// Oldest `/bin/{name}` viewer tokens are forgotten once this many pages have been rendered.
+const MAX_BIN_TOKENS: usize = 256;
 
 fn normalize_path_key(path: &Path) -> String {
     let key = path.to_string_lossy().to_string();
@@ -75,6 +81,8 @@ fn spawn_path_watcher(watch_path: PathBuf, tx: broadcast::Sender<()>) {
 struct Hub {
     token_to_path: HashMap<String, PathBuf>,
     path_to_tx: HashMap<String, broadcast::Sender<()>>,
+    /// `(token, bin name)` pairs issued to rendered bin viewer pages, oldest first.
+    bin_tokens: VecDeque<(String, String)>,
 }
 
 impl Hub {
@@ -82,6 +90,7 @@ impl Hub {
         Self {
             token_to_path: HashMap::new(),
             path_to_tx: HashMap::new(),
+            bin_tokens: VecDeque::new(),
         }
     }
 
@@ -101,6 +110,19 @@ impl Hub {
         self.token_to_path.contains_key(token)
     }
 
+    fn register_bin_session(&mut self, name: &str) -> String {
+        let token = Uuid::new_v4().to_string();
+        if self.bin_tokens.len() >= MAX_BIN_TOKENS {
+            self.bin_tokens.pop_front();
+        }
+        self.bin_tokens.push_back((token.clone(), name.to_string()));
+        token
+    }
+
+    fn bin_token_valid(&self, name: &str, token: &str) -> bool {
+        self.bin_tokens.iter().any(|(t, n)| t == token && n == name)
+    }
+
     fn subscribe(&self, token: &str) -> Option<broadcast::Receiver<()>> {
         let path = self.token_to_path.get(token)?;
         let key = normalize_path_key(path);
@@ -162,6 +184,13 @@ pub fn register_markdown_live_reload_session(file_path: &Path) -> Option<String>
     Some(g.register_session(file_path.to_path_buf()))
 }
 
+/// Issues a token that lets one rendered `/_mclocks/bin/{name}` page subscribe to `/bin/{name}/{token}`.
+pub fn register_bin_viewer_session(name: &str) -> Option<String> {
+    markdown_live_reload_ws_port()?;
+    let mut g = hub().lock().ok()?;
+    Some(g.register_bin_session(name))
+}
+
 fn forbidden_response() -> ErrorResponse {
     Response::builder()
         .status(StatusCode::FORBIDDEN)
@@ -174,10 +203,38 @@ fn parse_live_token(path: &str) -> Option<&str> {
         .map(|s| s.trim_end_matches('/'))
 }
 
+/// Splits `/bin/{name}/{token}` into its name and token.
+fn parse_bin_path(path: &str) -> Option<(&str, &str)> {
+    let (name, token) = path
+        .strip_prefix(BIN_PATH_PREFIX)?
+        .trim_end_matches('/')
+        .split_once('/')?;
+    (is_valid_bin_name(name) && !token.is_empty() && !token.contains('/')).then_some((name, token))
+}
+
+enum Subscription {
+    Live(String),
+    Bin(String),
+}
+
 async fn handle_connection(stream: tokio::net::TcpStream) {
-    let mut token_out: Option<String> = None;
+    let mut subscription: Option<Subscription> = None;
     let ws_result = accept_hdr_async(stream, |req: &Request, response: Response| {
         let path = req.uri().path();
+        if path.starts_with(BIN_PATH_PREFIX) {
+            let Some((name, token)) = parse_bin_path(path) else {
+                return Err(forbidden_response());
+            };
+            let ok = hub()
+                .lock()
+                .ok()
+                .is_some_and(|h| h.bin_token_valid(name, token));
+            if !ok {
+                return Err(forbidden_response());
+            }
+            subscription = Some(Subscription::Bin(name.to_string()));
+            return Ok(response);
+        }
         let token = match parse_live_token(path) {
             Some(t) if !t.is_empty() && !t.contains('/') => t.to_string(),
             _ => return Err(forbidden_response()),
@@ -186,7 +243,7 @@ async fn handle_connection(stream: tokio::net::TcpStream) {
         if !ok {
             return Err(forbidden_response());
         }
-        token_out = Some(token);
+        subscription = Some(Subscription::Live(token));
         Ok(response)
     })
     .await;
@@ -194,8 +251,12 @@ async fn handle_connection(stream: tokio::net::TcpStream) {
         Ok(ws) => ws,
         Err(_) => return,
     };
-    let token = match token_out {
-        Some(t) => t,
+    let token = match subscription {
+        Some(Subscription::Live(t)) => t,
+        Some(Subscription::Bin(name)) => {
+            handle_bin_connection(ws, name).await;
+            return;
+        }
         None => return,
     };
     let mut rx = {
@@ -237,6 +298,44 @@ async fn handle_connection(stream: tokio::net::TcpStream) {
     let _ = ws.close(None).await;
 }
 
+/// Sends every request stored in bin `name` as a JSON text message.
+async fn handle_bin_connection(
+    mut ws: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
+    name: String,
+) {
+    let mut rx = subscribe_bin_requests();
+    loop {
+        tokio::select! {
+            biased;
+            recv = rx.recv() => {
+                match recv {
+                    Ok(request) if request.bin == name => {
+                        let Ok(json) = serde_json::to_string(&request) else {
+                            continue;
+                        };
+                        if ws.send(Message::Text(json)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            ws_msg = ws.next() => {
+                match ws_msg {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Ping(p))) => {
+                        let _ = ws.send(Message::Pong(p)).await;
+                    }
+                    Some(Err(_)) => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+    let _ = ws.close(None).await;
+}
+
 async fn run_ws_server(port: u16, ready_tx: std::sync::mpsc::SyncSender<()>) {
     let addr = format!("127.0.0.1:{}", port);
     let listener = match TcpListener::bind(&addr).await {
@@ -264,3 +363,33 @@ async fn run_ws_server(port: u16, ready_tx: std::sync::mpsc::SyncSender<()>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bin_path() {
+        assert_eq!(parse_bin_path("/bin/orders/abc"), Some(("orders", "abc")));
+        assert_eq!(parse_bin_path("/bin/orders/abc/"), Some(("orders", "abc")));
+        assert_eq!(parse_bin_path("/bin/orders"), None);
+        assert_eq!(parse_bin_path("/bin/orders/"), None);
+        assert_eq!(parse_bin_path("/bin/orders/a/b"), None);
+        assert_eq!(parse_bin_path("/bin/../abc"), None);
+        assert_eq!(parse_bin_path("/live/abc"), None);
+    }
+
+    #[test]
+    fn test_bin_token_valid() {
+        let mut hub = Hub::new();
+        let token = hub.register_bin_session("orders");
+        assert!(hub.bin_token_valid("orders", &token));
+        assert!(!hub.bin_token_valid("payments", &token));
+        assert!(!hub.bin_token_valid("orders", "guess"));
+        for _ in 0..MAX_BIN_TOKENS {
+            hub.register_bin_session("orders");
+        }
+        assert!(!hub.bin_token_valid("orders", &token));
+        assert_eq!(hub.bin_tokens.len(), MAX_BIN_TOKENS);
+    }
+}
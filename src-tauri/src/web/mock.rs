@@ -0,0 +1,513 @@
+//! `web.mock` replay of recorded exchanges from a HAR file or a directory of recordings.
+use base64::{Engine as _, engine::general_purpose};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+use super::common::create_error_response;
+use super::handler_dump::DumpResponse;
+
+/// Response headers from a recording that describe the original transfer, not the replayed body.
+const DROPPED_RESPONSE_HEADERS: [&str; 5] = [
+    "Connection",
+    "Content-Encoding",
+    "Content-Length",
+    "Keep-Alive",
+    "Transfer-Encoding",
+];
+
+/// What happens to requests under the mock path that match no recording.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MockUnmatched {
+    #[default]
+    NotFound,
+    /// Continue to the proxy routes, debug endpoints and static files.
+    Passthrough,
+}
+
+/// One recorded request and the response to replay for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockExchange {
+    pub method: String,
+    /// Decoded URL path.
+    pub path: String,
+    /// Decoded query parameters in recorded order.
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Replay settings. Set from `web.mock`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockSettings {
+    /// Normalized URL prefix the mock answers under; `/` for everything.
+    pub prefix: String,
+    pub exchanges: Vec<MockExchange>,
+    pub unmatched: MockUnmatched,
+    /// Query parameter names left out of matching; `*` ignores the whole query.
+    pub ignore_query: Vec<String>,
+    /// Header names whose recorded values must match too, when the recording has them.
+    /// Other headers are ignored.
+    pub match_headers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: HarResponse,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+    #[serde(default)]
+    content: HarContent,
+}
+
+#[derive(Deserialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    mime_type: Option<String>,
+}
+
+/// A recording file: the request as `/dump` reports it and the response to replay.
+#[derive(Deserialize)]
+struct RecordedExchange {
+    request: DumpResponse,
+    #[serde(default)]
+    response: RecordedResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedResponse {
+    #[serde(default = "df_recorded_status")]
+    status: u16,
+    #[serde(default)]
+    headers: Vec<HashMap<String, String>>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+impl Default for RecordedResponse {
+    fn default() -> Self {
+        RecordedResponse {
+            status: df_recorded_status(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+}
+
+fn df_recorded_status() -> u16 {
+    200
+}
+
+fn decode_path(path: &str) -> String {
+    decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Decodes a query name or value, where `+` stands for a space.
+fn decode_component(value: &str) -> String {
+    decode_path(&value.replace('+', " "))
+}
+
+/// Splits a request URL or absolute URL into its decoded path and query parameters.
+fn split_url(url: &str) -> (String, Vec<(String, String)>) {
+    let without_fragment = url.split('#').next().unwrap_or(url);
+    let after_scheme = match without_fragment.find("://") {
+        Some(i) => {
+            let rest = &without_fragment[i + 3..];
+            rest.find(['/', '?']).map_or("/", |j| &rest[j..])
+        }
+        None => without_fragment,
+    };
+    let (path, query) = after_scheme.split_once('?').unwrap_or((after_scheme, ""));
+    let path = if path.is_empty() { "/" } else { path };
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(name), decode_component(value))
+        })
+        .collect();
+    (decode_path(path), query)
+}
+
+fn header_pairs(headers: &[HashMap<String, String>]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .flat_map(|map| map.iter().map(|(n, v)| (n.clone(), v.clone())))
+        .collect()
+}
+
+fn exchange_from_har_entry(entry: HarEntry) -> Result<MockExchange, String> {
+    let (path, query) = split_url(&entry.request.url);
+    let content = entry.response.content;
+    let body = match (content.text, content.encoding.as_deref()) {
+        (Some(text), Some("base64")) => general_purpose::STANDARD
+            .decode(text.trim())
+            .map_err(|e| format!("{}: invalid base64 body: {}", entry.request.url, e))?,
+        (Some(text), _) => text.into_bytes(),
+        (None, _) => Vec::new(),
+    };
+    let mut response_headers: Vec<(String, String)> = entry
+        .response
+        .headers
+        .into_iter()
+        .map(|h| (h.name, h.value))
+        .collect();
+    if let Some(mime_type) = content.mime_type.filter(|m| !m.is_empty())
+        && !response_headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case("Content-Type"))
+    {
+        response_headers.push(("Content-Type".to_string(), mime_type));
+    }
+    Ok(MockExchange {
+        method: entry.request.method.to_ascii_uppercase(),
+        path,
+        query,
+        headers: entry
+            .request
+            .headers
+            .into_iter()
+            .map(|h| (h.name, h.value))
+            .collect(),
+        status: entry.response.status,
+        response_headers,
+        body,
+    })
+}
+
+fn exchange_from_recording(recorded: RecordedExchange) -> MockExchange {
+    let request = recorded.request;
+    let query = request
+        .query
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|map| {
+            map.iter()
+                .map(|(n, v)| (decode_component(n), decode_component(v)))
+        })
+        .collect();
+    MockExchange {
+        method: request.method.to_ascii_uppercase(),
+        path: decode_path(&request.path),
+        query,
+        headers: header_pairs(&request.headers),
+        status: recorded.response.status,
+        response_headers: header_pairs(&recorded.response.headers),
+        body: recorded.response.body.unwrap_or_default().into_bytes(),
+    }
+}
+
+/// Parses one file: a HAR log, a recorded exchange, or an array of recorded exchanges.
+fn parse_mock_file(path: &Path) -> Result<Vec<MockExchange>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let invalid = |e: serde_json::Error| format!("Invalid recording {}: {}", path.display(), e);
+    if value.get("log").is_some() {
+        let har: Har = serde_json::from_value(value).map_err(invalid)?;
+        return har
+            .log
+            .entries
+            .into_iter()
+            .map(exchange_from_har_entry)
+            .collect();
+    }
+    let recordings: Vec<RecordedExchange> = if value.is_array() {
+        serde_json::from_value(value).map_err(invalid)?
+    } else {
+        vec![serde_json::from_value(value).map_err(invalid)?]
+    };
+    Ok(recordings
+        .into_iter()
+        .map(exchange_from_recording)
+        .collect())
+}
+
+/// Loads the recordings at `source`: a `.har`/`.json` file, or a directory of them read in name
+/// order.
+pub fn load_mock_exchanges(source: &Path) -> Result<Vec<MockExchange>, String> {
+    if !source.is_dir() {
+        return parse_mock_file(source);
+    }
+    let mut files: Vec<_> = fs::read_dir(source)
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        ext.eq_ignore_ascii_case("har") || ext.eq_ignore_ascii_case("json")
+                    })
+        })
+        .collect();
+    files.sort();
+    let mut exchanges = Vec::new();
+    for file in files {
+        exchanges.extend(parse_mock_file(&file)?);
+    }
+    Ok(exchanges)
+}
+
+impl MockSettings {
+    fn covers(&self, path: &str) -> bool {
+        self.prefix == "/"
+            || path == self.prefix
+            || path
+                .strip_prefix(self.prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// `path` without the mock prefix, for recordings made against the un-prefixed backend.
+    fn strip_prefix<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.prefix == "/" {
+            return None;
+        }
+        let rest = path.strip_prefix(self.prefix.as_str())?;
+        Some(if rest.is_empty() { "/" } else { rest })
+    }
+
+    /// The recording for a request to `path`: one of `path` itself, or else one of `path`
+    /// without the mock prefix, so `/mock/api/x` replays a recording of `/api/x`.
+    fn find_exchange(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+    ) -> Option<&MockExchange> {
+        std::iter::once(path)
+            .chain(self.strip_prefix(path))
+            .find_map(|path| {
+                self.exchanges
+                    .iter()
+                    .find(|e| self.matches(e, method, path, query, headers))
+            })
+    }
+
+    fn comparable_query(&self, query: &[(String, String)]) -> Vec<(String, String)> {
+        if self.ignore_query.iter().any(|name| name == "*") {
+            return Vec::new();
+        }
+        let mut kept: Vec<(String, String)> = query
+            .iter()
+            .filter(|(name, _)| !self.ignore_query.contains(name))
+            .cloned()
+            .collect();
+        kept.sort();
+        kept
+    }
+
+    fn matches(
+        &self,
+        exchange: &MockExchange,
+        method: &str,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+    ) -> bool {
+        let header = |headers: &[(String, String)], name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.trim().to_string())
+        };
+        exchange.method == method
+            && exchange.path == path
+            && self.comparable_query(&exchange.query) == self.comparable_query(query)
+            && self
+                .match_headers
+                .iter()
+                .all(|name| match header(&exchange.headers, name) {
+                    Some(recorded) => header(headers, name).as_ref() == Some(&recorded),
+                    None => true,
+                })
+    }
+}
+
+fn replay_response(exchange: &MockExchange) -> ResponseBox {
+    let headers: Vec<Header> = exchange
+        .response_headers
+        .iter()
+        .filter(|(name, _)| {
+            !DROPPED_RESPONSE_HEADERS
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(name))
+        })
+        .filter_map(|(name, value)| Header::from_bytes(name.as_bytes(), value.as_bytes()).ok())
+        .collect();
+    Response::new(
+        StatusCode(exchange.status),
+        headers,
+        std::io::Cursor::new(exchange.body.clone()),
+        Some(exchange.body.len()),
+        None,
+    )
+    .boxed()
+}
+
+/// Replays the first recording matching `request`. Returns `None` for requests outside the mock
+/// path, and for unmatched ones when they should pass through.
+pub fn handle_mock_request(request: &Request, settings: &MockSettings) -> Option<ResponseBox> {
+    let (path, query) = split_url(request.url());
+    if !settings.covers(&path) {
+        return None;
+    }
+    let method = request.method().to_string().to_ascii_uppercase();
+    let headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .map(|h| (h.field.to_string(), h.value.to_string()))
+        .collect();
+    match settings.find_exchange(&method, &path, &query, &headers) {
+        Some(exchange) => Some(replay_response(exchange)),
+        None => match settings.unmatched {
+            MockUnmatched::NotFound => Some(
+                create_error_response(
+                    StatusCode(404),
+                    &format!("No recorded response for {} {}", method, request.url()),
+                )
+                .boxed(),
+            ),
+            MockUnmatched::Passthrough => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_url() {
+        let (path, query) = split_url("https://api.example.com/v1/a%20b?q=x+y&_=123#top");
+        assert_eq!(path, "/v1/a b");
+        assert_eq!(
+            query,
+            [
+                ("q".to_string(), "x y".to_string()),
+                ("_".to_string(), "123".to_string())
+            ]
+        );
+        assert_eq!(split_url("http://localhost:3000").0, "/");
+        assert_eq!(
+            split_url("/users?id=1").1,
+            [("id".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_matches_ignores_volatile_query_and_headers() {
+        let exchange = MockExchange {
+            method: "GET".to_string(),
+            path: "/users".to_string(),
+            query: vec![
+                ("page".to_string(), "2".to_string()),
+                ("_".to_string(), "111".to_string()),
+            ],
+            headers: vec![
+                ("Accept".to_string(), "application/json".to_string()),
+                ("X-Request-Id".to_string(), "abc".to_string()),
+            ],
+            status: 200,
+            response_headers: Vec::new(),
+            body: Vec::new(),
+        };
+        let settings = MockSettings {
+            prefix: "/".to_string(),
+            exchanges: vec![exchange.clone()],
+            ignore_query: vec!["_".to_string()],
+            match_headers: vec!["accept".to_string()],
+            ..MockSettings::default()
+        };
+        let query = [
+            ("_".to_string(), "999".to_string()),
+            ("page".to_string(), "2".to_string()),
+        ];
+        let headers = [("accept".to_string(), "application/json".to_string())];
+        assert!(settings.matches(&exchange, "GET", "/users", &query, &headers));
+        assert!(!settings.matches(&exchange, "POST", "/users", &query, &headers));
+        assert!(!settings.matches(&exchange, "GET", "/users", &query[..1], &headers));
+        let other_accept = [("Accept".to_string(), "text/html".to_string())];
+        assert!(!settings.matches(&exchange, "GET", "/users", &query, &other_accept));
+    }
+
+    #[test]
+    fn test_find_exchange_under_prefix() {
+        let exchange = |path: &str, status: u16| MockExchange {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            status,
+            response_headers: Vec::new(),
+            body: Vec::new(),
+        };
+        let settings = MockSettings {
+            prefix: "/mock".to_string(),
+            exchanges: vec![
+                exchange("/api/x", 200),
+                exchange("/", 201),
+                exchange("/mock/api/y", 202),
+            ],
+            ..MockSettings::default()
+        };
+        let status = |path: &str| {
+            settings
+                .find_exchange("GET", path, &[], &[])
+                .map(|e| e.status)
+        };
+        assert_eq!(status("/mock/api/x"), Some(200));
+        assert_eq!(status("/mock"), Some(201));
+        assert_eq!(status("/mock/api/y"), Some(202));
+        assert!(!settings.covers("/api/x"));
+    }
+}
@@ -1,11 +1,28 @@
+pub mod access_guard;
+pub mod access_log;
 pub mod common;
+pub mod compression;
 pub mod dd_publish;
+pub mod handler_auth;
+pub mod handler_cache;
+pub mod handler_cookies;
 pub mod handler_dump;
 pub mod handler_editor;
+pub mod handler_fault;
+pub mod handler_httpbin;
 pub mod handler_local_preview;
+pub mod handler_redirect;
 pub mod handler_resource_meta;
+pub mod handler_routes;
 pub mod handler_slow;
 pub mod handler_static;
 pub mod handler_status;
+pub mod handler_status_random;
+pub mod http_conditional;
 pub mod markdown_live_reload;
+pub mod mock;
+pub mod proxy;
+pub mod request_bin;
+pub mod response_headers;
 pub mod status_code;
+pub mod tls;
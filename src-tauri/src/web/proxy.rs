@@ -0,0 +1,875 @@
+//! `web.proxy` reverse-proxy routes to local backends, with every exchange captured for the
+//! `/_mclocks/proxy` viewer.
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+use super::common::create_error_response;
+use super::handler_status::create_status_response;
+
+const VIEWER_PATH: &str = "/_mclocks/proxy";
+const EXCHANGES_PATH: &str = "/_mclocks/proxy/exchanges";
+/// The captured exchanges as a HAR log, which `web.mock` can replay.
+const EXCHANGES_HAR_PATH: &str = "/_mclocks/proxy/exchanges.har";
+/// Exchanges kept in memory for the viewer.
+const CAPTURE_CAPACITY: usize = 200;
+/// Bodies longer than this are cut in the capture (never in the proxied traffic).
+const CAPTURE_BODY_BYTES: usize = 64 * 1024;
+const MAX_PROXY_BODY_BYTES: u64 = 64 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(120);
+/// Connection-scoped headers that are not forwarded in either direction.
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "Proxy-Connection",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// A plain-HTTP upstream such as `http://127.0.0.1:8080/api`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyUpstream {
+    pub host: String,
+    pub port: u16,
+    /// Path prepended to forwarded requests; empty or starting with `/`, no trailing `/`.
+    pub base_path: String,
+}
+
+/// A resolved `web.proxy` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyRoute {
+    /// Normalized URL prefix: starts with `/`, no trailing `/` except for `/` itself.
+    pub prefix: String,
+    pub upstream: ProxyUpstream,
+    /// When `true`, `prefix` is removed from the path sent upstream.
+    pub strip_prefix: bool,
+    /// Added before forwarding, as with `/slow`.
+    pub delay: Duration,
+    /// Answer with the `/status/{code}` response instead of forwarding.
+    pub status: Option<u16>,
+}
+
+/// One captured request/response pair.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyExchange {
+    pub id: u64,
+    pub time: String,
+    pub route: String,
+    pub method: String,
+    pub path: String,
+    /// `None` when the route answered without contacting the upstream.
+    pub upstream_url: Option<String>,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: CapturedBody,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: CapturedBody,
+    pub duration_ms: u64,
+    /// Time spent waiting for the upstream, without the injected delay.
+    pub upstream_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedBody {
+    pub size: usize,
+    /// UTF-8 text of the (possibly truncated) body; `None` for binary or encoded bodies.
+    pub text: Option<String>,
+    pub truncated: bool,
+}
+
+impl CapturedBody {
+    fn capture(body: &[u8], encoded: bool) -> Self {
+        let truncated = body.len() > CAPTURE_BODY_BYTES;
+        let kept = &body[..body.len().min(CAPTURE_BODY_BYTES)];
+        let text = if encoded {
+            None
+        } else {
+            match std::str::from_utf8(kept) {
+                Ok(text) => Some(text.to_string()),
+                // A cut may split the last character.
+                Err(e) if truncated && e.error_len().is_none() => {
+                    Some(String::from_utf8_lossy(&kept[..e.valid_up_to()]).into_owned())
+                }
+                Err(_) => None,
+            }
+        };
+        CapturedBody {
+            size: body.len(),
+            text,
+            truncated,
+        }
+    }
+}
+
+#[derive(Default)]
+struct CaptureStore {
+    exchanges: VecDeque<ProxyExchange>,
+    next_id: u64,
+}
+
+fn store() -> &'static Mutex<CaptureStore> {
+    static STORE: OnceLock<Mutex<CaptureStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(CaptureStore::default()))
+}
+
+fn record_exchange(mut exchange: ProxyExchange) {
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    exchange.id = store.next_id;
+    store.next_id += 1;
+    if store.exchanges.len() >= CAPTURE_CAPACITY {
+        store.exchanges.pop_front();
+    }
+    store.exchanges.push_back(exchange);
+}
+
+/// Captured exchanges with an id of at least `since`, and the id to poll from next.
+pub fn recent_exchanges(since: u64) -> (Vec<ProxyExchange>, u64) {
+    let Ok(store) = store().lock() else {
+        return (Vec::new(), since);
+    };
+    let exchanges = store
+        .exchanges
+        .iter()
+        .filter(|e| e.id >= since)
+        .cloned()
+        .collect();
+    (exchanges, store.next_id)
+}
+
+/// Parses a `web.proxy[].upstream` URL. Only `http://` upstreams are supported.
+pub fn parse_upstream(url: &str) -> Result<ProxyUpstream, String> {
+    let rest = url
+        .trim()
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("upstream must start with http://: {:?}", url))?;
+    if rest.contains(['?', '#']) {
+        return Err(format!("upstream must not have a query: {:?}", url));
+    }
+    let (authority, base_path) = match rest.find('/') {
+        Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid upstream port: {:?}", url))?,
+        ),
+        _ => (authority, 80),
+    };
+    if host.is_empty() || host.contains('@') {
+        return Err(format!("invalid upstream host: {:?}", url));
+    }
+    Ok(ProxyUpstream {
+        host: host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        port,
+        base_path: base_path.to_string(),
+    })
+}
+
+impl ProxyUpstream {
+    fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        if self.port == 80 {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+}
+
+impl ProxyRoute {
+    fn matches(&self, path: &str) -> bool {
+        self.prefix == "/"
+            || path == self.prefix
+            || path
+                .strip_prefix(self.prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Path and query sent upstream for the request URL `url`.
+    fn upstream_target(&self, url: &str) -> String {
+        let forwarded = if self.strip_prefix && self.prefix != "/" {
+            &url[self.prefix.len()..]
+        } else {
+            url
+        };
+        if forwarded.is_empty() || forwarded.starts_with('?') {
+            format!("{}/{}", self.upstream.base_path, forwarded)
+        } else {
+            format!("{}{}", self.upstream.base_path, forwarded)
+        }
+    }
+}
+
+/// The route whose prefix matches `url`, preferring the longest prefix.
+pub fn find_proxy_route<'a>(routes: &'a [ProxyRoute], url: &str) -> Option<&'a ProxyRoute> {
+    let path = url.split('?').next().unwrap_or(url);
+    routes
+        .iter()
+        .filter(|route| route.matches(path))
+        .max_by_key(|route| route.prefix.len())
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(name))
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+struct UpstreamResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// An upstream body over `MAX_PROXY_BODY_BYTES`, answered with `502` like other bad responses.
+fn body_too_large() -> std::io::Error {
+    invalid_data(format!(
+        "response body exceeds the proxy limit of {} MB",
+        MAX_PROXY_BODY_BYTES / (1024 * 1024)
+    ))
+}
+
+fn read_chunked_body(reader: &mut impl BufRead) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line)?;
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| invalid_data(format!("invalid chunk size: {:?}", size_line.trim())))?;
+        if size == 0 {
+            // Trailers end with an empty line.
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        let end = start
+            .checked_add(size)
+            .filter(|&end| end as u64 <= MAX_PROXY_BODY_BYTES)
+            .ok_or_else(body_too_large)?;
+        body.resize(end, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = String::new();
+        reader.read_line(&mut crlf)?;
+    }
+}
+
+/// Reads an HTTP/1.1 response from a connection the upstream closes after answering.
+fn read_upstream_response(stream: TcpStream, head_only: bool) -> std::io::Result<UpstreamResponse> {
+    let mut reader = BufReader::new(stream);
+    let (status, headers) = loop {
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| {
+                invalid_data(format!("invalid status line: {:?}", status_line.trim()))
+            })?;
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        // Interim responses such as `100 Continue` are followed by the real one.
+        if !(100..200).contains(&status) {
+            break (status, headers);
+        }
+    };
+
+    let body = if head_only || matches!(status, 204 | 304) {
+        Vec::new()
+    } else if header_value(&headers, "Transfer-Encoding")
+        .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"))
+    {
+        read_chunked_body(&mut reader)?
+    } else if let Some(len) = header_value(&headers, "Content-Length") {
+        let len = len
+            .parse::<u64>()
+            .map_err(|_| invalid_data(format!("invalid Content-Length: {:?}", len)))?;
+        if len > MAX_PROXY_BODY_BYTES {
+            return Err(body_too_large());
+        }
+        let mut body = vec![0; len as usize];
+        reader.read_exact(&mut body)?;
+        body
+    } else {
+        let mut body = Vec::new();
+        reader
+            .take(MAX_PROXY_BODY_BYTES + 1)
+            .read_to_end(&mut body)?;
+        if body.len() as u64 > MAX_PROXY_BODY_BYTES {
+            return Err(body_too_large());
+        }
+        body
+    };
+    Ok(UpstreamResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn send_upstream(
+    upstream: &ProxyUpstream,
+    method: &str,
+    target: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<UpstreamResponse, (StatusCode, String)> {
+    let connect_error = |e: std::io::Error| {
+        (
+            StatusCode(502),
+            format!("Failed to connect to {}: {}", upstream.authority(), e),
+        )
+    };
+    let addr = (upstream.host.as_str(), upstream.port)
+        .to_socket_addrs()
+        .map_err(connect_error)?
+        .next()
+        .ok_or_else(|| {
+            (
+                StatusCode(502),
+                format!("Failed to resolve {}", upstream.authority()),
+            )
+        })?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(connect_error)?;
+    let io_error = |e: std::io::Error| {
+        let status = match e.kind() {
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => StatusCode(504),
+            _ => StatusCode(502),
+        };
+        (status, format!("Upstream {}: {}", upstream.authority(), e))
+    };
+    stream
+        .set_read_timeout(Some(UPSTREAM_TIMEOUT))
+        .map_err(io_error)?;
+    stream
+        .set_write_timeout(Some(UPSTREAM_TIMEOUT))
+        .map_err(io_error)?;
+
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, target);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes()).map_err(io_error)?;
+    stream.write_all(body).map_err(io_error)?;
+    stream.flush().map_err(io_error)?;
+
+    read_upstream_response(stream, method == "HEAD").map_err(io_error)
+}
+
+fn response_header_pairs(response: &ResponseBox) -> Vec<(String, String)> {
+    response
+        .headers()
+        .iter()
+        .map(|h| (h.field.to_string(), h.value.to_string()))
+        .collect()
+}
+
+/// Forwards `request` to the route's upstream (after its delay), or answers with its status
+/// override, and captures the exchange.
+pub fn handle_proxy_request(request: &mut Request, route: &ProxyRoute) -> ResponseBox {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let url = request.url().to_string();
+    let request_headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .map(|h| (h.field.to_string(), h.value.to_string()))
+        .collect();
+    let mut request_body = Vec::new();
+    if request
+        .as_reader()
+        .take(MAX_PROXY_BODY_BYTES + 1)
+        .read_to_end(&mut request_body)
+        .is_err()
+    {
+        return create_error_response(StatusCode(400), "Failed to read request body").boxed();
+    }
+    let mut exchange = ProxyExchange {
+        time: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        route: route.prefix.clone(),
+        method: method.clone(),
+        path: url.clone(),
+        request_body: CapturedBody::capture(
+            &request_body,
+            header_value(&request_headers, "Content-Encoding").is_some(),
+        ),
+        request_headers: request_headers.clone(),
+        ..ProxyExchange::default()
+    };
+    if request_body.len() as u64 > MAX_PROXY_BODY_BYTES {
+        let message = "Request body too large";
+        exchange.error = Some(message.to_string());
+        let response = create_error_response(StatusCode(413), message).boxed();
+        return record_response(exchange, response, started);
+    }
+
+    if !route.delay.is_zero() {
+        thread::sleep(route.delay);
+    }
+
+    let response = if let Some(code) = route.status {
+        create_status_response(code).boxed()
+    } else {
+        let target = route.upstream_target(&url);
+        exchange.upstream_url = Some(format!("http://{}{}", route.upstream.authority(), target));
+        let original_host = header_value(&request_headers, "Host").map(str::to_string);
+        let mut forwarded: Vec<(String, String)> = request_headers
+            .iter()
+            .filter(|(name, _)| {
+                !is_hop_by_hop(name)
+                    && !["Host", "Content-Length", "Expect"]
+                        .iter()
+                        .any(|h| h.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+            .collect();
+        forwarded.insert(0, ("Host".to_string(), route.upstream.authority()));
+        if let Some(addr) = request.remote_addr() {
+            let forwarded_for = match header_value(&request_headers, "X-Forwarded-For") {
+                Some(previous) => format!("{}, {}", previous, addr.ip()),
+                None => addr.ip().to_string(),
+            };
+            forwarded.retain(|(name, _)| !name.eq_ignore_ascii_case("X-Forwarded-For"));
+            forwarded.push(("X-Forwarded-For".to_string(), forwarded_for));
+        }
+        if let Some(host) = original_host {
+            forwarded.push(("X-Forwarded-Host".to_string(), host));
+        }
+        let proto = if request.secure() { "https" } else { "http" };
+        forwarded.push(("X-Forwarded-Proto".to_string(), proto.to_string()));
+
+        let upstream_started = Instant::now();
+        let result = send_upstream(&route.upstream, &method, &target, &forwarded, &request_body);
+        exchange.upstream_ms = Some(upstream_started.elapsed().as_millis() as u64);
+        match result {
+            Ok(upstream) => {
+                let headers: Vec<Header> = upstream
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| {
+                        !is_hop_by_hop(name)
+                            && (method == "HEAD" || !name.eq_ignore_ascii_case("Content-Length"))
+                    })
+                    .filter_map(|(name, value)| {
+                        Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+                    })
+                    .collect();
+                exchange.response_body = CapturedBody::capture(
+                    &upstream.body,
+                    header_value(&upstream.headers, "Content-Encoding").is_some(),
+                );
+                let body_len = upstream.body.len();
+                Response::new(
+                    StatusCode(upstream.status),
+                    headers,
+                    std::io::Cursor::new(upstream.body),
+                    Some(body_len),
+                    None,
+                )
+                .boxed()
+            }
+            Err((status, message)) => {
+                eprintln!("proxy {}: {}", route.prefix, message);
+                exchange.error = Some(message.clone());
+                create_error_response(status, &message).boxed()
+            }
+        }
+    };
+
+    record_response(exchange, response, started)
+}
+
+/// Completes `exchange` with `response` and captures it.
+fn record_response(
+    mut exchange: ProxyExchange,
+    response: ResponseBox,
+    started: Instant,
+) -> ResponseBox {
+    exchange.status = response.status_code().0;
+    exchange.response_headers = response_header_pairs(&response);
+    exchange.duration_ms = started.elapsed().as_millis() as u64;
+    record_exchange(exchange);
+    response
+}
+
+/// Requests for the capture viewer, which are answered by the main server and never logged.
+pub fn is_proxy_viewer_request(url: &str) -> bool {
+    let path = url.split('?').next().unwrap_or(url);
+    path == VIEWER_PATH || path == EXCHANGES_PATH || path == EXCHANGES_HAR_PATH
+}
+
+fn har_headers(headers: &[(String, String)]) -> Vec<serde_json::Value> {
+    headers
+        .iter()
+        .map(|(name, value)| serde_json::json!({ "name": name, "value": value }))
+        .collect()
+}
+
+fn har_content_type(headers: &[(String, String)]) -> &str {
+    header_value(headers, "Content-Type").unwrap_or("")
+}
+
+/// Converts captured exchanges to a HAR 1.2 log. Bodies are only included when they were
+/// captured as text, and may be truncated.
+pub fn exchanges_to_har(exchanges: &[ProxyExchange]) -> serde_json::Value {
+    let entries: Vec<serde_json::Value> = exchanges
+        .iter()
+        .map(|e| {
+            let host = header_value(&e.request_headers, "Host").unwrap_or("localhost");
+            let mut request = serde_json::json!({
+                "method": e.method,
+                "url": format!("http://{}{}", host, e.path),
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": har_headers(&e.request_headers),
+                "queryString": [],
+                "headersSize": -1,
+                "bodySize": e.request_body.size,
+            });
+            if let Some(text) = &e.request_body.text
+                && e.request_body.size > 0
+            {
+                request["postData"] = serde_json::json!({
+                    "mimeType": har_content_type(&e.request_headers),
+                    "text": text,
+                });
+            }
+            let mut content = serde_json::json!({
+                "size": e.response_body.size,
+                "mimeType": har_content_type(&e.response_headers),
+            });
+            if let Some(text) = &e.response_body.text {
+                content["text"] = serde_json::json!(text);
+            }
+            serde_json::json!({
+                "startedDateTime": e.time,
+                "time": e.duration_ms,
+                "request": request,
+                "response": {
+                    "status": e.status,
+                    "statusText": crate::web::status_code::get_status_phrase(e.status),
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": har_headers(&e.response_headers),
+                    "content": content,
+                    "redirectURL": header_value(&e.response_headers, "Location").unwrap_or(""),
+                    "headersSize": -1,
+                    "bodySize": e.response_body.size,
+                },
+                "cache": {},
+                "timings": {
+                    "send": 0,
+                    "wait": e.upstream_ms.unwrap_or(e.duration_ms),
+                    "receive": 0,
+                },
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "mclocks", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    })
+}
+
+/// Serves the capture viewer page and the JSON feed it polls.
+pub fn handle_proxy_viewer_request(request: &Request) -> ResponseBox {
+    let url = request.url();
+    if url.split('?').next() == Some(EXCHANGES_HAR_PATH) {
+        let (exchanges, _) = recent_exchanges(0);
+        let body = serde_json::to_string_pretty(&exchanges_to_har(&exchanges))
+            .unwrap_or_else(|_| "{}".to_string());
+        let mut response = Response::from_string(body).boxed();
+        for (name, value) in [
+            ("Content-Type", "application/json"),
+            (
+                "Content-Disposition",
+                "attachment; filename=\"mclocks-proxy.har\"",
+            ),
+        ] {
+            if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                response.add_header(header);
+            }
+        }
+        return response;
+    }
+    if url.split('?').next() == Some(EXCHANGES_PATH) {
+        let since = url
+            .split_once('?')
+            .map(|(_, q)| q)
+            .unwrap_or("")
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == "since")
+            .and_then(|(_, v)| decode(v).ok()?.parse().ok())
+            .unwrap_or(0);
+        let (exchanges, next) = recent_exchanges(since);
+        let body = serde_json::json!({ "next": next, "exchanges": exchanges }).to_string();
+        let mut response = Response::from_string(body).boxed();
+        if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+            response.add_header(header);
+        }
+        return response;
+    }
+    if *request.method() != Method::Get && *request.method() != Method::Head {
+        return create_error_response(StatusCode(405), "Method Not Allowed").boxed();
+    }
+    let mut response = Response::from_string(PROXY_VIEWER_HTML)
+        .with_status_code(StatusCode(200))
+        .boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+const PROXY_VIEWER_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Proxy Capture - mclocks</title>
+<style>
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 13px; margin: 0; color: #222; background: #fafafa; display: flex; flex-direction: column; height: 100vh; }
+header { display: flex; gap: 12px; align-items: center; padding: 8px 12px; background: #fff; border-bottom: 1px solid #ddd; }
+header h1 { font-size: 14px; margin: 0 12px 0 0; }
+main { flex: 1; display: flex; min-height: 0; }
+#list { flex: 1; overflow: auto; border-right: 1px solid #ddd; }
+#detail { flex: 1; overflow: auto; padding: 8px 12px; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 3px 8px; border-bottom: 1px solid #eee; white-space: nowrap; }
+td.path { white-space: normal; word-break: break-all; }
+tbody tr { cursor: pointer; }
+tbody tr.selected { background: #ddf4ff; }
+tr.s2 td.status { color: #1a7f37; }
+tr.s3 td.status { color: #0969da; }
+tr.s4 td.status { color: #9a6700; }
+tr.s5 td.status { color: #cf222e; font-weight: bold; }
+h2 { font-size: 13px; margin: 12px 0 4px; }
+pre { white-space: pre-wrap; word-break: break-all; margin: 0; padding: 6px; background: #fff; border: 1px solid #eee; }
+@media (prefers-color-scheme: dark) {
+  body { color: #ddd; background: #1e1e1e; }
+  header { background: #252526; border-color: #333; }
+  #list, th, td { border-color: #333; }
+  tbody tr.selected { background: #264f78; }
+  pre { background: #252526; border-color: #333; }
+}
+</style>
+</head>
+<body>
+<header>
+<h1>Proxy Capture</h1>
+<label><input id="follow" type="checkbox" checked> follow</label>
+<button id="clear">clear</button>
+<a href="/_mclocks/proxy/exchanges.har">download HAR</a>
+<span id="count"></span>
+</header>
+<main>
+<div id="list">
+<table>
+<thead><tr><th>time</th><th>method</th><th>status</th><th>ms</th><th>path</th></tr></thead>
+<tbody id="rows"></tbody>
+</table>
+</div>
+<div id="detail">Select an exchange.</div>
+</main>
+<script>
+const rows = document.getElementById("rows");
+const detail = document.getElementById("detail");
+const follow = document.getElementById("follow");
+const count = document.getElementById("count");
+const exchanges = new Map();
+let next = 0;
+
+const cell = (text, className) => {
+  const td = document.createElement("td");
+  td.textContent = text;
+  if (className) td.className = className;
+  return td;
+};
+
+const section = (title, text) => {
+  const h2 = document.createElement("h2");
+  h2.textContent = title;
+  const pre = document.createElement("pre");
+  pre.textContent = text;
+  return [h2, pre];
+};
+
+const headerText = (headers) => headers.map(([name, value]) => name + ": " + value).join("\n");
+
+const bodyText = (body) => {
+  if (!body.size) return "(empty)";
+  if (body.text == null) return "(" + body.size + " bytes, binary or encoded)";
+  return body.text + (body.truncated ? "\n... (" + body.size + " bytes, truncated)" : "");
+};
+
+const show = (id) => {
+  const e = exchanges.get(id);
+  for (const tr of rows.children) tr.classList.toggle("selected", tr.dataset.id === String(id));
+  detail.replaceChildren(
+    ...section("Request", e.method + " " + e.path + (e.upstreamUrl ? "\n-> " + e.upstreamUrl : "")),
+    ...section("Request headers", headerText(e.requestHeaders)),
+    ...section("Request body", bodyText(e.requestBody)),
+    ...section("Response", e.status + " in " + e.durationMs + " ms" + (e.upstreamMs == null ? "" : " (upstream " + e.upstreamMs + " ms)") + (e.error ? "\n" + e.error : "")),
+    ...section("Response headers", headerText(e.responseHeaders)),
+    ...section("Response body", bodyText(e.responseBody)),
+  );
+};
+
+const render = (e) => {
+  const tr = document.createElement("tr");
+  tr.dataset.id = e.id;
+  tr.className = "s" + String(e.status).charAt(0);
+  tr.append(cell(e.time), cell(e.method), cell(e.status, "status"), cell(e.durationMs), cell(e.path, "path"));
+  tr.addEventListener("click", () => show(e.id));
+  return tr;
+};
+
+const poll = async () => {
+  try {
+    const res = await fetch("/_mclocks/proxy/exchanges?since=" + next);
+    const data = await res.json();
+    next = data.next;
+    for (const e of data.exchanges) {
+      exchanges.set(e.id, e);
+      rows.append(render(e));
+    }
+    count.textContent = rows.children.length + " exchanges";
+    if (follow.checked && data.exchanges.length) document.getElementById("list").scrollTop = 1e9;
+  } catch (_) {
+    // The server may be restarting; keep polling.
+  }
+};
+
+document.getElementById("clear").addEventListener("click", () => {
+  exchanges.clear();
+  rows.replaceChildren();
+  detail.textContent = "Select an exchange.";
+  count.textContent = "";
+});
+poll();
+setInterval(poll, 1000);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(prefix: &str, upstream: &str, strip_prefix: bool) -> ProxyRoute {
+        ProxyRoute {
+            prefix: prefix.to_string(),
+            upstream: parse_upstream(upstream).expect("parse upstream"),
+            strip_prefix,
+            delay: Duration::ZERO,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_upstream() {
+        assert_eq!(
+            parse_upstream("http://127.0.0.1:8080/v2/").unwrap(),
+            ProxyUpstream {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                base_path: "/v2".to_string(),
+            }
+        );
+        let ipv6 = parse_upstream("http://[::1]:9000").unwrap();
+        assert_eq!((ipv6.host.as_str(), ipv6.port), ("::1", 9000));
+        assert_eq!(ipv6.authority(), "[::1]:9000");
+        assert_eq!(parse_upstream("http://localhost").unwrap().port, 80);
+        assert!(parse_upstream("https://127.0.0.1:8443").is_err());
+        assert!(parse_upstream("http://127.0.0.1:port").is_err());
+        assert!(parse_upstream("http://127.0.0.1:8080/?a=1").is_err());
+    }
+
+    #[test]
+    fn test_find_proxy_route_and_target() {
+        let routes = vec![
+            route("/api", "http://127.0.0.1:8080", false),
+            route("/api/v2", "http://127.0.0.1:9090/next", true),
+        ];
+        let api = find_proxy_route(&routes, "/api/users?id=1").expect("route");
+        assert_eq!(api.upstream_target("/api/users?id=1"), "/api/users?id=1");
+        let v2 = find_proxy_route(&routes, "/api/v2/users").expect("route");
+        assert_eq!(v2.upstream.port, 9090);
+        assert_eq!(v2.upstream_target("/api/v2/users"), "/next/users");
+        assert_eq!(v2.upstream_target("/api/v2?x=1"), "/next/?x=1");
+        assert!(find_proxy_route(&routes, "/apix").is_none());
+        assert!(find_proxy_route(&routes, "/").is_none());
+    }
+
+    #[test]
+    fn test_read_chunked_body() {
+        let mut chunked = &b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n"[..];
+        assert_eq!(read_chunked_body(&mut chunked).unwrap(), b"hello world");
+
+        let mut huge = &b"1\r\na\r\nffffffffffffffff\r\n"[..];
+        let err = read_chunked_body(&mut huge).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("exceeds the proxy limit"));
+    }
+}
@@ -0,0 +1,471 @@
+//! `web.bin` request bins: `/bin/{name}` stores whatever is sent to it for the
+//! `/_mclocks/bin/{name}` history page, which follows new requests over the live-reload WebSocket.
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Read;
+use std::sync::{Mutex, OnceLock};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+use tokio::sync::broadcast;
+
+use super::common::create_error_response;
+use super::markdown_live_reload::register_bin_viewer_session;
+
+const BIN_PATH_PREFIX: &str = "/bin/";
+const VIEWER_PATH: &str = "/_mclocks/bin";
+/// Bodies longer than this are cut in the stored copy.
+const MAX_STORED_BODY_BYTES: usize = 1024 * 1024;
+const MAX_BIN_BODY_BYTES: u64 = 10 * 1024 * 1024;
+/// Bins kept at once; the one idle the longest is dropped first.
+const MAX_BINS: usize = 100;
+const MAX_BIN_NAME_LEN: usize = 64;
+
+/// Set from `web.bin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestBinSettings {
+    /// Requests kept per bin.
+    pub max_requests: usize,
+    /// Show an OS notification for every stored request.
+    pub notify: bool,
+}
+
+/// One request stored in a bin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinRequest {
+    pub id: u64,
+    pub bin: String,
+    pub time: String,
+    pub method: String,
+    /// Path after `/bin/{name}`, with the query.
+    pub path: String,
+    pub remote_addr: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body_size: usize,
+    /// UTF-8 body; `None` when the body is binary (see `body_base64`).
+    pub body: Option<String>,
+    pub body_base64: Option<String>,
+    pub body_truncated: bool,
+}
+
+type BinNotifier = Box<dyn Fn(&BinRequest) + Send + Sync>;
+
+#[derive(Default)]
+struct BinStore {
+    settings: Option<RequestBinSettings>,
+    bins: BTreeMap<String, VecDeque<BinRequest>>,
+    next_id: u64,
+}
+
+fn store() -> &'static Mutex<BinStore> {
+    static STORE: OnceLock<Mutex<BinStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(BinStore::default()))
+}
+
+fn events() -> &'static broadcast::Sender<BinRequest> {
+    static EVENTS: OnceLock<broadcast::Sender<BinRequest>> = OnceLock::new();
+    EVENTS.get_or_init(|| broadcast::channel(64).0)
+}
+
+fn notifier() -> &'static Mutex<Option<BinNotifier>> {
+    static NOTIFIER: OnceLock<Mutex<Option<BinNotifier>>> = OnceLock::new();
+    NOTIFIER.get_or_init(|| Mutex::new(None))
+}
+
+/// Enables, resizes or (with `None`) disables the bins. Stored requests survive reconfiguration.
+pub fn configure_request_bin(settings: Option<RequestBinSettings>) {
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    store.settings = settings;
+    match settings {
+        Some(settings) => {
+            for requests in store.bins.values_mut() {
+                while requests.len() > settings.max_requests {
+                    requests.pop_front();
+                }
+            }
+        }
+        None => store.bins.clear(),
+    }
+}
+
+/// Installs the callback used for `web.bin.notify`; the app shows an OS notification with it.
+pub fn set_bin_notifier(notify: impl Fn(&BinRequest) + Send + Sync + 'static) {
+    if let Ok(mut slot) = notifier().lock() {
+        *slot = Some(Box::new(notify));
+    }
+}
+
+/// New requests across all bins, for the WebSocket feed.
+pub fn subscribe_bin_requests() -> broadcast::Receiver<BinRequest> {
+    events().subscribe()
+}
+
+pub fn is_valid_bin_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_BIN_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Splits `/bin/{name}/rest?query` into the bin name and `/rest?query`.
+fn split_bin_url(url: &str) -> Option<(&str, String)> {
+    let rest = url.strip_prefix(BIN_PATH_PREFIX)?;
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (name, tail) = rest.split_at(end);
+    let tail = if tail.starts_with('/') {
+        tail.to_string()
+    } else {
+        format!("/{}", tail)
+    };
+    Some((name, tail))
+}
+
+fn store_request(request: BinRequest) -> Option<BinRequest> {
+    let mut store = store().lock().ok()?;
+    let max_requests = store.settings?.max_requests;
+    let mut request = request;
+    request.id = store.next_id;
+    store.next_id += 1;
+    if !store.bins.contains_key(&request.bin) && store.bins.len() >= MAX_BINS {
+        let idle = store
+            .bins
+            .iter()
+            .min_by_key(|(_, requests)| requests.back().map_or(0, |r| r.id))
+            .map(|(name, _)| name.clone());
+        if let Some(idle) = idle {
+            store.bins.remove(&idle);
+        }
+    }
+    let requests = store.bins.entry(request.bin.clone()).or_default();
+    while requests.len() >= max_requests {
+        requests.pop_front();
+    }
+    requests.push_back(request.clone());
+    let notify = store.settings.is_some_and(|s| s.notify);
+    drop(store);
+
+    let _ = events().send(request.clone());
+    if notify
+        && let Ok(notifier) = notifier().lock()
+        && let Some(notify) = notifier.as_ref()
+    {
+        notify(&request);
+    }
+    Some(request)
+}
+
+fn json_response(status: u16, value: serde_json::Value) -> ResponseBox {
+    let mut response = Response::from_string(value.to_string())
+        .with_status_code(StatusCode(status))
+        .boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+/// Stores a request sent to `/bin/{name}`. Returns `None` for other paths and while bins are off.
+pub fn handle_bin_request(request: &mut Request) -> Option<ResponseBox> {
+    let (name, path) = split_bin_url(request.url())?;
+    store().lock().ok()?.settings?;
+    if !is_valid_bin_name(name) {
+        return Some(
+            create_error_response(
+                StatusCode(400),
+                "Bin names use letters, digits, '-' and '_' (up to 64 characters)",
+            )
+            .boxed(),
+        );
+    }
+    let name = name.to_string();
+    let mut body = Vec::new();
+    if request
+        .as_reader()
+        .take(MAX_BIN_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .is_err()
+    {
+        return Some(create_error_response(StatusCode(400), "Failed to read request body").boxed());
+    }
+    if body.len() as u64 > MAX_BIN_BODY_BYTES {
+        return Some(create_error_response(StatusCode(413), "Request body too large").boxed());
+    }
+    let body_size = body.len();
+    let body_truncated = body_size > MAX_STORED_BODY_BYTES;
+    body.truncate(MAX_STORED_BODY_BYTES);
+    let (text, base64) = match String::from_utf8(body) {
+        Ok(text) => (Some(text), None),
+        Err(e) => (None, Some(general_purpose::STANDARD.encode(e.into_bytes()))),
+    };
+    let stored = store_request(BinRequest {
+        id: 0,
+        bin: name.clone(),
+        time: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        method: request.method().to_string(),
+        path,
+        remote_addr: request.remote_addr().map(|addr| addr.to_string()),
+        headers: request
+            .headers()
+            .iter()
+            .map(|h| (h.field.to_string(), h.value.to_string()))
+            .collect(),
+        body_size,
+        body: text.filter(|t| !t.is_empty()),
+        body_base64: base64,
+        body_truncated,
+    })?;
+    Some(json_response(
+        200,
+        serde_json::json!({ "ok": true, "bin": name, "id": stored.id }),
+    ))
+}
+
+/// Requests for the history pages, which are answered by the main server and never logged.
+pub fn is_bin_viewer_request(url: &str) -> bool {
+    let path = url.split('?').next().unwrap_or(url);
+    path == VIEWER_PATH
+        || path
+            .strip_prefix(VIEWER_PATH)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn query_since(url: &str) -> u64 {
+    url.split_once('?')
+        .map(|(_, q)| q)
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == "since")
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0)
+}
+
+fn html_response(html: String) -> ResponseBox {
+    let mut response = Response::from_string(html).boxed();
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]) {
+        response.add_header(header);
+    }
+    response
+}
+
+/// Serves `/_mclocks/bin` (the list of bins), `/_mclocks/bin/{name}` (the history page) and
+/// `/_mclocks/bin/{name}/requests` (JSON; `DELETE` empties the bin).
+pub fn handle_bin_viewer_request(request: &Request, ws_port: Option<u16>) -> ResponseBox {
+    let url = request.url();
+    let path = url.split('?').next().unwrap_or(url);
+    let Ok(mut store) = store().lock() else {
+        return create_error_response(StatusCode(500), "Internal Server Error").boxed();
+    };
+    if store.settings.is_none() {
+        return create_error_response(StatusCode(404), "Request bins are disabled (web.bin)")
+            .boxed();
+    }
+    let rest = path
+        .strip_prefix(VIEWER_PATH)
+        .unwrap_or("")
+        .trim_matches('/');
+    if rest.is_empty() {
+        let items: String = store
+            .bins
+            .iter()
+            .rev()
+            .map(|(name, requests)| {
+                format!(
+                    "<li><a href=\"/_mclocks/bin/{0}\">{0}</a> ({1})</li>",
+                    name,
+                    requests.len()
+                )
+            })
+            .collect();
+        let items = if items.is_empty() {
+            "<li>No requests yet. Send one to <code>/bin/{name}</code>.</li>".to_string()
+        } else {
+            items
+        };
+        return html_response(BIN_INDEX_HTML.replace("{{items}}", &items));
+    }
+    let (name, requests_path) = match rest.split_once('/') {
+        Some((name, tail)) => (name, Some(tail)),
+        None => (rest, None),
+    };
+    if !is_valid_bin_name(name) {
+        return create_error_response(StatusCode(404), "Not Found").boxed();
+    }
+    match requests_path {
+        None => {
+            // The page may only subscribe with the token issued here, so other origins
+            // cannot read captured requests over the WebSocket.
+            let (ws_port, ws_token) = ws_port
+                .zip(register_bin_viewer_session(name))
+                .map_or(("0".to_string(), String::new()), |(port, token)| {
+                    (port.to_string(), token)
+                });
+            html_response(
+                BIN_VIEWER_HTML
+                    .replace("{{name}}", name)
+                    .replace("{{wsPort}}", &ws_port)
+                    .replace("{{wsToken}}", &ws_token),
+            )
+        }
+        Some("requests") if *request.method() == Method::Delete => {
+            store.bins.remove(name);
+            json_response(200, serde_json::json!({ "ok": true }))
+        }
+        Some("requests") => {
+            let since = query_since(url);
+            let requests: Vec<&BinRequest> = store
+                .bins
+                .get(name)
+                .map(|requests| requests.iter().filter(|r| r.id >= since).collect())
+                .unwrap_or_default();
+            json_response(
+                200,
+                serde_json::json!({ "next": store.next_id, "requests": requests }),
+            )
+        }
+        Some(_) => create_error_response(StatusCode(404), "Not Found").boxed(),
+    }
+}
+
+const BIN_INDEX_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Request Bins - mclocks</title>
+<style>
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 13px; margin: 16px; color: #222; background: #fafafa; }
+@media (prefers-color-scheme: dark) { body { color: #ddd; background: #1e1e1e; } a { color: #4daafc; } }
+</style>
+</head>
+<body>
+<h1>Request Bins</h1>
+<ul>{{items}}</ul>
+</body>
+</html>
+"##;
+
+const BIN_VIEWER_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{name}} - Request Bin - mclocks</title>
+<style>
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 13px; margin: 0; color: #222; background: #fafafa; }
+header { position: sticky; top: 0; display: flex; gap: 12px; align-items: center; padding: 8px 12px; background: #fff; border-bottom: 1px solid #ddd; }
+header h1 { font-size: 14px; margin: 0 12px 0 0; }
+#status { color: #888; }
+article { margin: 12px; padding: 8px 12px; background: #fff; border: 1px solid #ddd; }
+article h2 { font-size: 13px; margin: 0 0 6px; }
+article .meta { color: #888; font-weight: normal; }
+pre { white-space: pre-wrap; word-break: break-all; margin: 4px 0; }
+@media (prefers-color-scheme: dark) {
+  body { color: #ddd; background: #1e1e1e; }
+  header, article { background: #252526; border-color: #333; }
+}
+</style>
+</head>
+<body>
+<header>
+<h1>Request Bin: {{name}}</h1>
+<button id="clear">clear</button>
+<span id="count"></span>
+<span id="status"></span>
+</header>
+<main id="requests"></main>
+<script>
+const NAME = "{{name}}";
+const WS_PORT = {{wsPort}};
+const WS_TOKEN = "{{wsToken}}";
+const list = document.getElementById("requests");
+const count = document.getElementById("count");
+const status = document.getElementById("status");
+const seen = new Set();
+let next = 0;
+
+const el = (tag, text, className) => {
+  const node = document.createElement(tag);
+  if (text != null) node.textContent = text;
+  if (className) node.className = className;
+  return node;
+};
+
+const add = (r) => {
+  if (seen.has(r.id)) return;
+  seen.add(r.id);
+  next = Math.max(next, r.id + 1);
+  const article = el("article");
+  const title = el("h2", r.method + " " + r.path + " ");
+  title.append(el("span", r.time + (r.remoteAddr ? " from " + r.remoteAddr : ""), "meta"));
+  const headers = r.headers.map(([name, value]) => name + ": " + value).join("\n");
+  let body = r.body != null ? r.body : r.bodyBase64 != null ? "(base64) " + r.bodyBase64 : "(empty)";
+  if (r.bodyTruncated) body += "\n... (" + r.bodySize + " bytes, truncated)";
+  article.append(title, el("pre", headers), el("pre", body));
+  list.prepend(article);
+  count.textContent = seen.size + " requests";
+};
+
+const load = async () => {
+  try {
+    const res = await fetch("/_mclocks/bin/" + NAME + "/requests?since=" + next);
+    const data = await res.json();
+    data.requests.forEach(add);
+    next = Math.max(next, data.next);
+  } catch (_) {
+    // The server may be restarting; try again on the next tick.
+  }
+};
+
+const connect = () => {
+  if (!WS_PORT) {
+    status.textContent = "polling";
+    setInterval(load, 2000);
+    return;
+  }
+  const ws = new WebSocket("ws://127.0.0.1:" + WS_PORT + "/bin/" + NAME + "/" + WS_TOKEN);
+  ws.onopen = () => { status.textContent = "live"; load(); };
+  ws.onmessage = (e) => add(JSON.parse(e.data));
+  ws.onclose = () => { status.textContent = "reconnecting"; setTimeout(connect, 2000); };
+};
+
+document.getElementById("clear").addEventListener("click", async () => {
+  await fetch("/_mclocks/bin/" + NAME + "/requests", { method: "DELETE" });
+  list.replaceChildren();
+  seen.clear();
+  count.textContent = "";
+});
+load().then(connect);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_bin_url() {
+        assert_eq!(
+            split_bin_url("/bin/github/hook?x=1"),
+            Some(("github", "/hook?x=1".to_string()))
+        );
+        assert_eq!(
+            split_bin_url("/bin/github"),
+            Some(("github", "/".to_string()))
+        );
+        assert_eq!(
+            split_bin_url("/bin/github?x=1"),
+            Some(("github", "/?x=1".to_string()))
+        );
+        assert_eq!(split_bin_url("/binary"), None);
+        assert!(is_valid_bin_name("stripe-test_1"));
+        assert!(!is_valid_bin_name("a.b"));
+        assert!(!is_valid_bin_name(""));
+    }
+}
@@ -0,0 +1,262 @@
+//! `web.headers` rules and the `web.cors` policy, applied to every main-server response.
+use tiny_http::{Header, Method, Request, Response, ResponseBox, StatusCode};
+use urlencoding::decode;
+
+/// Headers set on responses whose URL path matches `pattern` (a glob, see [`glob_matches`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderRule {
+    pub pattern: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// Cross-origin policy. Set from `web.cors`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsSettings {
+    /// Exact origins such as `http://localhost:5173`; `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// `*` allows whatever the preflight asks for.
+    pub allowed_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Seconds a browser may cache a preflight result.
+    pub max_age: u64,
+}
+
+/// Extra response headers for one listener.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseHeaderSettings {
+    /// Applied in order, so a later rule overrides a header set by an earlier one.
+    pub rules: Vec<HeaderRule>,
+    pub cors: Option<CorsSettings>,
+}
+
+impl CorsSettings {
+    /// `Access-Control-Allow-Origin` value for `origin`, or `None` when it is not allowed.
+    /// Credentialed responses can't use `*`, so wildcard matches echo the origin then.
+    fn allow_origin_value(&self, origin: &str) -> Option<String> {
+        let origin = origin.trim();
+        if origin.is_empty() {
+            return None;
+        }
+        if self.allowed_origins.iter().any(|o| o == "*") {
+            return Some(if self.allow_credentials {
+                origin.to_string()
+            } else {
+                "*".to_string()
+            });
+        }
+        self.allowed_origins
+            .iter()
+            .any(|o| o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+            .then(|| origin.to_string())
+    }
+
+    fn origin_headers(&self, allow_origin: String) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if allow_origin != "*" {
+            headers.push(("Vary".to_string(), "Origin".to_string()));
+        }
+        headers.push(("Access-Control-Allow-Origin".to_string(), allow_origin));
+        if self.allow_credentials {
+            headers.push((
+                "Access-Control-Allow-Credentials".to_string(),
+                "true".to_string(),
+            ));
+        }
+        headers
+    }
+}
+
+/// Matches a URL path against a glob: `*` and `?` stay within one path segment, `**` spans
+/// segments. `/fixtures/**` matches everything below `/fixtures/`.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+            [b'*', rest @ ..] => {
+                let segment_len = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+                (0..=segment_len).any(|i| matches(rest, &path[i..]))
+            }
+            [b'?', rest @ ..] => {
+                path.first().is_some_and(|&c| c != b'/') && matches(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+fn request_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+fn build_header(name: &str, value: &str) -> Option<Header> {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+}
+
+/// Answers a CORS preflight (`OPTIONS` with `Origin` and `Access-Control-Request-Method`) from an
+/// allowed origin. Other requests, and preflights from other origins, go on to the route.
+pub fn handle_cors_preflight(
+    request: &Request,
+    settings: &ResponseHeaderSettings,
+) -> Option<ResponseBox> {
+    let cors = settings.cors.as_ref()?;
+    if *request.method() != Method::Options
+        || request_header(request, "Access-Control-Request-Method").is_none()
+    {
+        return None;
+    }
+    let allow_origin = cors.allow_origin_value(request_header(request, "Origin")?)?;
+    let allow_headers = if cors.allowed_headers.iter().any(|h| h == "*") {
+        request_header(request, "Access-Control-Request-Headers")
+            .unwrap_or("")
+            .to_string()
+    } else {
+        cors.allowed_headers.join(", ")
+    };
+
+    let mut headers = cors.origin_headers(allow_origin);
+    headers.push((
+        "Access-Control-Allow-Methods".to_string(),
+        cors.allowed_methods.join(", "),
+    ));
+    if !allow_headers.is_empty() {
+        headers.push(("Access-Control-Allow-Headers".to_string(), allow_headers));
+    }
+    headers.push((
+        "Access-Control-Max-Age".to_string(),
+        cors.max_age.to_string(),
+    ));
+
+    let mut response = Response::empty(StatusCode(204)).boxed();
+    for (name, value) in headers {
+        if let Some(header) = build_header(&name, &value) {
+            response.add_header(header);
+        }
+    }
+    Some(response)
+}
+
+/// Sets the matching `web.headers` rules and, for allowed origins, the CORS headers on `response`.
+/// Headers already on the response with the same name are replaced.
+pub fn apply_response_headers(
+    request: &Request,
+    response: ResponseBox,
+    settings: &ResponseHeaderSettings,
+) -> ResponseBox {
+    let raw_path = request.url().split('?').next().unwrap_or("/");
+    let path = decode(raw_path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| raw_path.to_string());
+
+    let mut overrides: Vec<(String, String)> = Vec::new();
+    for rule in settings
+        .rules
+        .iter()
+        .filter(|r| glob_matches(&r.pattern, &path))
+    {
+        for (name, value) in &rule.headers {
+            overrides.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+            overrides.push((name.clone(), value.clone()));
+        }
+    }
+    let mut appended: Vec<(String, String)> = Vec::new();
+    if let Some(cors) = &settings.cors
+        && let Some(allow_origin) =
+            request_header(request, "Origin").and_then(|origin| cors.allow_origin_value(origin))
+    {
+        for (name, value) in cors.origin_headers(allow_origin) {
+            // `Vary` is added next to any existing one instead of replacing it.
+            if name == "Vary" {
+                appended.push((name, value));
+            } else {
+                overrides.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+                overrides.push((name, value));
+            }
+        }
+        if !cors.expose_headers.is_empty() {
+            overrides.push((
+                "Access-Control-Expose-Headers".to_string(),
+                cors.expose_headers.join(", "),
+            ));
+        }
+    }
+    if overrides.is_empty() && appended.is_empty() {
+        return response;
+    }
+
+    let status = response.status_code();
+    let data_length = response.data_length();
+    let chunked_threshold = response.chunked_threshold();
+    let mut headers: Vec<Header> = response
+        .headers()
+        .iter()
+        .filter(|h| {
+            !overrides
+                .iter()
+                .any(|(name, _)| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .collect();
+    headers.extend(
+        overrides
+            .iter()
+            .chain(appended.iter())
+            .filter_map(|(name, value)| build_header(name, value)),
+    );
+    Response::new(status, headers, response.into_reader(), data_length, None)
+        .with_chunked_threshold(chunked_threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("/fixtures/**", "/fixtures/a/b.json"));
+        assert!(glob_matches("/fixtures/**", "/fixtures/"));
+        assert!(!glob_matches("/fixtures/**", "/fixtures"));
+        assert!(glob_matches("/*.json", "/data.json"));
+        assert!(!glob_matches("/*.json", "/nested/data.json"));
+        assert!(glob_matches("/**/*.wasm", "/pkg/app_bg.wasm"));
+        assert!(glob_matches("/v?/users", "/v1/users"));
+        assert!(!glob_matches("/v?/users", "/v10/users"));
+        assert!(glob_matches("**", "/anything/at/all"));
+    }
+
+    #[test]
+    fn test_cors_allow_origin_value() {
+        let mut cors = CorsSettings {
+            allowed_origins: vec!["http://localhost:5173/".to_string()],
+            allowed_methods: vec!["GET".to_string()],
+            allowed_headers: vec!["*".to_string()],
+            expose_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: 600,
+        };
+        assert_eq!(
+            cors.allow_origin_value("http://localhost:5173"),
+            Some("http://localhost:5173".to_string())
+        );
+        assert_eq!(cors.allow_origin_value("http://localhost:3000"), None);
+        assert_eq!(cors.allow_origin_value(""), None);
+
+        cors.allowed_origins = vec!["*".to_string()];
+        assert_eq!(
+            cors.allow_origin_value("http://localhost:3000"),
+            Some("*".to_string())
+        );
+        cors.allow_credentials = true;
+        assert_eq!(
+            cors.allow_origin_value("http://localhost:3000"),
+            Some("http://localhost:3000".to_string())
+        );
+    }
+}
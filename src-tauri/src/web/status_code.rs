@@ -76,11 +76,21 @@ pub fn should_have_response_body(code: u16) -> bool {
     }
 }
 
+/// Values that replace the defaults `apply_status_headers` uses, e.g. from `/status/{code}?location=`.
+/// An override is also sent for a status that would not add the header on its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusHeaderOverrides {
+    pub location: Option<String>,
+    pub retry_after: Option<String>,
+    pub www_authenticate: Option<String>,
+}
+
 pub fn apply_status_headers(
     mut response: Response<Cursor<Vec<u8>>>,
     status_code: u16,
     _status: StatusCode,
     _has_body: bool,
+    overrides: &StatusHeaderOverrides,
 ) -> Response<Cursor<Vec<u8>>> {
     // Helper function to add header
     let add_header = |response: Response<Cursor<Vec<u8>>>,
@@ -98,11 +108,16 @@ pub fn apply_status_headers(
     match status_code {
         // 3xx Redirection - Location header required
         301 | 302 | 303 | 305 | 307 | 308 => {
-            response = add_header(response, b"Location", b"/");
+            let location = overrides.location.as_deref().unwrap_or("/");
+            response = add_header(response, b"Location", location.as_bytes());
         }
         // 401 Unauthorized - WWW-Authenticate required
         401 => {
-            response = add_header(response, b"WWW-Authenticate", b"Basic realm=\"test\"");
+            let challenge = overrides
+                .www_authenticate
+                .as_deref()
+                .unwrap_or("Basic realm=\"test\"");
+            response = add_header(response, b"WWW-Authenticate", challenge.as_bytes());
         }
         // 402 Payment Required - no special header
         402 => {}
@@ -160,7 +175,8 @@ pub fn apply_status_headers(
         428 => {}
         // 429 Too Many Requests - Retry-After recommended
         429 => {
-            response = add_header(response, b"Retry-After", b"60");
+            let retry_after = overrides.retry_after.as_deref().unwrap_or("60");
+            response = add_header(response, b"Retry-After", retry_after.as_bytes());
         }
         // 431 Request Header Fields Too Large - no special header
         431 => {}
@@ -174,7 +190,8 @@ pub fn apply_status_headers(
         502 => {}
         // 503 Service Unavailable - Retry-After recommended
         503 => {
-            response = add_header(response, b"Retry-After", b"60");
+            let retry_after = overrides.retry_after.as_deref().unwrap_or("60");
+            response = add_header(response, b"Retry-After", retry_after.as_bytes());
         }
         // 504 Gateway Timeout - no special header
         504 => {}
@@ -190,7 +207,11 @@ pub fn apply_status_headers(
         510 => {}
         // 511 Network Authentication Required - WWW-Authenticate required
         511 => {
-            response = add_header(response, b"WWW-Authenticate", b"Basic realm=\"network\"");
+            let challenge = overrides
+                .www_authenticate
+                .as_deref()
+                .unwrap_or("Basic realm=\"network\"");
+            response = add_header(response, b"WWW-Authenticate", challenge.as_bytes());
         }
         // 1xx Informational
         100..=199 => {}
@@ -203,5 +224,18 @@ pub fn apply_status_headers(
         _ => {}
     }
 
+    for (name, value) in [
+        ("Location", &overrides.location),
+        ("Retry-After", &overrides.retry_after),
+        ("WWW-Authenticate", &overrides.www_authenticate),
+    ] {
+        let Some(value) = value else {
+            continue;
+        };
+        if !response.headers().iter().any(|h| h.field.equiv(name)) {
+            response = add_header(response, name.as_bytes(), value.as_bytes());
+        }
+    }
+
     response
 }
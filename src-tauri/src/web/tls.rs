@@ -0,0 +1,338 @@
+//! Development CA and server certificate for the HTTPS listener (`web.tls`).
+//!
+//! The CA key and certificate are generated once and kept, so trusting `ca.pem` in the OS or
+//! browser keeps working; the server certificate is re-issued whenever its host names change.
+//! Either one is re-issued when it is about to expire. The CA is name-constrained to the
+//! certificate's host names, so a leaked `ca-key.pem` can't sign for other sites; a new CA is
+//! generated when a host it doesn't cover is added.
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, CidrSubnet, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, GeneralSubtree, IsCa, KeyPair, KeyUsagePurpose, NameConstraints,
+    date_time_ymd,
+};
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tiny_http::{Header, Response, ResponseBox};
+
+/// Where the main listeners offer the CA certificate for download.
+pub const DEV_CA_PATH: &str = "/_mclocks/ca.pem";
+const CA_COMMON_NAME: &str = "mclocks Development CA";
+const CA_CERT_FILE: &str = "ca.pem";
+const CA_KEY_FILE: &str = "ca-key.pem";
+const SERVER_CERT_FILE: &str = "server.pem";
+const SERVER_KEY_FILE: &str = "server-key.pem";
+/// Host names the current server certificate was issued for, one per line.
+const SERVER_HOSTS_FILE: &str = "server-hosts.txt";
+/// Host names every server certificate covers, before `web.tls.hosts`.
+const DEFAULT_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+/// Stored certificates expiring within this many days are re-issued.
+const RENEW_BEFORE_DAYS: u64 = 30;
+const CA_VALID_DAYS: u64 = 20 * 365;
+/// Browsers reject server certificates valid for more than 398 days.
+const SERVER_VALID_DAYS: u64 = 397;
+
+/// PEM material for one HTTPS listener.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TlsCertificate {
+    pub ca_cert_pem: String,
+    pub server_cert_pem: String,
+    pub server_key_pem: String,
+}
+
+impl std::fmt::Debug for TlsCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsCertificate").finish_non_exhaustive()
+    }
+}
+
+impl TlsCertificate {
+    pub fn ssl_config(&self) -> tiny_http::SslConfig {
+        tiny_http::SslConfig {
+            certificate: self.server_cert_pem.clone().into_bytes(),
+            private_key: self.server_key_pem.clone().into_bytes(),
+        }
+    }
+}
+
+/// The name constraint that lets the CA sign for `host`.
+fn permitted_subtree(host: &str) -> GeneralSubtree {
+    match host.parse::<IpAddr>() {
+        Ok(ip) => {
+            let prefix = if ip.is_ipv4() { 32 } else { 128 };
+            GeneralSubtree::IpAddress(CidrSubnet::from_addr_prefix(ip, prefix))
+        }
+        Err(_) => GeneralSubtree::DnsName(host.to_string()),
+    }
+}
+
+fn ca_params(hosts: &[String]) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, CA_COMMON_NAME);
+    name.push(DnType::OrganizationName, "mclocks");
+    params.distinguished_name = name;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: hosts.iter().map(|host| permitted_subtree(host)).collect(),
+        excluded_subtrees: Vec::new(),
+    });
+    set_validity(&mut params, CA_VALID_DAYS);
+    params
+}
+
+/// Makes `params` valid from yesterday (to allow for clock skew) for `days` days from today.
+fn set_validity(params: &mut CertificateParams, days: u64) {
+    let ymd = |date: NaiveDate| date_time_ymd(date.year(), date.month() as u8, date.day() as u8);
+    let today = Utc::now().date_naive();
+    params.not_before = ymd(today - Days::new(1));
+    params.not_after = ymd(today + Days::new(days));
+}
+
+/// Whether `cert_pem` can't be read or expires within `RENEW_BEFORE_DAYS`.
+fn needs_renewal(cert_pem: &str) -> bool {
+    let renew_at = Utc::now() + Days::new(RENEW_BEFORE_DAYS);
+    CertificateParams::from_ca_cert_pem(cert_pem)
+        .map(|params| params.not_after.unix_timestamp() <= renew_at.timestamp())
+        .unwrap_or(true)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes a private key, readable only by the current user on Unix.
+fn write_key_file(path: &Path, contents: &str) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| {
+                // `mode` only applies when the file is created; also tighten older key files.
+                file.set_permissions(fs::Permissions::from_mode(0o600))?;
+                file.write_all(contents.as_bytes())
+            })
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+    #[cfg(not(unix))]
+    write_file(path, contents)
+}
+
+/// Whether the CA in `cert_pem` is constrained to names that include every one of `hosts`.
+fn ca_covers_hosts(cert_pem: &str, hosts: &[String]) -> bool {
+    let Ok(Some(constraints)) =
+        CertificateParams::from_ca_cert_pem(cert_pem).map(|params| params.name_constraints)
+    else {
+        return false;
+    };
+    hosts.iter().all(|host| {
+        constraints
+            .permitted_subtrees
+            .contains(&permitted_subtree(host))
+    })
+}
+
+/// Loads the CA from `dir`, generating it on first use or when it doesn't cover `hosts`. The
+/// issuer object is rebuilt from the stored key with the same name, so it signs certificates
+/// that chain to the stored `ca.pem`.
+fn load_or_create_ca(
+    dir: &Path,
+    hosts: &[String],
+) -> Result<(String, Certificate, KeyPair, bool), String> {
+    let cert_path = dir.join(CA_CERT_FILE);
+    let key_path = dir.join(CA_KEY_FILE);
+    let stored = fs::read_to_string(&cert_path)
+        .ok()
+        .zip(fs::read_to_string(&key_path).ok());
+    if let Some((cert_pem, key_pem)) = stored
+        && !needs_renewal(&cert_pem)
+        && ca_covers_hosts(&cert_pem, hosts)
+        && let Ok(key) = KeyPair::from_pem(&key_pem)
+    {
+        let issuer = ca_params(hosts)
+            .self_signed(&key)
+            .map_err(|e| format!("Failed to load development CA: {}", e))?;
+        return Ok((cert_pem, issuer, key, false));
+    }
+    let key = KeyPair::generate().map_err(|e| format!("Failed to generate CA key: {}", e))?;
+    let cert = ca_params(hosts)
+        .self_signed(&key)
+        .map_err(|e| format!("Failed to generate development CA: {}", e))?;
+    let cert_pem = cert.pem();
+    write_key_file(&key_path, &key.serialize_pem())?;
+    write_file(&cert_path, &cert_pem)?;
+    Ok((cert_pem, cert, key, true))
+}
+
+/// Host names for the server certificate: the defaults plus `extra_hosts`, without duplicates.
+pub fn server_cert_hosts(extra_hosts: &[String]) -> Vec<String> {
+    let mut hosts: Vec<String> = DEFAULT_HOSTS.iter().map(|h| h.to_string()).collect();
+    for host in extra_hosts {
+        let host = host.trim().to_ascii_lowercase();
+        if !host.is_empty() && !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+/// Returns the CA and a server certificate for `hosts` stored in `dir`, creating what is missing.
+pub fn ensure_dev_certificate(dir: &Path, hosts: &[String]) -> Result<TlsCertificate, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let (ca_cert_pem, issuer, issuer_key, ca_created) = load_or_create_ca(dir, hosts)?;
+
+    let cert_path = dir.join(SERVER_CERT_FILE);
+    let key_path = dir.join(SERVER_KEY_FILE);
+    let hosts_path = dir.join(SERVER_HOSTS_FILE);
+    let hosts_list = hosts.join("\n");
+    if !ca_created
+        && fs::read_to_string(&hosts_path).ok().as_deref() == Some(hosts_list.as_str())
+        && let (Ok(server_cert_pem), Ok(server_key_pem)) = (
+            fs::read_to_string(&cert_path),
+            fs::read_to_string(&key_path),
+        )
+        && !needs_renewal(&server_cert_pem)
+    {
+        return Ok(TlsCertificate {
+            ca_cert_pem,
+            server_cert_pem,
+            server_key_pem,
+        });
+    }
+
+    let mut params = CertificateParams::new(hosts.to_vec())
+        .map_err(|e| format!("Invalid web.tls host name: {}", e))?;
+    params.distinguished_name.push(
+        DnType::CommonName,
+        hosts.first().map(String::as_str).unwrap_or("localhost"),
+    );
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyEncipherment,
+    ];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    set_validity(&mut params, SERVER_VALID_DAYS);
+    let key = KeyPair::generate().map_err(|e| format!("Failed to generate server key: {}", e))?;
+    let cert = params
+        .signed_by(&key, &issuer, &issuer_key)
+        .map_err(|e| format!("Failed to issue server certificate: {}", e))?;
+    let certificate = TlsCertificate {
+        ca_cert_pem,
+        server_cert_pem: cert.pem(),
+        server_key_pem: key.serialize_pem(),
+    };
+    write_key_file(&key_path, &certificate.server_key_pem)?;
+    write_file(&cert_path, &certificate.server_cert_pem)?;
+    write_file(&hosts_path, &hosts_list)?;
+    Ok(certificate)
+}
+
+pub fn dev_ca_dir(config_app_dir: &Path) -> PathBuf {
+    config_app_dir.join("tls")
+}
+
+/// Serves the CA certificate so it can be imported into a trust store.
+pub fn create_dev_ca_response(ca_cert_pem: &str) -> ResponseBox {
+    let mut response = Response::from_string(ca_cert_pem).boxed();
+    for (name, value) in [
+        ("Content-Type", "application/x-pem-file"),
+        (
+            "Content-Disposition",
+            "attachment; filename=\"mclocks-dev-ca.pem\"",
+        ),
+    ] {
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_dev_certificate_keeps_ca() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let hosts = server_cert_hosts(&["mybox.lan".to_string(), "LOCALHOST".to_string()]);
+        assert_eq!(hosts, ["localhost", "127.0.0.1", "::1", "mybox.lan"]);
+
+        let first = ensure_dev_certificate(temp_dir.path(), &hosts).expect("create");
+        let again = ensure_dev_certificate(temp_dir.path(), &hosts).expect("load");
+        assert_eq!(first, again, "stored certificates are reused");
+
+        let other_hosts = server_cert_hosts(&[]);
+        let reissued = ensure_dev_certificate(temp_dir.path(), &other_hosts).expect("reissue");
+        assert_eq!(reissued.ca_cert_pem, first.ca_cert_pem);
+        assert_ne!(reissued.server_cert_pem, first.server_cert_pem);
+
+        let constraints = CertificateParams::from_ca_cert_pem(&first.ca_cert_pem)
+            .expect("parse CA")
+            .name_constraints
+            .expect("name constraints");
+        assert!(
+            constraints
+                .permitted_subtrees
+                .contains(&GeneralSubtree::DnsName("mybox.lan".to_string()))
+        );
+        assert!(!ca_covers_hosts(
+            &first.ca_cert_pem,
+            &server_cert_hosts(&["other.lan".to_string()])
+        ));
+        let new_host = server_cert_hosts(&["other.lan".to_string()]);
+        let new_ca = ensure_dev_certificate(temp_dir.path(), &new_host).expect("new CA");
+        assert_ne!(new_ca.ca_cert_pem, first.ca_cert_pem);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in [CA_KEY_FILE, SERVER_KEY_FILE] {
+                let metadata = fs::metadata(temp_dir.path().join(file)).expect("metadata");
+                assert_eq!(metadata.permissions().mode() & 0o777, 0o600, "{}", file);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ensure_dev_certificate_renews_expiring_certificates() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let hosts = server_cert_hosts(&[]);
+        let first = ensure_dev_certificate(temp_dir.path(), &hosts).expect("create");
+        assert!(!needs_renewal(&first.ca_cert_pem));
+        assert!(!needs_renewal(&first.server_cert_pem));
+        assert!(needs_renewal("not a certificate"));
+
+        let expiring = |days: u64| {
+            let mut params = CertificateParams::new(hosts.clone()).expect("params");
+            set_validity(&mut params, days);
+            let key = KeyPair::generate().expect("key");
+            params.self_signed(&key).expect("self-sign").pem()
+        };
+        assert!(needs_renewal(&expiring(RENEW_BEFORE_DAYS - 1)));
+        assert!(!needs_renewal(&expiring(RENEW_BEFORE_DAYS + 1)));
+
+        fs::write(temp_dir.path().join(SERVER_CERT_FILE), expiring(1)).expect("write");
+        let renewed = ensure_dev_certificate(temp_dir.path(), &hosts).expect("renew server");
+        assert_eq!(renewed.ca_cert_pem, first.ca_cert_pem);
+        assert!(!needs_renewal(&renewed.server_cert_pem));
+
+        fs::write(temp_dir.path().join(CA_CERT_FILE), expiring(1)).expect("write");
+        let renewed = ensure_dev_certificate(temp_dir.path(), &hosts).expect("renew CA");
+        assert!(!needs_renewal(&renewed.ca_cert_pem));
+        assert_ne!(renewed.server_cert_pem, first.server_cert_pem);
+    }
+}
@@ -1,15 +1,57 @@
+use chrono::{Local, SecondsFormat};
 use directories::BaseDirs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::sync::atomic::AtomicBool;
-use std::{fs, net::TcpListener, path::PathBuf, thread};
-use tiny_http::Server;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use std::{
+    fs,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    thread,
+};
+use tiny_http::{Request, Server, StatusCode};
 
 use crate::config::{AppConfig, get_config_app_path, parse_config_json_to_value};
 use crate::util::open_with_system_command;
-use crate::web::handler_static::handle_web_request;
-
-#[derive(Serialize, Deserialize, Debug)]
+use crate::web::access_guard::{AccessGuardSettings, guard_request, strip_query_token};
+use crate::web::access_log::{
+    AccessLogEntry, AccessLogSettings, configure_access_log, handle_access_log_request,
+    is_access_log_request, record_access,
+};
+use crate::web::common::create_error_response;
+use crate::web::compression::{CompressionSettings, compress_response};
+use crate::web::handler_auth::AuthEndpointSettings;
+use crate::web::handler_fault::{
+    configure_fault_listener, fault_listener_port, write_fault_response,
+};
+use crate::web::handler_routes::{MockRoute, RouteBody, parse_route_path};
+use crate::web::handler_slow::{MAX_SLOW_SECONDS, stream_drip_response};
+use crate::web::handler_static::{WebResponse, handle_web_request};
+use crate::web::handler_status_random::{
+    StatusProfile, build_status_profile, configure_status_profiles, is_valid_profile_name,
+};
+use crate::web::mock::{MockSettings, MockUnmatched, handle_mock_request, load_mock_exchanges};
+use crate::web::proxy::{
+    ProxyRoute, find_proxy_route, handle_proxy_request, handle_proxy_viewer_request,
+    is_proxy_viewer_request, parse_upstream,
+};
+use crate::web::request_bin::{
+    RequestBinSettings, configure_request_bin, handle_bin_request, handle_bin_viewer_request,
+    is_bin_viewer_request,
+};
+use crate::web::response_headers::{
+    CorsSettings, HeaderRule, ResponseHeaderSettings, apply_response_headers, handle_cors_preflight,
+};
+use crate::web::tls::{
+    DEV_CA_PATH, TlsCertificate, create_dev_ca_response, dev_ca_dir, ensure_dev_certificate,
+    server_cert_hosts,
+};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct EditorConfig {
     #[serde(default)]
@@ -22,7 +64,7 @@ pub struct EditorConfig {
     pub args: Option<Vec<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct WebMarkdownConfig {
     #[serde(
@@ -41,25 +83,234 @@ pub struct WebMarkdownConfig {
     pub enable_preview_api: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct WebAssetsConfig {
     #[serde(default)]
     pub port: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCompressionConfig {
+    #[serde(default = "df_compression_enabled")]
+    pub enabled: bool,
+    #[serde(default = "df_compression_min_bytes")]
+    pub min_bytes: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebAccessLogConfig {
+    #[serde(default = "df_access_log_enabled")]
+    pub enabled: bool,
+    #[serde(default = "df_access_log_max_bytes")]
+    #[schemars(range(min = 1))]
+    pub max_bytes: u64,
+    #[serde(default = "df_access_log_max_files")]
+    pub max_files: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebTlsConfig {
+    #[serde(default = "df_tls_enabled")]
+    pub enabled: bool,
+    #[serde(default = "df_tls_port", deserialize_with = "deserialize_web_port")]
+    #[schemars(range(min = MIN_WEB_PORT))]
+    pub port: u16,
+    /// Extra host names or IP addresses the server certificate is issued for.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSecurityConfig {
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// A `web.headers` entry: headers set on responses whose URL path matches the `path` glob.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebHeaderRuleConfig {
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// A `web.routes` entry: a fake endpoint answering `method` + `path` with a fixed response.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebRouteConfig {
+    /// `*` matches any method.
+    #[serde(default = "df_route_method")]
+    pub method: String,
+    /// Path with optional `:name` parameters and a trailing `*`, e.g. `/api/users/:id`.
+    pub path: String,
+    #[serde(default = "df_route_status")]
+    #[schemars(range(min = 100, max = 599))]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// File (relative to `web.root`) whose contents are the body template.
+    #[serde(default)]
+    pub body_file: Option<String>,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+/// A `web.proxy` entry: requests under `path` are forwarded to `upstream`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebProxyConfig {
+    pub path: String,
+    /// Plain-HTTP base URL such as `http://127.0.0.1:8080`.
+    pub upstream: String,
+    #[serde(default)]
+    pub strip_prefix: bool,
+    /// Latency added before forwarding, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Answer with this status (as `/status/{code}` does) instead of forwarding.
+    #[serde(default)]
+    #[schemars(range(min = 100, max = 599))]
+    pub status: Option<u16>,
+}
+
+/// What `web.mock` does with requests that match no recording.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WebMockUnmatched {
+    #[default]
+    NotFound,
+    Passthrough,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebMockConfig {
+    /// A HAR file, a recording (`.json`), or a directory of them.
+    pub source: String,
+    /// URL prefix the recordings are replayed under.
+    #[serde(default = "df_mock_path")]
+    pub path: String,
+    #[serde(default)]
+    pub unmatched: WebMockUnmatched,
+    #[serde(default)]
+    pub ignore_query: Vec<String>,
+    #[serde(default)]
+    pub match_headers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebBinConfig {
+    #[serde(default = "df_bin_enabled")]
+    pub enabled: bool,
+    /// Requests kept per bin; older ones are dropped.
+    #[serde(default = "df_bin_max_requests")]
+    #[schemars(range(min = 1, max = MAX_BIN_REQUESTS))]
+    pub max_requests: usize,
+    /// Show an OS notification when a request lands in a bin.
+    #[serde(default)]
+    pub notify: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthConfig {
+    #[serde(default = "df_auth_enabled")]
+    pub enabled: bool,
+    /// Token `/bearer` accepts; without it any token is accepted and echoed.
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebStatusProfileConfig {
+    /// Status code to relative weight, e.g. `{"200": 80, "500": 15, "503": 5}`.
+    pub weights: BTreeMap<String, u32>,
+    /// Makes the sequence of statuses reproducible from startup or a counter reset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCorsConfig {
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "df_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    #[serde(default = "df_cors_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    #[serde(default = "df_cors_max_age")]
+    pub max_age: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct WebContentConfig {
     #[serde(default)]
     pub markdown: Option<WebMarkdownConfig>,
+    #[serde(default = "df_source_max_bytes")]
+    pub source_max_bytes: u64,
+}
+
+/// How a mounted file is shown when the request has no `?mode=`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WebMountMode {
+    #[default]
+    Content,
+    Source,
+    Raw,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebMountOptions {
+    pub path: String,
+    #[serde(default = "df_mount_listing")]
+    pub listing: bool,
+    #[serde(default = "df_mount_show_hidden")]
+    pub show_hidden: bool,
+    #[serde(default)]
+    pub default_mode: WebMountMode,
+    #[serde(default)]
+    pub index: bool,
+    #[serde(default)]
+    pub spa_fallback: Option<String>,
+    #[serde(default)]
+    pub not_found_page: Option<String>,
+}
+
+/// A `web.mounts` value: either just the directory, or the directory with options.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(untagged)]
+pub enum WebMountConfig {
+    Path(String),
+    Options(WebMountOptions),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct WebConfig {
     pub root: String,
     #[serde(default = "df_web_port", deserialize_with = "deserialize_web_port")]
+    #[schemars(range(min = MIN_WEB_PORT))]
     pub port: u16,
     #[serde(default = "df_open_browser_at_start")]
     pub open_browser_at_start: bool,
@@ -69,12 +320,62 @@ pub struct WebConfig {
     pub slow: bool,
     #[serde(default = "df_status")]
     pub status: bool,
+    #[serde(default = "df_fault")]
+    pub fault: bool,
+    /// Plain-HTTP port where `/fault/close` and `/fault/reset` end the connection.
+    #[serde(default = "df_fault_port", deserialize_with = "deserialize_web_port")]
+    #[schemars(range(min = MIN_WEB_PORT))]
+    pub fault_port: u16,
+    #[serde(default = "df_httpbin")]
+    pub httpbin: bool,
+    #[serde(default = "df_cookies_and_caching")]
+    pub cookies_and_caching: bool,
+    #[serde(default)]
+    pub auth: Option<WebAuthConfig>,
+    #[serde(default = "df_workers", deserialize_with = "deserialize_web_workers")]
+    #[schemars(range(min = 1, max = MAX_WEB_WORKERS))]
+    pub workers: usize,
+    #[serde(default)]
+    pub compression: Option<WebCompressionConfig>,
+    #[serde(default)]
+    pub access_log: Option<WebAccessLogConfig>,
+    #[serde(default)]
+    pub security: Option<WebSecurityConfig>,
+    #[serde(default)]
+    pub tls: Option<WebTlsConfig>,
+    #[serde(default)]
+    pub headers: Vec<WebHeaderRuleConfig>,
+    #[serde(default)]
+    pub cors: Option<WebCorsConfig>,
+    #[serde(default)]
+    pub routes: Vec<WebRouteConfig>,
+    #[serde(default)]
+    pub proxy: Vec<WebProxyConfig>,
+    #[serde(default)]
+    pub mock: Option<WebMockConfig>,
+    #[serde(default)]
+    pub bin: Option<WebBinConfig>,
+    /// Named weight sets for `/status/random/{name}`.
+    #[serde(default)]
+    pub status_profiles: BTreeMap<String, WebStatusProfileConfig>,
     #[serde(default)]
     pub content: Option<WebContentConfig>,
     #[serde(default)]
     pub assets: Option<WebAssetsConfig>,
     #[serde(default)]
     pub editor: Option<EditorConfig>,
+    /// URL prefix (e.g. `/docs`) to directory, served next to `root` on the main port.
+    #[serde(default)]
+    pub mounts: BTreeMap<String, WebMountConfig>,
+    /// Serve `index.html` instead of the listing for directories that have one.
+    #[serde(default)]
+    pub index: bool,
+    /// File under `root` served for unknown paths, for client-side (history API) routing.
+    #[serde(default)]
+    pub spa_fallback: Option<String>,
+    /// File under `root` served with status 404 for unknown paths.
+    #[serde(default)]
+    pub not_found_page: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -100,6 +401,36 @@ pub struct WebMarkdownHighlightConfig {
     pub mermaid_js_url: String,
 }
 
+/// How a served directory tree answers directory and unknown-path requests.
+/// Paths are resolved inside the root when the config is loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebRootOptions {
+    pub index: bool,
+    pub spa_fallback: Option<PathBuf>,
+    pub not_found_page: Option<PathBuf>,
+}
+
+/// A resolved `web.mounts` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebMount {
+    /// Normalized URL prefix: starts with `/`, no trailing `/`, never `/` itself.
+    pub prefix: String,
+    pub root: PathBuf,
+    /// When `false`, directories under the mount are not listed.
+    pub listing: bool,
+    /// When `false`, dot-files are left out of listings and are not served.
+    pub show_hidden: bool,
+    pub default_mode: WebMountMode,
+    pub options: WebRootOptions,
+}
+
+/// HTTPS listener serving the same routes as the main one. Set from `web.tls`.
+#[derive(Debug, Clone)]
+pub struct WebTlsServerConfig {
+    pub port: u16,
+    pub certificate: TlsCertificate,
+}
+
 #[derive(Debug, Clone)]
 pub struct WebAssetsServerConfig {
     pub root: String,
@@ -114,10 +445,43 @@ pub struct WebServerConfig {
     pub dump: bool,
     pub slow: bool,
     pub status: bool,
+    /// Enables the `/fault/…` endpoints that send broken responses.
+    pub fault: bool,
+    /// Port of the listener sending `close` and `reset`; `None` when `fault` is disabled.
+    pub fault_port: Option<u16>,
+    /// Enables the httpbin-style utility endpoints (`/headers`, `/bytes/{n}`, …).
+    pub httpbin: bool,
+    /// Enables `/cookies`, `/cookies/set`, `/cookies/delete`, `/cache/{seconds}` and `/etag/{etag}`.
+    pub cookies_and_caching: bool,
+    /// `/basic-auth`, `/bearer` and `/digest-auth`; `None` when `web.auth` is absent or disabled.
+    pub auth: Option<AuthEndpointSettings>,
+    /// Number of worker threads handling requests in parallel on each listener.
+    pub workers: usize,
+    /// gzip/brotli negotiation for text-like responses on both Main and Assets listeners.
+    pub compression: CompressionSettings,
+    /// `web.headers` rules and the `web.cors` policy for the main listener.
+    pub response_headers: ResponseHeaderSettings,
+    /// Request log shared by both listeners; `None` when `web.accessLog.enabled` is `false`.
+    pub access_log: Option<AccessLogSettings>,
+    /// Host/Origin allowlist and access token checked on the main listener. Set from `web.security`.
+    pub access_guard: AccessGuardSettings,
+    /// `web.routes` fake endpoints, checked before mounts, debug endpoints and files.
+    pub routes: Vec<MockRoute>,
+    /// `web.proxy` routes, checked on the main listener before any other route.
+    pub proxy_routes: Vec<ProxyRoute>,
+    /// Recorded exchanges replayed from `web.mock`, checked before the proxy routes.
+    pub mock: Option<Arc<MockSettings>>,
+    /// `/bin/{name}` request bins on the main listener; `None` when `web.bin` is absent or disabled.
+    pub request_bin: Option<RequestBinSettings>,
+    /// `web.statusProfiles` served at `/status/random/{name}` when `status` is enabled.
+    pub status_profiles: BTreeMap<String, StatusProfile>,
     pub allow_html_in_md: bool,
     pub markdown_open_external_link_in_new_tab: bool,
+    /// Files larger than this are not loaded into the source views. Set from `web.content.sourceMaxBytes`.
+    pub source_max_bytes: u64,
     pub markdown_highlight: Option<WebMarkdownHighlightConfig>,
     pub assets_server: Option<WebAssetsServerConfig>,
+    pub tls_server: Option<WebTlsServerConfig>,
     /// Local WebSocket port for rendered-Markdown live reload (below assets port when present).
     pub markdown_live_reload_ws_port: Option<u16>,
     pub editor_repos_dir: Option<String>,
@@ -126,6 +490,10 @@ pub struct WebServerConfig {
     pub editor_args: Vec<String>,
     /// When `true`, the main web server handles `POST /preview`. Set from `web.content.markdown.enablePreviewApi` at startup.
     pub local_preview_api_enabled: Arc<AtomicBool>,
+    /// Extra directories served under URL prefixes on the main server. Set from `web.mounts`.
+    pub mounts: Vec<WebMount>,
+    /// `web.index` / `web.spaFallback` / `web.notFoundPage` for `root`.
+    pub root_options: WebRootOptions,
 }
 
 fn df_web_port() -> u16 {
@@ -143,12 +511,92 @@ fn df_slow() -> bool {
 fn df_status() -> bool {
     false
 }
+fn df_fault() -> bool {
+    false
+}
+fn df_fault_port() -> u16 {
+    3031
+}
+fn df_httpbin() -> bool {
+    false
+}
+fn df_cookies_and_caching() -> bool {
+    false
+}
+fn df_workers() -> usize {
+    8
+}
+fn df_compression_enabled() -> bool {
+    true
+}
+fn df_compression_min_bytes() -> usize {
+    1024
+}
+fn df_compression_settings() -> CompressionSettings {
+    CompressionSettings {
+        enabled: df_compression_enabled(),
+        min_bytes: df_compression_min_bytes(),
+    }
+}
+fn df_route_method() -> String {
+    "GET".to_string()
+}
+fn df_route_status() -> u16 {
+    200
+}
+fn df_mock_path() -> String {
+    "/".to_string()
+}
+fn df_bin_enabled() -> bool {
+    true
+}
+fn df_auth_enabled() -> bool {
+    true
+}
+fn df_bin_max_requests() -> usize {
+    100
+}
+fn df_tls_enabled() -> bool {
+    true
+}
+fn df_tls_port() -> u16 {
+    3443
+}
+fn df_access_log_enabled() -> bool {
+    true
+}
+fn df_access_log_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+fn df_access_log_max_files() -> usize {
+    5
+}
+fn df_cors_allowed_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]
+        .map(String::from)
+        .to_vec()
+}
+fn df_cors_allowed_headers() -> Vec<String> {
+    vec!["*".to_string()]
+}
+fn df_cors_max_age() -> u64 {
+    600
+}
 fn df_allow_html_in_md() -> bool {
     false
 }
 fn df_markdown_open_external_link_in_new_tab() -> bool {
     true
 }
+pub(crate) fn df_source_max_bytes() -> u64 {
+    32 * 1024 * 1024
+}
+fn df_mount_listing() -> bool {
+    true
+}
+fn df_mount_show_hidden() -> bool {
+    true
+}
 
 const MIN_WEB_PORT: u16 = 2000;
 
@@ -166,6 +614,27 @@ where
     Ok(port)
 }
 
+const MAX_WEB_WORKERS: usize = 64;
+const MAX_BIN_REQUESTS: usize = 10_000;
+const TLS_REBIND_ATTEMPTS: usize = 20;
+const TLS_REBIND_WAIT: std::time::Duration = std::time::Duration::from_millis(50);
+/// Shortest `web.security.token` accepted; it guards process-launching endpoints.
+const MIN_ACCESS_TOKEN_LEN: usize = 16;
+
+fn deserialize_web_workers<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let workers = usize::deserialize(deserializer)?;
+    if workers == 0 || workers > MAX_WEB_WORKERS {
+        return Err(serde::de::Error::custom(format!(
+            "web.workers must be between 1 and {}",
+            MAX_WEB_WORKERS
+        )));
+    }
+    Ok(workers)
+}
+
 fn is_local_port_available(port: u16) -> bool {
     TcpListener::bind(("127.0.0.1", port)).is_ok()
 }
@@ -190,12 +659,22 @@ fn main_http_bind_addr() -> &'static str {
 
 fn tcp_bind_ip_for_listen_kind(listen_kind: WebServerListenKind) -> &'static str {
     match listen_kind {
-        WebServerListenKind::Main => main_http_bind_addr(),
+        WebServerListenKind::Main | WebServerListenKind::MainTls => main_http_bind_addr(),
         WebServerListenKind::Assets => "127.0.0.1",
     }
 }
 
-fn find_available_port_downward(start_port: u16, min_port: u16, role: &str) -> Result<u16, String> {
+/// A port is usable when it is free or already bound by one of our own running listeners.
+fn is_port_usable(port: u16, held_ports: &[u16]) -> bool {
+    held_ports.contains(&port) || is_local_port_available(port)
+}
+
+fn find_available_port_downward(
+    start_port: u16,
+    min_port: u16,
+    role: &str,
+    held_ports: &[u16],
+) -> Result<u16, String> {
     if start_port < min_port {
         return Err(format!(
             "Failed to resolve {} port: start port {} is below minimum {}",
@@ -204,7 +683,7 @@ fn find_available_port_downward(start_port: u16, min_port: u16, role: &str) -> R
     }
     let mut candidate = start_port;
     loop {
-        if is_local_port_available(candidate) {
+        if is_port_usable(candidate, held_ports) {
             return Ok(candidate);
         }
         if candidate == min_port {
@@ -275,6 +754,8 @@ const MCLOCKS_ASSETS_VERSION: &str = "20260508-19";
 #[derive(Clone, Copy, Debug)]
 pub enum WebServerListenKind {
     Main,
+    /// HTTPS twin of `Main` with the same routes and settings.
+    MainTls,
     Assets,
 }
 
@@ -282,6 +763,7 @@ impl WebServerListenKind {
     const fn log_label(self) -> &'static str {
         match self {
             WebServerListenKind::Main => "Main Server",
+            WebServerListenKind::MainTls => "Main Server (TLS)",
             WebServerListenKind::Assets => "Assets Server",
         }
     }
@@ -289,7 +771,7 @@ impl WebServerListenKind {
     /// Parenthetical purpose in the startup log when the document root exists.
     const fn log_purpose_with_root(self) -> &'static str {
         match self {
-            WebServerListenKind::Main => "User web root",
+            WebServerListenKind::Main | WebServerListenKind::MainTls => "User web root",
             WebServerListenKind::Assets => "Bundled app static assets",
         }
     }
@@ -297,88 +779,538 @@ impl WebServerListenKind {
     /// Parenthetical purpose when there is no on-disk root (temp-share only).
     const fn log_purpose_no_root(self) -> &'static str {
         match self {
-            WebServerListenKind::Main => "Temp-share only",
+            WebServerListenKind::Main | WebServerListenKind::MainTls => "Temp-share only",
             WebServerListenKind::Assets => "Bundled assets",
         }
     }
+
+    const fn url_scheme(self) -> &'static str {
+        match self {
+            WebServerListenKind::MainTls => "https",
+            WebServerListenKind::Main | WebServerListenKind::Assets => "http",
+        }
+    }
+
+    /// Main and MainTls serve the user routes; Assets only bundled files.
+    const fn serves_main_routes(self) -> bool {
+        !matches!(self, WebServerListenKind::Assets)
+    }
+}
+
+/// Per-request settings of one listener. Swapped in place when config.json changes.
+#[derive(Debug, Clone)]
+pub struct WebListenerSettings {
+    pub root: PathBuf,
+    pub dump: bool,
+    pub slow: bool,
+    pub status: bool,
+    pub fault: bool,
+    pub httpbin: bool,
+    pub cookies_and_caching: bool,
+    pub auth: Option<AuthEndpointSettings>,
+    pub allow_html_in_md: bool,
+    pub markdown_open_external_link_in_new_tab: bool,
+    pub markdown_highlight: Option<WebMarkdownHighlightConfig>,
+    pub editor_repos_dir: Option<String>,
+    pub editor_include_host: bool,
+    pub editor_command: String,
+    pub editor_args: Vec<String>,
+    pub markdown_live_reload_ws_port: Option<u16>,
+    pub local_preview_api: Option<Arc<AtomicBool>>,
+    pub source_max_bytes: u64,
+    pub compression: CompressionSettings,
+    pub response_headers: ResponseHeaderSettings,
+    pub access_guard: AccessGuardSettings,
+    /// Development CA offered at `/_mclocks/ca.pem` while the TLS listener is enabled.
+    pub dev_ca_pem: Option<String>,
+    pub routes: Vec<MockRoute>,
+    pub proxy_routes: Vec<ProxyRoute>,
+    pub mock: Option<Arc<MockSettings>>,
+    pub mounts: Vec<WebMount>,
+    pub root_options: WebRootOptions,
+}
+
+impl WebServerConfig {
+    pub fn main_listener_settings(&self) -> WebListenerSettings {
+        WebListenerSettings {
+            root: PathBuf::from(&self.root),
+            dump: self.dump,
+            slow: self.slow,
+            status: self.status,
+            fault: self.fault,
+            httpbin: self.httpbin,
+            cookies_and_caching: self.cookies_and_caching,
+            auth: self.auth.clone(),
+            allow_html_in_md: self.allow_html_in_md,
+            markdown_open_external_link_in_new_tab: self.markdown_open_external_link_in_new_tab,
+            markdown_highlight: self.markdown_highlight.clone(),
+            editor_repos_dir: self.editor_repos_dir.clone(),
+            editor_include_host: self.editor_include_host,
+            editor_command: self.editor_command.clone(),
+            editor_args: self.editor_args.clone(),
+            markdown_live_reload_ws_port: self.markdown_live_reload_ws_port,
+            local_preview_api: Some(self.local_preview_api_enabled.clone()),
+            source_max_bytes: self.source_max_bytes,
+            compression: self.compression,
+            response_headers: self.response_headers.clone(),
+            access_guard: self.access_guard.clone(),
+            dev_ca_pem: self
+                .tls_server
+                .as_ref()
+                .map(|tls| tls.certificate.ca_cert_pem.clone()),
+            routes: self.routes.clone(),
+            proxy_routes: self.proxy_routes.clone(),
+            mock: self.mock.clone(),
+            mounts: self.mounts.clone(),
+            root_options: self.root_options.clone(),
+        }
+    }
+
+    /// Settings for the HTTPS listener: the main listener's, behind the development certificate.
+    pub fn tls_listener_settings(&self) -> Option<(u16, &TlsCertificate, WebListenerSettings)> {
+        let tls_server = self.tls_server.as_ref()?;
+        Some((
+            tls_server.port,
+            &tls_server.certificate,
+            self.main_listener_settings(),
+        ))
+    }
+
+    /// Settings for the bundled assets listener: static files only, no debug endpoints.
+    pub fn assets_listener_settings(&self) -> Option<(u16, WebListenerSettings)> {
+        let assets_server = self.assets_server.as_ref()?;
+        Some((
+            assets_server.port,
+            WebListenerSettings {
+                root: PathBuf::from(&assets_server.root),
+                dump: false,
+                slow: false,
+                status: false,
+                fault: false,
+                httpbin: false,
+                cookies_and_caching: false,
+                auth: None,
+                allow_html_in_md: false,
+                markdown_open_external_link_in_new_tab: true,
+                markdown_highlight: None,
+                editor_repos_dir: None,
+                editor_include_host: false,
+                editor_command: "code".to_string(),
+                editor_args: vec!["-g".to_string(), "{file}:{line}".to_string()],
+                markdown_live_reload_ws_port: None,
+                local_preview_api: None,
+                source_max_bytes: self.source_max_bytes,
+                compression: self.compression,
+                response_headers: ResponseHeaderSettings::default(),
+                access_guard: AccessGuardSettings::default(),
+                dev_ca_pem: None,
+                routes: Vec::new(),
+                proxy_routes: Vec::new(),
+                mock: None,
+                mounts: Vec::new(),
+                root_options: WebRootOptions::default(),
+            },
+        ))
+    }
+}
+
+type RequestHandler = dyn Fn(&mut Request) -> WebResponse + Send + Sync;
+
+/// A bound listener and its worker pool. Dropping the handle leaves the listener running.
+pub struct WebServerHandle {
+    server: Arc<Server>,
+    port: u16,
+    /// Certificate the listener was bound with; a new one needs a re-bind.
+    certificate: Option<TlsCertificate>,
+    workers: usize,
+    label: &'static str,
+    settings: Arc<RwLock<Arc<WebListenerSettings>>>,
+    handler: Arc<RequestHandler>,
+}
+
+impl WebServerHandle {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Applies new settings to subsequent requests and grows or shrinks the worker pool.
+    /// In-flight requests finish with the settings they started with.
+    pub fn update(&mut self, settings: WebListenerSettings, workers: usize) {
+        match self.settings.write() {
+            Ok(mut guard) => *guard = Arc::new(settings),
+            Err(poisoned) => *poisoned.into_inner() = Arc::new(settings),
+        }
+        let workers = workers.max(1);
+        if workers > self.workers {
+            spawn_request_workers(
+                Arc::clone(&self.server),
+                workers - self.workers,
+                Arc::clone(&self.handler),
+                self.label,
+            );
+        } else {
+            for _ in workers..self.workers {
+                self.server.unblock();
+            }
+        }
+        self.workers = workers;
+    }
+
+    /// Stops accepting requests. The port is released once in-flight requests complete.
+    pub fn stop(self) {
+        for _ in 0..self.workers {
+            self.server.unblock();
+        }
+    }
 }
 
+/// Binds `port` and serves it on a pool of `workers` threads until the returned handle is stopped.
 pub fn start_web_server(
-    root: String,
+    settings: WebListenerSettings,
     port: u16,
-    dump_enabled: bool,
-    slow_enabled: bool,
-    status_enabled: bool,
-    allow_html_in_md: bool,
-    markdown_open_external_link_in_new_tab: bool,
-    markdown_highlight: Option<WebMarkdownHighlightConfig>,
-    editor_repos_dir: Option<String>,
-    editor_include_host: bool,
-    editor_command: String,
-    editor_args: Vec<String>,
-    markdown_live_reload_ws_port: Option<u16>,
-    local_preview_api: Option<Arc<AtomicBool>>,
+    workers: usize,
     listen_kind: WebServerListenKind,
-) {
-    thread::spawn(move || {
-        let bind_ip = tcp_bind_ip_for_listen_kind(listen_kind);
-        let server = match Server::http(format!("{}:{}", bind_ip, port)) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Failed to start web server on port {}: {}", port, e);
-                return;
-            }
-        };
+) -> Result<WebServerHandle, String> {
+    bind_web_server(settings, port, workers, listen_kind, None)
+}
 
-        let root_path = PathBuf::from(root);
-        let label = listen_kind.log_label();
-        if bind_ip != "127.0.0.1" && matches!(listen_kind, WebServerListenKind::Main) {
-            eprintln!(
-                "Main Server listens on {}:{} (reachable from WSL when loopback forwarding is unavailable). Firewall may apply.",
-                bind_ip, port
-            );
+/// Like [`start_web_server`] for the HTTPS listener, presenting `certificate`.
+pub fn start_tls_web_server(
+    settings: WebListenerSettings,
+    port: u16,
+    workers: usize,
+    certificate: &TlsCertificate,
+) -> Result<WebServerHandle, String> {
+    bind_web_server(
+        settings,
+        port,
+        workers,
+        WebServerListenKind::MainTls,
+        Some(certificate),
+    )
+}
+
+fn bind_web_server(
+    settings: WebListenerSettings,
+    port: u16,
+    workers: usize,
+    listen_kind: WebServerListenKind,
+    certificate: Option<&TlsCertificate>,
+) -> Result<WebServerHandle, String> {
+    let bind_ip = tcp_bind_ip_for_listen_kind(listen_kind);
+    let addr = format!("{}:{}", bind_ip, port);
+    let server = match certificate {
+        Some(certificate) => Server::https(addr, certificate.ssl_config()),
+        None => Server::http(addr),
+    }
+    .map_err(|e| format!("Failed to start web server on port {}: {}", port, e))?;
+
+    let label = listen_kind.log_label();
+    if bind_ip != "127.0.0.1" && listen_kind.serves_main_routes() {
+        eprintln!(
+            "{} listens on {}:{} (reachable from WSL when loopback forwarding is unavailable). Firewall may apply.",
+            label, bind_ip, port
+        );
+    }
+    let scheme = listen_kind.url_scheme();
+    if settings.root.exists() && settings.root.is_dir() {
+        println!(
+            "{}: {}://localhost:{} ({}) {}",
+            label,
+            scheme,
+            port,
+            listen_kind.log_purpose_with_root(),
+            settings.root.display()
+        );
+    } else {
+        println!(
+            "{}: {}://localhost:{} ({}) {}",
+            label,
+            scheme,
+            port,
+            listen_kind.log_purpose_no_root(),
+            "-"
+        );
+    }
+
+    let settings = Arc::new(RwLock::new(Arc::new(settings)));
+    let handler_settings = Arc::clone(&settings);
+    let handler = move |request: &mut Request| {
+        let s = match handler_settings.read() {
+            Ok(guard) => Arc::clone(&guard),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        };
+        if listen_kind.serves_main_routes()
+            && let Some(rejected) = guard_request(request, &s.access_guard)
+        {
+            return WebResponse::Handled(rejected);
+        }
+        if listen_kind.serves_main_routes() && is_access_log_request(request.url()) {
+            return WebResponse::Handled(handle_access_log_request(request));
+        }
+        if listen_kind.serves_main_routes() && is_proxy_viewer_request(request.url()) {
+            return WebResponse::Handled(handle_proxy_viewer_request(request));
+        }
+        if listen_kind.serves_main_routes() && is_bin_viewer_request(request.url()) {
+            return WebResponse::Handled(handle_bin_viewer_request(
+                request,
+                s.markdown_live_reload_ws_port,
+            ));
+        }
+        if let Some(ca_cert_pem) = &s.dev_ca_pem
+            && request.url().split('?').next() == Some(DEV_CA_PATH)
+        {
+            return WebResponse::Handled(create_dev_ca_response(ca_cert_pem));
+        }
+        if let Some(preflight) = handle_cors_preflight(request, &s.response_headers) {
+            return WebResponse::Handled(preflight);
+        }
+        if listen_kind.serves_main_routes()
+            && let Some(response) = handle_bin_request(request)
+        {
+            return WebResponse::Handled(apply_response_headers(
+                request,
+                response,
+                &s.response_headers,
+            ));
+        }
+        if let Some(mock) = &s.mock
+            && let Some(response) = handle_mock_request(request, mock)
+        {
+            return WebResponse::Handled(apply_response_headers(
+                request,
+                response,
+                &s.response_headers,
+            ));
+        }
+        if let Some(route) = find_proxy_route(&s.proxy_routes, request.url()) {
+            let response = handle_proxy_request(request, route);
+            return WebResponse::Handled(apply_response_headers(
+                request,
+                response,
+                &s.response_headers,
+            ));
+        }
+        match handle_web_request(request, &s, port) {
+            WebResponse::Handled(response) => {
+                let response = apply_response_headers(request, response, &s.response_headers);
+                WebResponse::Handled(compress_response(request, response, s.compression))
+            }
+            WebResponse::Drip(head, drip) => WebResponse::Drip(
+                apply_response_headers(request, head, &s.response_headers),
+                drip,
+            ),
+            WebResponse::Fault(fault) => WebResponse::Fault(fault),
         }
-        if root_path.exists() && root_path.is_dir() {
-            println!(
-                "{}: http://localhost:{} ({}) {}",
-                label,
+    };
+    let handler: Arc<RequestHandler> = Arc::new(handler);
+    let server = Arc::new(server);
+    let workers = workers.max(1);
+    spawn_request_workers(Arc::clone(&server), workers, Arc::clone(&handler), label);
+    Ok(WebServerHandle {
+        server,
+        port,
+        certificate: certificate.cloned(),
+        workers,
+        label,
+        settings,
+        handler,
+    })
+}
+
+/// Spawns `count` threads serving requests from `server` until each receives an unblock.
+/// A panicking handler is answered with 500 and does not take its worker down.
+fn spawn_request_workers(
+    server: Arc<Server>,
+    count: usize,
+    handler: Arc<RequestHandler>,
+    label: &'static str,
+) -> Vec<thread::JoinHandle<()>> {
+    (0..count)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let started = Instant::now();
+                    let method = request.method().to_string();
+                    let path = strip_query_token(request.url());
+                    let user_agent = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("User-Agent"))
+                        .map(|h| h.value.to_string());
+                    let response = match catch_unwind(AssertUnwindSafe(|| handler(&mut request))) {
+                        Ok(response) => response,
+                        Err(_) => {
+                            eprintln!(
+                                "{}: request handler panicked: {} {}",
+                                label,
+                                request.method(),
+                                request.url()
+                            );
+                            WebResponse::Handled(
+                                create_error_response(StatusCode(500), "Internal Server Error")
+                                    .boxed(),
+                            )
+                        }
+                    };
+                    let (status, bytes) = match response {
+                        WebResponse::Handled(response) => {
+                            let status = response.status_code().0;
+                            let bytes = response.data_length().map(|len| len as u64);
+                            if let Err(e) = request.respond(response) {
+                                eprintln!("Failed to send response: {}", e);
+                            }
+                            (status, bytes)
+                        }
+                        WebResponse::Drip(head, settings) => {
+                            let status = head.status_code().0;
+                            // A client hanging up mid-drip is expected, so the error is not logged.
+                            let bytes = stream_drip_response(request, head, &settings).ok();
+                            (status, bytes)
+                        }
+                        WebResponse::Fault(fault) => {
+                            // Broken responses often make the client hang up; that is not logged.
+                            // There is no real status, so faults are logged as 200.
+                            let _ = write_fault_response(request, fault);
+                            (200, None)
+                        }
+                    };
+                    if !is_access_log_request(&path)
+                        && !is_proxy_viewer_request(&path)
+                        && !is_bin_viewer_request(&path)
+                    {
+                        record_access(AccessLogEntry {
+                            time: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+                            listener: label.to_string(),
+                            method,
+                            path,
+                            status,
+                            bytes,
+                            duration_ms: started.elapsed().as_millis() as u64,
+                            user_agent,
+                        });
+                    }
+                }
+            })
+        })
+        .collect()
+}
+
+/// Running Main, TLS and Assets listeners, re-configured in place when config.json changes.
+#[derive(Default)]
+pub struct WebServers {
+    pub main: Option<WebServerHandle>,
+    pub tls: Option<WebServerHandle>,
+    pub assets: Option<WebServerHandle>,
+}
+
+impl WebServers {
+    /// Ports bound by the running listeners, which a reload may keep using.
+    pub fn held_ports(&self) -> Vec<u16> {
+        [&self.main, &self.tls, &self.assets]
+            .into_iter()
+            .flatten()
+            .map(WebServerHandle::port)
+            .chain(fault_listener_port())
+            .collect()
+    }
+
+    /// Starts or re-configures both listeners for `config`. A listener whose port changed is
+    /// re-bound first and the old one is stopped only once the new port is listening.
+    pub fn apply(&mut self, config: &WebServerConfig) -> Result<(), String> {
+        configure_access_log(config.access_log.clone());
+        configure_request_bin(config.request_bin);
+        configure_status_profiles(config.status_profiles.clone());
+        configure_fault_listener(config.fault_port, main_http_bind_addr())?;
+        reconfigure_listener(
+            &mut self.main,
+            config.main_listener_settings(),
+            config.port,
+            config.workers,
+            WebServerListenKind::Main,
+        )?;
+        match config.tls_listener_settings() {
+            Some((port, certificate, settings)) => reconfigure_tls_listener(
+                &mut self.tls,
+                settings,
                 port,
-                listen_kind.log_purpose_with_root(),
-                root_path.display()
-            );
-        } else {
-            println!(
-                "{}: http://localhost:{} ({}) {}",
-                label,
+                config.workers,
+                certificate,
+            )?,
+            None => {
+                if let Some(old) = self.tls.take() {
+                    old.stop();
+                }
+            }
+        }
+        match config.assets_listener_settings() {
+            Some((port, settings)) => reconfigure_listener(
+                &mut self.assets,
+                settings,
                 port,
-                listen_kind.log_purpose_no_root(),
-                "-"
-            );
+                config.workers,
+                WebServerListenKind::Assets,
+            )?,
+            None => {
+                if let Some(old) = self.assets.take() {
+                    old.stop();
+                }
+            }
         }
+        Ok(())
+    }
+}
 
-        for mut request in server.incoming_requests() {
-            let response = handle_web_request(
-                &mut request,
-                &root_path,
-                dump_enabled,
-                slow_enabled,
-                status_enabled,
-                allow_html_in_md,
-                markdown_open_external_link_in_new_tab,
-                markdown_highlight.as_ref(),
-                &editor_repos_dir,
-                editor_include_host,
-                &editor_command,
-                &editor_args,
-                markdown_live_reload_ws_port,
-                local_preview_api.as_ref(),
-                port,
-            );
-            if let Err(e) = request.respond(response) {
-                eprintln!("Failed to send response: {}", e);
+fn reconfigure_listener(
+    slot: &mut Option<WebServerHandle>,
+    settings: WebListenerSettings,
+    port: u16,
+    workers: usize,
+    listen_kind: WebServerListenKind,
+) -> Result<(), String> {
+    if let Some(handle) = slot.as_mut().filter(|h| h.port == port) {
+        handle.update(settings, workers);
+        return Ok(());
+    }
+    let handle = start_web_server(settings, port, workers, listen_kind)?;
+    if let Some(old) = slot.replace(handle) {
+        old.stop();
+    }
+    Ok(())
+}
+
+/// Like [`reconfigure_listener`], but a new certificate on the same port needs the old listener
+/// gone before binding again, so there is a short gap while it drains.
+fn reconfigure_tls_listener(
+    slot: &mut Option<WebServerHandle>,
+    settings: WebListenerSettings,
+    port: u16,
+    workers: usize,
+    certificate: &TlsCertificate,
+) -> Result<(), String> {
+    if let Some(handle) = slot
+        .as_mut()
+        .filter(|h| h.port == port && h.certificate.as_ref() == Some(certificate))
+    {
+        handle.update(settings, workers);
+        return Ok(());
+    }
+    if let Some(old) = slot.take_if(|h| h.port == port) {
+        old.stop();
+        for _ in 0..TLS_REBIND_ATTEMPTS {
+            if is_local_port_available(port) {
+                break;
             }
+            thread::sleep(TLS_REBIND_WAIT);
         }
-    });
+    }
+    let handle = start_tls_web_server(settings, port, workers, certificate)?;
+    if let Some(old) = slot.replace(handle) {
+        old.stop();
+    }
+    Ok(())
 }
 
 fn build_unconfigured_web_root_path(identifier: &String) -> Result<String, String> {
@@ -390,15 +1322,64 @@ fn build_unconfigured_web_root_path(identifier: &String) -> Result<String, Strin
     Ok(root.to_string_lossy().to_string())
 }
 
-pub fn default_web_server_config(identifier: &String) -> Result<WebServerConfig, String> {
-    let main_port = find_available_port_downward(df_web_port(), MIN_WEB_PORT, "main web")?;
-    let markdown_ws_start = main_port
-        .checked_sub(1)
-        .ok_or("Failed to resolve markdown live reload ws port: main web port is too low")?;
-    let markdown_live_reload_ws_port = Some(find_available_port_downward(
+fn resolve_access_guard_settings(
+    security: Option<&WebSecurityConfig>,
+) -> Result<AccessGuardSettings, String> {
+    let Some(security) = security else {
+        return Ok(AccessGuardSettings::default());
+    };
+    let token = security.token.as_deref().map(str::trim);
+    if let Some(token) = token
+        && (token.len() < MIN_ACCESS_TOKEN_LEN
+            || !token
+                .bytes()
+                .all(|c| c.is_ascii_graphic() && c != b';' && c != b','))
+    {
+        return Err(format!(
+            "web.security.token must be at least {} printable characters without ';' or ','",
+            MIN_ACCESS_TOKEN_LEN
+        ));
+    }
+    Ok(AccessGuardSettings {
+        allowed_hosts: security.allowed_hosts.clone(),
+        allowed_origins: security.allowed_origins.clone(),
+        token: token.map(String::from),
+    })
+}
+
+fn resolve_access_log_settings(
+    identifier: &String,
+    config: Option<&WebAccessLogConfig>,
+) -> Result<Option<AccessLogSettings>, String> {
+    if config.is_some_and(|c| !c.enabled) {
+        return Ok(None);
+    }
+    let base_dir = BaseDirs::new().ok_or("Failed to get base dir")?;
+    Ok(Some(AccessLogSettings {
+        path: base_dir.config_dir().join(identifier).join("access.log"),
+        max_bytes: config.map_or_else(df_access_log_max_bytes, |c| c.max_bytes),
+        max_files: config.map_or_else(df_access_log_max_files, |c| c.max_files),
+    }))
+}
+
+pub fn default_web_server_config(identifier: &String) -> Result<WebServerConfig, String> {
+    build_default_web_server_config(identifier, &[])
+}
+
+fn build_default_web_server_config(
+    identifier: &String,
+    held_ports: &[u16],
+) -> Result<WebServerConfig, String> {
+    let main_port =
+        find_available_port_downward(df_web_port(), MIN_WEB_PORT, "main web", held_ports)?;
+    let markdown_ws_start = main_port
+        .checked_sub(1)
+        .ok_or("Failed to resolve markdown live reload ws port: main web port is too low")?;
+    let markdown_live_reload_ws_port = Some(find_available_port_downward(
         markdown_ws_start,
         MIN_WEB_PORT,
         "markdown live reload ws",
+        held_ports,
     )?);
     Ok(WebServerConfig {
         root: build_unconfigured_web_root_path(identifier)?,
@@ -407,16 +1388,35 @@ pub fn default_web_server_config(identifier: &String) -> Result<WebServerConfig,
         dump: false,
         slow: false,
         status: false,
+        fault: false,
+        fault_port: None,
+        httpbin: false,
+        cookies_and_caching: false,
+        auth: None,
+        workers: df_workers(),
+        compression: df_compression_settings(),
+        response_headers: ResponseHeaderSettings::default(),
+        access_log: resolve_access_log_settings(identifier, None)?,
+        access_guard: AccessGuardSettings::default(),
+        routes: Vec::new(),
+        proxy_routes: Vec::new(),
+        mock: None,
+        request_bin: None,
+        status_profiles: BTreeMap::new(),
         allow_html_in_md: false,
         markdown_open_external_link_in_new_tab: true,
+        source_max_bytes: df_source_max_bytes(),
         markdown_highlight: None,
         assets_server: None,
+        tls_server: None,
         markdown_live_reload_ws_port,
         editor_repos_dir: None,
         editor_include_host: false,
         editor_command: "code".to_string(),
         editor_args: vec!["-g".to_string(), "{file}:{line}".to_string()],
         local_preview_api_enabled: Arc::new(AtomicBool::new(false)),
+        mounts: Vec::new(),
+        root_options: WebRootOptions::default(),
     })
 }
 
@@ -624,6 +1624,25 @@ fn prepare_markdown_assets_root(identifier: &String) -> Result<String, String> {
 }
 
 pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, String> {
+    load_web_config_holding_ports(identifier, &[])
+}
+
+/// Resolves the web server config on reload. `held_ports` are bound by the running listeners
+/// and may be reused; a missing `web` section falls back to the temp-share-only defaults.
+pub fn reload_web_server_config(
+    identifier: &String,
+    held_ports: &[u16],
+) -> Result<WebServerConfig, String> {
+    match load_web_config_holding_ports(identifier, held_ports)? {
+        Some(config) => Ok(config),
+        None => build_default_web_server_config(identifier, held_ports),
+    }
+}
+
+fn load_web_config_holding_ports(
+    identifier: &String,
+    held_ports: &[u16],
+) -> Result<Option<WebServerConfig>, String> {
     let base_dir = BaseDirs::new().ok_or("Failed to get base dir")?;
     let config_path = base_dir.config_dir().join(get_config_app_path(identifier));
 
@@ -648,6 +1667,24 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         return Err(format!("web.root not exists: {}", root_path.display()));
     }
 
+    let mounts = resolve_web_mounts(&web_config.mounts)?;
+    let response_headers =
+        resolve_response_header_settings(&web_config.headers, web_config.cors.as_ref())?;
+    let access_log = resolve_access_log_settings(identifier, web_config.access_log.as_ref())?;
+    let access_guard = resolve_access_guard_settings(web_config.security.as_ref())?;
+    let routes = resolve_web_routes(&web_config.routes, &root_path)?;
+    let proxy_routes = resolve_proxy_routes(&web_config.proxy)?;
+    let mock = resolve_mock_settings(web_config.mock.as_ref())?;
+    let request_bin = resolve_request_bin_settings(web_config.bin.as_ref())?;
+    let status_profiles = resolve_status_profiles(&web_config.status_profiles)?;
+    let root_options = resolve_web_root_options(
+        "web",
+        &root_path,
+        web_config.index,
+        &web_config.spa_fallback,
+        &web_config.not_found_page,
+    )?;
+
     let editor_repos_dir = match web_config
         .editor
         .as_ref()
@@ -668,6 +1705,11 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         .and_then(|c| c.markdown.as_ref())
         .map(|m| m.open_external_link_in_new_tab)
         .unwrap_or(true);
+    let source_max_bytes = web_config
+        .content
+        .as_ref()
+        .map(|c| c.source_max_bytes)
+        .unwrap_or_else(df_source_max_bytes);
     let enable_preview_api = web_config
         .content
         .as_ref()
@@ -676,7 +1718,7 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         .unwrap_or(false);
     let has_explicit_web_port = config_value.pointer("/web/port").is_some();
     let main_port = if has_explicit_web_port {
-        if !is_local_port_available(web_config.port) {
+        if !is_port_usable(web_config.port, held_ports) {
             return Err(format!(
                 "web.port {} is already in use. Please free the port or change web.port.",
                 web_config.port
@@ -684,12 +1726,13 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         }
         web_config.port
     } else {
-        find_available_port_downward(web_config.port, MIN_WEB_PORT, "main web")?
+        find_available_port_downward(web_config.port, MIN_WEB_PORT, "main web", held_ports)?
     };
     let assets_start_port = main_port
         .checked_sub(1)
         .ok_or("Failed to resolve assets port: main web port is too low to derive assets port")?;
-    let assets_port = find_available_port_downward(assets_start_port, MIN_WEB_PORT, "assets")?;
+    let assets_port =
+        find_available_port_downward(assets_start_port, MIN_WEB_PORT, "assets", held_ports)?;
     let markdown_ws_start = assets_port
         .checked_sub(1)
         .ok_or("Failed to resolve markdown live reload ws port: assets port is too low")?;
@@ -697,7 +1740,69 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         markdown_ws_start,
         MIN_WEB_PORT,
         "markdown live reload ws",
+        held_ports,
     )?);
+    let tls_server = match web_config.tls.as_ref().filter(|tls| tls.enabled) {
+        Some(tls) => {
+            let tls_port = if config_value.pointer("/web/tls/port").is_some() {
+                if !is_port_usable(tls.port, held_ports) {
+                    return Err(format!(
+                        "web.tls.port {} is already in use. Please free the port or change web.tls.port.",
+                        tls.port
+                    ));
+                }
+                tls.port
+            } else {
+                find_available_port_downward(tls.port, MIN_WEB_PORT, "tls", held_ports)?
+            };
+            if [
+                Some(main_port),
+                Some(assets_port),
+                markdown_live_reload_ws_port,
+            ]
+            .contains(&Some(tls_port))
+            {
+                return Err(format!(
+                    "web.tls.port {} is taken by another mclocks listener. Please change web.tls.port.",
+                    tls_port
+                ));
+            }
+            let config_app_dir = base_dir.config_dir().join(identifier);
+            let certificate = ensure_dev_certificate(
+                &dev_ca_dir(&config_app_dir),
+                &server_cert_hosts(&tls.hosts),
+            )?;
+            Some(WebTlsServerConfig {
+                port: tls_port,
+                certificate,
+            })
+        }
+        None => None,
+    };
+    let fault_port = if web_config.fault {
+        if !is_port_usable(web_config.fault_port, held_ports) {
+            return Err(format!(
+                "web.faultPort {} is already in use. Please free the port or change web.faultPort.",
+                web_config.fault_port
+            ));
+        }
+        if [
+            Some(main_port),
+            Some(assets_port),
+            markdown_live_reload_ws_port,
+            tls_server.as_ref().map(|tls| tls.port),
+        ]
+        .contains(&Some(web_config.fault_port))
+        {
+            return Err(format!(
+                "web.faultPort {} is taken by another mclocks listener. Please change web.faultPort.",
+                web_config.fault_port
+            ));
+        }
+        Some(web_config.fault_port)
+    } else {
+        None
+    };
     let assets_root = prepare_markdown_assets_root(identifier)?;
     let assets_server = Some(WebAssetsServerConfig {
         root: assets_root,
@@ -806,28 +1911,285 @@ pub fn load_web_config(identifier: &String) -> Result<Option<WebServerConfig>, S
         dump: web_config.dump,
         slow: web_config.slow,
         status: web_config.status,
+        fault: web_config.fault,
+        fault_port,
+        httpbin: web_config.httpbin,
+        cookies_and_caching: web_config.cookies_and_caching,
+        auth: web_config
+            .auth
+            .as_ref()
+            .filter(|auth| auth.enabled)
+            .map(|auth| AuthEndpointSettings {
+                bearer_token: auth.bearer_token.clone(),
+            }),
+        workers: web_config.workers,
+        compression: web_config
+            .compression
+            .as_ref()
+            .map(|c| CompressionSettings {
+                enabled: c.enabled,
+                min_bytes: c.min_bytes,
+            })
+            .unwrap_or_else(df_compression_settings),
+        response_headers,
+        access_log,
+        access_guard,
+        routes,
+        proxy_routes,
+        mock,
+        request_bin,
+        status_profiles,
         allow_html_in_md,
         markdown_open_external_link_in_new_tab,
+        source_max_bytes,
         markdown_highlight,
         assets_server,
+        tls_server,
         markdown_live_reload_ws_port,
         editor_repos_dir,
         editor_include_host,
         editor_command,
         editor_args,
         local_preview_api_enabled: Arc::new(AtomicBool::new(enable_preview_api)),
+        mounts,
+        root_options,
     }))
 }
 
-fn normalize_editor_repos_dir(repos_dir: &str) -> Result<String, String> {
-    let mut normalized = repos_dir.to_string();
+fn validate_response_header(key: &str, name: &str, value: &str) -> Result<(), String> {
+    let reserved = ["Content-Length", "Transfer-Encoding", "Connection"]
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(name));
+    let valid = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).is_ok()
+        && !name.is_empty()
+        && name.bytes().all(|c| c.is_ascii_graphic() && c != b':');
+    if reserved || !valid {
+        return Err(format!("{}: invalid response header: {:?}", key, name));
+    }
+    Ok(())
+}
+
+fn resolve_response_header_settings(
+    rules: &[WebHeaderRuleConfig],
+    cors: Option<&WebCorsConfig>,
+) -> Result<ResponseHeaderSettings, String> {
+    let mut resolved_rules = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let key = format!("web.headers[{}]", i);
+        if !rule.path.starts_with('/') && !rule.path.starts_with("**") {
+            return Err(format!("{}.path must start with '/': {:?}", key, rule.path));
+        }
+        for (name, value) in &rule.headers {
+            validate_response_header(&key, name, value)?;
+        }
+        resolved_rules.push(HeaderRule {
+            pattern: rule.path.clone(),
+            headers: rule
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        });
+    }
+    let cors = cors.map(|c| CorsSettings {
+        allowed_origins: c.allowed_origins.clone(),
+        allowed_methods: c
+            .allowed_methods
+            .iter()
+            .map(|m| m.trim().to_ascii_uppercase())
+            .collect(),
+        allowed_headers: c.allowed_headers.clone(),
+        expose_headers: c.expose_headers.clone(),
+        allow_credentials: c.allow_credentials,
+        max_age: c.max_age,
+    });
+    Ok(ResponseHeaderSettings {
+        rules: resolved_rules,
+        cors,
+    })
+}
+
+fn resolve_web_routes(routes: &[WebRouteConfig], root: &Path) -> Result<Vec<MockRoute>, String> {
+    let mut resolved = Vec::new();
+    for (i, route) in routes.iter().enumerate() {
+        let key = format!("web.routes[{}]", i);
+        let segments = parse_route_path(&route.path).map_err(|e| format!("{}: {}", key, e))?;
+        let method = route.method.trim().to_ascii_uppercase();
+        if method.is_empty() || !method.chars().all(|c| c.is_ascii_alphabetic() || c == '*') {
+            return Err(format!("{}.method is invalid: {:?}", key, route.method));
+        }
+        if !(100..=599).contains(&route.status) {
+            return Err(format!("{}.status must be 100-599: {}", key, route.status));
+        }
+        if route.delay_ms > MAX_SLOW_SECONDS * 1000 {
+            return Err(format!(
+                "{}.delayMs exceeds maximum ({})",
+                key,
+                MAX_SLOW_SECONDS * 1000
+            ));
+        }
+        for (name, value) in &route.headers {
+            validate_response_header(&key, name, value)?;
+        }
+        let body = match (&route.body, &route.body_file) {
+            (Some(_), Some(_)) => {
+                return Err(format!("{}: set either body or bodyFile, not both", key));
+            }
+            (_, Some(file)) => {
+                let expanded = PathBuf::from(expand_home_dir(file)?);
+                let path = if expanded.is_absolute() {
+                    expanded
+                } else {
+                    root.join(expanded)
+                };
+                if !path.is_file() {
+                    return Err(format!(
+                        "{}.bodyFile is not an existing file: {}",
+                        key,
+                        path.display()
+                    ));
+                }
+                RouteBody::File(path)
+            }
+            (body, None) => RouteBody::Inline(body.clone().unwrap_or_default()),
+        };
+        resolved.push(MockRoute {
+            method,
+            segments,
+            status: route.status,
+            headers: route
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            body,
+            delay: std::time::Duration::from_millis(route.delay_ms),
+        });
+    }
+    Ok(resolved)
+}
+
+fn resolve_proxy_routes(proxy: &[WebProxyConfig]) -> Result<Vec<ProxyRoute>, String> {
+    let mut routes: Vec<ProxyRoute> = Vec::new();
+    for (i, entry) in proxy.iter().enumerate() {
+        let key = format!("web.proxy[{}]", i);
+        let trimmed = entry.path.trim().trim_end_matches('/');
+        if !entry.path.trim().starts_with('/') || trimmed.contains(['?', '#', '*']) {
+            return Err(format!(
+                "{}.path must be a path prefix such as /api: {:?}",
+                key, entry.path
+            ));
+        }
+        let prefix = if trimmed.is_empty() { "/" } else { trimmed }.to_string();
+        if routes.iter().any(|r| r.prefix == prefix) {
+            return Err(format!("{}: duplicate proxy path: {}", key, prefix));
+        }
+        let upstream = parse_upstream(&entry.upstream).map_err(|e| format!("{}: {}", key, e))?;
+        if entry.delay_ms > MAX_SLOW_SECONDS * 1000 {
+            return Err(format!(
+                "{}.delayMs exceeds maximum ({})",
+                key,
+                MAX_SLOW_SECONDS * 1000
+            ));
+        }
+        if let Some(status) = entry.status
+            && !(100..=599).contains(&status)
+        {
+            return Err(format!("{}.status must be 100-599: {}", key, status));
+        }
+        routes.push(ProxyRoute {
+            prefix,
+            upstream,
+            strip_prefix: entry.strip_prefix,
+            delay: std::time::Duration::from_millis(entry.delay_ms),
+            status: entry.status,
+        });
+    }
+    Ok(routes)
+}
+
+fn resolve_request_bin_settings(
+    bin: Option<&WebBinConfig>,
+) -> Result<Option<RequestBinSettings>, String> {
+    let Some(bin) = bin.filter(|b| b.enabled) else {
+        return Ok(None);
+    };
+    if bin.max_requests == 0 || bin.max_requests > MAX_BIN_REQUESTS {
+        return Err(format!(
+            "web.bin.maxRequests must be between 1 and {}",
+            MAX_BIN_REQUESTS
+        ));
+    }
+    Ok(Some(RequestBinSettings {
+        max_requests: bin.max_requests,
+        notify: bin.notify,
+    }))
+}
+
+fn resolve_status_profiles(
+    profiles: &BTreeMap<String, WebStatusProfileConfig>,
+) -> Result<BTreeMap<String, StatusProfile>, String> {
+    let mut resolved = BTreeMap::new();
+    for (name, profile) in profiles {
+        if !is_valid_profile_name(name) {
+            return Err(format!(
+                "web.statusProfiles: invalid profile name '{}' (use letters, digits, '-' and '_')",
+                name
+            ));
+        }
+        let weights = profile
+            .weights
+            .iter()
+            .map(|(code, weight)| (code.clone(), *weight));
+        let status_profile = build_status_profile(weights, profile.seed)
+            .map_err(|e| format!("web.statusProfiles.{}: {}", name, e))?;
+        resolved.insert(name.clone(), status_profile);
+    }
+    Ok(resolved)
+}
+
+fn resolve_mock_settings(
+    mock: Option<&WebMockConfig>,
+) -> Result<Option<Arc<MockSettings>>, String> {
+    let Some(mock) = mock else {
+        return Ok(None);
+    };
+    let trimmed = mock.path.trim().trim_end_matches('/');
+    if !mock.path.trim().starts_with('/') || trimmed.contains(['?', '#', '*']) {
+        return Err(format!(
+            "web.mock.path must be a path prefix such as /api: {:?}",
+            mock.path
+        ));
+    }
+    let source = PathBuf::from(expand_home_dir(&mock.source)?);
+    if !source.exists() {
+        return Err(format!("web.mock.source not exists: {}", source.display()));
+    }
+    let exchanges = load_mock_exchanges(&source).map_err(|e| format!("web.mock: {}", e))?;
+    Ok(Some(Arc::new(MockSettings {
+        prefix: if trimmed.is_empty() { "/" } else { trimmed }.to_string(),
+        exchanges,
+        unmatched: match mock.unmatched {
+            WebMockUnmatched::NotFound => MockUnmatched::NotFound,
+            WebMockUnmatched::Passthrough => MockUnmatched::Passthrough,
+        },
+        ignore_query: mock.ignore_query.clone(),
+        match_headers: mock.match_headers.clone(),
+    })))
+}
 
-    if normalized.starts_with("~") {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .map_err(|_| "HOME or USERPROFILE environment variable not set")?;
-        normalized = normalized.replacen("~", &home, 1);
+fn expand_home_dir(path: &str) -> Result<String, String> {
+    if !path.starts_with("~") {
+        return Ok(path.to_string());
     }
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map_err(|_| "HOME or USERPROFILE environment variable not set")?;
+    Ok(path.replacen("~", &home, 1))
+}
+
+fn normalize_editor_repos_dir(repos_dir: &str) -> Result<String, String> {
+    let normalized = expand_home_dir(repos_dir)?;
 
     let path = PathBuf::from(&normalized);
     if !path.exists() {
@@ -843,11 +2205,126 @@ fn normalize_editor_repos_dir(repos_dir: &str) -> Result<String, String> {
     Ok(normalized)
 }
 
+/// `/docs/` and `docs` both become `/docs`. Empty, `.` and `..` segments are rejected.
+fn normalize_mount_prefix(prefix: &str) -> Result<String, String> {
+    let trimmed = prefix.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Err(format!(
+            "web.mounts: mount prefix must not be the root: {:?}",
+            prefix
+        ));
+    }
+    let segments: Vec<&str> = trimmed.split('/').collect();
+    if segments
+        .iter()
+        .any(|s| s.is_empty() || *s == "." || *s == ".." || s.contains('\\'))
+    {
+        return Err(format!("web.mounts: invalid mount prefix: {:?}", prefix));
+    }
+    Ok(format!("/{}", segments.join("/")))
+}
+
+/// Resolves a `spaFallback`/`notFoundPage` value to a file inside `root`.
+fn resolve_file_in_root(root: &Path, relative: &str, key: &str) -> Result<PathBuf, String> {
+    let relative = relative.trim_start_matches('/');
+    let invalid = relative.is_empty()
+        || relative
+            .split(['/', '\\'])
+            .any(|segment| segment == ".." || segment.contains(':'));
+    let resolved = root.join(relative);
+    let inside_root = match (root.canonicalize(), resolved.canonicalize()) {
+        (Ok(root), Ok(file)) => file.starts_with(root) && file.is_file(),
+        _ => false,
+    };
+    if invalid || !inside_root {
+        return Err(format!(
+            "{} must name an existing file inside its root: {}",
+            key, relative
+        ));
+    }
+    Ok(resolved)
+}
+
+fn resolve_web_root_options(
+    key_prefix: &str,
+    root: &Path,
+    index: bool,
+    spa_fallback: &Option<String>,
+    not_found_page: &Option<String>,
+) -> Result<WebRootOptions, String> {
+    let resolve = |value: &Option<String>, key: &str| {
+        value
+            .as_deref()
+            .map(|relative| {
+                resolve_file_in_root(root, relative, &format!("{}.{}", key_prefix, key))
+            })
+            .transpose()
+    };
+    Ok(WebRootOptions {
+        index,
+        spa_fallback: resolve(spa_fallback, "spaFallback")?,
+        not_found_page: resolve(not_found_page, "notFoundPage")?,
+    })
+}
+
+fn resolve_web_mounts(mounts: &BTreeMap<String, WebMountConfig>) -> Result<Vec<WebMount>, String> {
+    let mut resolved: Vec<WebMount> = Vec::new();
+    for (prefix, mount) in mounts {
+        let normalized_prefix = normalize_mount_prefix(prefix)?;
+        if resolved.iter().any(|m| m.prefix == normalized_prefix) {
+            return Err(format!(
+                "web.mounts: duplicate mount prefix: {}",
+                normalized_prefix
+            ));
+        }
+        let path = match mount {
+            WebMountConfig::Path(path) => path,
+            WebMountConfig::Options(options) => &options.path,
+        };
+        let root = PathBuf::from(expand_home_dir(path)?);
+        if !root.is_dir() {
+            return Err(format!(
+                "web.mounts.{} is not an existing directory: {}",
+                normalized_prefix,
+                root.display()
+            ));
+        }
+        let resolved_mount = match mount {
+            WebMountConfig::Path(_) => WebMount {
+                prefix: normalized_prefix,
+                root,
+                listing: df_mount_listing(),
+                show_hidden: df_mount_show_hidden(),
+                default_mode: WebMountMode::default(),
+                options: WebRootOptions::default(),
+            },
+            WebMountConfig::Options(options) => WebMount {
+                options: resolve_web_root_options(
+                    &format!("web.mounts.{}", normalized_prefix),
+                    &root,
+                    options.index,
+                    &options.spa_fallback,
+                    &options.not_found_page,
+                )?,
+                prefix: normalized_prefix,
+                root,
+                listing: options.listing,
+                show_hidden: options.show_hidden,
+                default_mode: options.default_mode,
+            },
+        };
+        resolved.push(resolved_mount);
+    }
+    Ok(resolved)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::web::dd_publish::{TEMP_DIR_PREFIX, register_temp_root};
-    use crate::web::handler_static::{get_content_type, handle_web_request};
+    use crate::web::dd_publish::{
+        TEMP_DIR_PREFIX, build_temp_file_url, register_temp_file, register_temp_root,
+    };
+    use crate::web::handler_static::{WebResponse, get_content_type, handle_web_request};
     use std::fs;
     use std::path::PathBuf;
     use std::thread;
@@ -2997,6 +4474,149 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handle_web_request_range_returns_partial_content() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        fs::write(root_path.join("clip.mp4"), b"0123456789").expect("Failed to create clip.mp4");
+        let port = find_available_port();
+
+        let _server_handle = start_test_server(root_path, port, false, false, false);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let full = client
+            .get(format!("http://127.0.0.1:{}/clip.mp4", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(full.status(), 200);
+        assert_eq!(full.headers().get("accept-ranges").unwrap(), "bytes");
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/clip.mp4", port))
+            .header("Range", "bytes=2-5")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 206);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes 2-5/10"
+        );
+        assert_eq!(response.bytes().unwrap().as_ref(), b"2345");
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/clip.mp4", port))
+            .header("Range", "bytes=-3")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.bytes().unwrap().as_ref(), b"789");
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/clip.mp4", port))
+            .header("Range", "bytes=10-")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 416);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes */10"
+        );
+    }
+
+    #[test]
+    fn test_handle_web_request_conditional_get_returns_not_modified() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        fs::write(root_path.join("page.txt"), "cached").expect("Failed to create page.txt");
+        let port = find_available_port();
+
+        let _server_handle = start_test_server(root_path, port, false, false, false);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let first = client
+            .get(format!("http://127.0.0.1:{}/page.txt", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(first.status(), 200);
+        let etag = first
+            .headers()
+            .get("etag")
+            .expect("ETag header should exist")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let last_modified = first
+            .headers()
+            .get("last-modified")
+            .expect("Last-Modified header should exist")
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let by_etag = client
+            .get(format!("http://127.0.0.1:{}/page.txt", port))
+            .header("If-None-Match", &etag)
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(by_etag.status(), 304);
+        assert_eq!(by_etag.headers().get("etag").unwrap(), etag.as_str());
+
+        let by_date = client
+            .get(format!("http://127.0.0.1:{}/page.txt", port))
+            .header("If-Modified-Since", &last_modified)
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(by_date.status(), 304);
+
+        let stale = client
+            .get(format!("http://127.0.0.1:{}/page.txt", port))
+            .header("If-None-Match", "\"stale\"")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(stale.status(), 200);
+        assert_eq!(stale.text().unwrap(), "cached");
+    }
+
+    #[test]
+    fn test_temp_file_share_supports_range_and_etag() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let dropped = temp_dir.path().join("dropped.webm");
+        fs::write(&dropped, b"abcdefghij").expect("Failed to create dropped file");
+        let port = find_available_port();
+
+        let _server_handle = start_test_server(
+            temp_dir.path().join("missing-web-root"),
+            port,
+            false,
+            false,
+            false,
+        );
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let hash = register_temp_file(&dropped).expect("Failed to register temp file");
+        let url = build_temp_file_url(port, &hash, &dropped)
+            .expect("Failed to build URL")
+            .replace("?mode=source", "?mode=raw");
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(&url)
+            .header("Range", "bytes=0-2")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 206);
+        let etag = response.headers().get("etag").unwrap().clone();
+        assert_eq!(response.bytes().unwrap().as_ref(), b"abc");
+
+        let response = client
+            .get(&url)
+            .header("If-None-Match", etag)
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 304);
+    }
+
     #[test]
     fn test_handle_web_request_multibyte_filename() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -3269,38 +4889,62 @@ mod tests {
     ) -> std::thread::JoinHandle<()> {
         use std::sync::Arc;
         use std::sync::atomic::AtomicBool;
+        let settings = WebListenerSettings {
+            dump: dump_enabled,
+            slow: slow_enabled,
+            status: status_enabled,
+            allow_html_in_md,
+            local_preview_api: Some(Arc::new(AtomicBool::new(false))),
+            ..test_listener_settings(root)
+        };
         thread::spawn(move || {
             let server = match Server::http(format!("127.0.0.1:{}", port)) {
                 Ok(s) => s,
                 Err(_) => return,
             };
-            let editor_args: Vec<String> = vec!["-g".to_string(), "{file}:{line}".to_string()];
-            let editor_command = "code".to_string();
-            let preview_off = Arc::new(AtomicBool::new(false));
-
             for mut request in server.incoming_requests() {
-                let response = handle_web_request(
-                    &mut request,
-                    &root,
-                    dump_enabled,
-                    slow_enabled,
-                    status_enabled,
-                    allow_html_in_md,
-                    true,
-                    None,
-                    &None,
-                    false,
-                    &editor_command,
-                    &editor_args,
-                    None,
-                    Some(&preview_off),
-                    port,
-                );
-                let _ = request.respond(response);
+                match handle_web_request(&mut request, &settings, port) {
+                    WebResponse::Handled(response) | WebResponse::Drip(response, _) => {
+                        let _ = request.respond(response);
+                    }
+                    WebResponse::Fault(_) => {}
+                }
             }
         })
     }
 
+    fn test_listener_settings(root: PathBuf) -> WebListenerSettings {
+        WebListenerSettings {
+            root,
+            dump: false,
+            slow: false,
+            status: false,
+            fault: false,
+            httpbin: false,
+            cookies_and_caching: false,
+            auth: None,
+            allow_html_in_md: false,
+            markdown_open_external_link_in_new_tab: true,
+            markdown_highlight: None,
+            editor_repos_dir: None,
+            editor_include_host: false,
+            editor_command: "code".to_string(),
+            editor_args: vec!["-g".to_string(), "{file}:{line}".to_string()],
+            markdown_live_reload_ws_port: None,
+            local_preview_api: None,
+            source_max_bytes: df_source_max_bytes(),
+            compression: df_compression_settings(),
+            response_headers: ResponseHeaderSettings::default(),
+            access_guard: AccessGuardSettings::default(),
+            dev_ca_pem: None,
+            routes: Vec::new(),
+            proxy_routes: Vec::new(),
+            mock: None,
+            mounts: Vec::new(),
+            root_options: WebRootOptions::default(),
+        }
+    }
+
     fn start_test_server(
         root: PathBuf,
         port: u16,
@@ -3567,45 +5211,152 @@ mod tests {
     }
 
     #[test]
-    fn test_handle_slow_request_default() {
+    fn test_handle_dump_request_with_form_and_multipart_bodies() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let root_path = temp_dir.path().to_path_buf();
-        let port = 3038;
-
-        let _server_handle = start_test_server(root_path, port, false, true, false);
+        let port = find_available_port();
+        let _server_handle =
+            start_test_server(temp_dir.path().to_path_buf(), port, true, false, false);
         thread::sleep(std::time::Duration::from_millis(100));
 
-        let start = std::time::Instant::now();
         let client = reqwest::blocking::Client::new();
-        let response = client
-            .get(&format!("http://127.0.0.1:{}/slow/3", port))
+        let form: serde_json::Value = client
+            .post(format!("http://127.0.0.1:{}/dump", port))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("q=a+b&q=c%26d")
             .send()
-            .expect("Failed to send request");
-        let elapsed = start.elapsed();
+            .expect("Failed to send request")
+            .json()
+            .expect("Failed to parse JSON");
+        assert_eq!(
+            form["parsed_body"],
+            serde_json::json!([{ "q": "a b" }, { "q": "c&d" }])
+        );
 
-        assert_eq!(response.status(), 200);
-        assert_eq!(response.text().unwrap(), "OK");
-        // Should wait approximately 3 seconds (allow some tolerance)
-        assert!(elapsed.as_secs() >= 3, "Should wait at least 3 seconds");
-        assert!(elapsed.as_secs() < 5, "Should not wait more than 5 seconds");
+        let mut upload = b"--b1\r\nContent-Disposition: form-data; name=\"file\"; filename=\"x.png\"\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        upload.extend_from_slice(&[0x89, b'P', b'N', b'G']);
+        upload.extend_from_slice(b"\r\n--b1--\r\n");
+        let multipart: serde_json::Value = client
+            .post(format!("http://127.0.0.1:{}/dump", port))
+            .header("Content-Type", "multipart/form-data; boundary=b1")
+            .body(upload)
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("Failed to parse JSON");
+        assert_eq!(multipart["body_encoding"], "base64");
+        let part = &multipart["parsed_body"][0];
+        assert_eq!(part["name"], "file");
+        assert_eq!(part["filename"], "x.png");
+        assert_eq!(part["content_type"], "image/png");
+        assert_eq!(part["size"], 4);
+        assert_eq!(part["base64"], "iVBORw==");
     }
 
     #[test]
-    fn test_handle_slow_request_with_seconds() {
+    fn test_handle_dump_request_shapes_response_and_renders_html() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let root_path = temp_dir.path().to_path_buf();
-        let port = 3039;
-
-        let _server_handle = start_test_server(root_path, port, false, true, false);
+        let port = find_available_port();
+        let _server_handle =
+            start_test_server(temp_dir.path().to_path_buf(), port, true, false, false);
         thread::sleep(std::time::Duration::from_millis(100));
 
-        let start = std::time::Instant::now();
         let client = reqwest::blocking::Client::new();
-        let response = client
-            .get(&format!("http://127.0.0.1:{}/slow/5", port))
+        let start = std::time::Instant::now();
+        let shaped = client
+            .post(format!(
+                "http://127.0.0.1:{}/dump/orders?status=201&header=X-Foo:bar&delay=1",
+                port
+            ))
+            .body("created")
             .send()
             .expect("Failed to send request");
-        let elapsed = start.elapsed();
+        assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+        assert_eq!(shaped.status(), 201);
+        assert_eq!(shaped.headers()["x-foo"], "bar");
+        assert_eq!(shaped.headers()["content-type"], "application/json");
+        let json: serde_json::Value = shaped.json().expect("Failed to parse JSON");
+        assert_eq!(json["path"], "/orders");
+        assert_eq!(json["body"], "created");
+
+        let html = client
+            .get(format!("http://127.0.0.1:{}/dump/page", port))
+            .header("Accept", "text/html,application/xhtml+xml")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(html.status(), 200);
+        assert!(
+            html.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        let html = html.text().unwrap();
+        assert!(html.contains("id=\"sidebar\""));
+        assert!(html.contains("/page"));
+
+        let forced_json = client
+            .get(format!("http://127.0.0.1:{}/dump?format=json", port))
+            .header("Accept", "text/html")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(forced_json.headers()["content-type"], "application/json");
+
+        // Values that don't shape the response are only echoed, as before shaping existed.
+        let echoed = client
+            .get(format!(
+                "http://127.0.0.1:{}/dump?status=abc&delay=later&header=plain",
+                port
+            ))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(echoed.status(), 200);
+        let json: serde_json::Value = echoed.json().expect("Failed to parse JSON");
+        assert_eq!(
+            json["query"],
+            serde_json::json!([{"status": "abc"}, {"delay": "later"}, {"header": "plain"}])
+        );
+    }
+
+    #[test]
+    fn test_handle_slow_request_default() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let port = 3038;
+
+        let _server_handle = start_test_server(root_path, port, false, true, false);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let start = std::time::Instant::now();
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(&format!("http://127.0.0.1:{}/slow/3", port))
+            .send()
+            .expect("Failed to send request");
+        let elapsed = start.elapsed();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "OK");
+        // Should wait approximately 3 seconds (allow some tolerance)
+        assert!(elapsed.as_secs() >= 3, "Should wait at least 3 seconds");
+        assert!(elapsed.as_secs() < 5, "Should not wait more than 5 seconds");
+    }
+
+    #[test]
+    fn test_handle_slow_request_with_seconds() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let port = 3039;
+
+        let _server_handle = start_test_server(root_path, port, false, true, false);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let start = std::time::Instant::now();
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(&format!("http://127.0.0.1:{}/slow/5", port))
+            .send()
+            .expect("Failed to send request");
+        let elapsed = start.elapsed();
 
         assert_eq!(response.status(), 200);
         assert_eq!(response.text().unwrap(), "OK");
@@ -4035,7 +5786,7 @@ mod tests {
         let _busy_listener =
             TcpListener::bind(("127.0.0.1", busy_assets_port)).expect("Failed to occupy port");
         let resolved_assets_port =
-            find_available_port_downward(busy_assets_port, MIN_WEB_PORT, "assets")
+            find_available_port_downward(busy_assets_port, MIN_WEB_PORT, "assets", &[])
                 .expect("Should resolve fallback assets port");
         assert_eq!(
             resolved_assets_port,
@@ -4050,7 +5801,7 @@ mod tests {
         let _busy_listener =
             TcpListener::bind(("127.0.0.1", preferred_port)).expect("Failed to occupy port");
         let resolved_main_port =
-            find_available_port_downward(preferred_port, MIN_WEB_PORT, "main web")
+            find_available_port_downward(preferred_port, MIN_WEB_PORT, "main web", &[])
                 .expect("Should resolve fallback main port");
         assert_eq!(
             resolved_main_port,
@@ -4157,6 +5908,7 @@ mod tests {
             .map(|m| m.enable_preview_api)
             .unwrap_or(false);
         assert_eq!(preview, false, "Default enable_preview_api should be false");
+        assert_eq!(config.fault_port, 3031, "Default fault port should be 3031");
     }
 
     #[test]
@@ -4485,4 +6237,1975 @@ mod tests {
             "60"
         );
     }
+
+    #[test]
+    fn test_handle_status_request_header_overrides() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let port = find_available_port();
+
+        let _server_handle = start_test_server(root_path, port, false, false, true);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to create client");
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+
+        let response = client
+            .get(url(
+                "/status/308?location=https%3A%2F%2Fexample.test%2Fnext",
+            ))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 308);
+        assert_eq!(response.headers()["location"], "https://example.test/next");
+
+        let response = client
+            .get(url("/status/503?retry-after=5"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.headers()["retry-after"], "5");
+
+        let response = client
+            .get(url("/status/401?www-authenticate=Bearer+realm%3D%22api%22"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(
+            response.headers()["www-authenticate"],
+            "Bearer realm=\"api\""
+        );
+
+        let response = client
+            .get(url("/status/201?location=%2Fitems%2F1"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.headers()["location"], "/items/1");
+
+        let response = client
+            .get(url("/status/302?location=a%0d%0aX-Injected:1"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 400);
+    }
+
+    #[test]
+    fn test_handle_redirect_requests() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let port = find_available_port();
+
+        let _server_handle = start_test_server(root_path, port, false, false, true);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to create client");
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let location = |path: &str| {
+            let response = client
+                .get(url(path))
+                .send()
+                .expect("Failed to send request");
+            let status = response.status().as_u16();
+            let location = response
+                .headers()
+                .get("location")
+                .map(|v| v.to_str().unwrap().to_string());
+            (status, location)
+        };
+
+        assert_eq!(
+            location("/redirect/3"),
+            (302, Some("/redirect/2".to_string()))
+        );
+        assert_eq!(
+            location("/redirect/2?status=307"),
+            (307, Some("/redirect/1?status=307".to_string()))
+        );
+        assert_eq!(location("/redirect/0"), (200, None));
+        assert_eq!(
+            location("/absolute-redirect/1"),
+            (
+                302,
+                Some(format!("http://127.0.0.1:{}/absolute-redirect/0", port))
+            )
+        );
+        assert_eq!(
+            location("/relative-redirect/1?absolute=true"),
+            (302, Some("/relative-redirect/0?absolute=true".to_string()))
+        );
+        assert_eq!(
+            location("/redirect-to?url=https%3A%2F%2Fexample.test%2F&status=307"),
+            (307, Some("https://example.test/".to_string()))
+        );
+        assert_eq!(location("/redirect-to").0, 400);
+        assert_eq!(location("/redirect/abc").0, 400);
+        assert_eq!(
+            location("/redirect-loop"),
+            (302, Some("/redirect-loop".to_string()))
+        );
+        assert_eq!(
+            location("/redirect-loop/2"),
+            (302, Some("/redirect-loop/2?hop=2".to_string()))
+        );
+        assert_eq!(
+            location("/redirect-loop/2?hop=2"),
+            (302, Some("/redirect-loop/2?hop=1".to_string()))
+        );
+
+        let following = reqwest::blocking::Client::new();
+        let response = following
+            .get(url("/redirect/5"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert!(response.url().path().ends_with("/redirect/0"));
+        assert!(following.get(url("/redirect-loop/3")).send().is_err());
+    }
+
+    #[test]
+    fn test_handle_random_status_requests() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let port = find_available_port();
+
+        let profiles: BTreeMap<String, WebStatusProfileConfig> =
+            serde_json::from_value(serde_json::json!({
+                "flaky": { "weights": { "200": 3, "500": 1 }, "seed": 9 }
+            }))
+            .expect("deserialize profiles");
+        configure_status_profiles(resolve_status_profiles(&profiles).expect("resolve profiles"));
+        let _server_handle = start_test_server(root_path, port, false, false, true);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let statuses = |path: &str| -> Vec<u16> {
+            (0..20)
+                .map(|_| {
+                    client
+                        .get(url(path))
+                        .send()
+                        .expect("Failed to send request")
+                        .status()
+                        .as_u16()
+                })
+                .collect()
+        };
+
+        let response = client
+            .get(url("/status/random?503=1"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 503);
+        assert_eq!(response.headers()["retry-after"], "60");
+
+        let first = statuses("/status/random/flaky");
+        assert!(first.iter().all(|code| *code == 200 || *code == 500));
+        let counters: serde_json::Value = client
+            .get(url("/_mclocks/status/random"))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("counters json");
+        let flaky = counters["profiles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["profile"] == "flaky")
+            .expect("flaky counters");
+        assert_eq!(flaky["total"], 20);
+        assert_eq!(
+            flaky["counts"]["200"].as_u64().unwrap_or(0),
+            first.iter().filter(|code| **code == 200).count() as u64
+        );
+        assert!(
+            counters["profiles"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|p| p["profile"] == "503=1" && p["adhoc"] == true)
+        );
+
+        let reset = client
+            .delete(url("/_mclocks/status/random"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(reset.status(), 204);
+        assert_eq!(statuses("/status/random/flaky"), first);
+
+        let unknown = client
+            .get(url("/status/random/missing"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(unknown.status(), 404);
+        let invalid = client
+            .get(url("/status/random?200=0"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(invalid.status(), 400);
+        assert!(
+            resolve_status_profiles(
+                &serde_json::from_value(serde_json::json!({ "bad": { "weights": { "700": 1 } } }))
+                    .expect("deserialize profiles")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_web_config_deserialize_workers() {
+        let config: WebConfig = serde_json::from_str(r#"{"root": "/test"}"#).expect("deserialize");
+        assert_eq!(config.workers, df_workers(), "Default workers should apply");
+
+        let config: WebConfig =
+            serde_json::from_str(r#"{"root": "/test", "workers": 2}"#).expect("deserialize");
+        assert_eq!(config.workers, 2, "Workers should match");
+
+        let result: Result<WebConfig, _> =
+            serde_json::from_str(r#"{"root": "/test", "workers": 0}"#);
+        assert!(result.is_err(), "Should reject zero workers");
+        let result: Result<WebConfig, _> =
+            serde_json::from_str(r#"{"root": "/test", "workers": 65}"#);
+        assert!(result.is_err(), "Should reject too many workers");
+    }
+
+    #[test]
+    fn test_web_config_deserialize_source_max_bytes() {
+        let config: WebConfig =
+            serde_json::from_str(r#"{"root": "/test", "content": {}}"#).expect("deserialize");
+        assert_eq!(
+            config.content.unwrap().source_max_bytes,
+            df_source_max_bytes(),
+            "Default source limit should apply"
+        );
+
+        let config: WebConfig =
+            serde_json::from_str(r#"{"root": "/test", "content": {"sourceMaxBytes": 1024}}"#)
+                .expect("deserialize");
+        assert_eq!(config.content.unwrap().source_max_bytes, 1024);
+    }
+
+    #[test]
+    fn test_handle_web_request_streams_file_larger_than_sniff_prefix() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        // Multi-byte characters straddle the sniffed prefix boundary
+        let content = "あいうえお\n".repeat(20_000);
+        fs::write(root_path.join("big.txt"), &content).expect("Failed to create big.txt");
+
+        let port = find_available_port();
+        let _server_handle = start_test_server(root_path, port, false, false, false);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/big.txt?mode=raw", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get("content-length").unwrap(),
+            &content.len().to_string()
+        );
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain; charset=UTF-8"
+        );
+        assert_eq!(response.text().unwrap(), content);
+
+        let tail_start = content.len() - 16;
+        let response = client
+            .get(format!("http://127.0.0.1:{}/big.txt", port))
+            .header("Range", format!("bytes={}-", tail_start))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 206);
+        assert_eq!(
+            response.bytes().unwrap().as_ref(),
+            &content.as_bytes()[tail_start..]
+        );
+    }
+
+    #[test]
+    fn test_start_web_server_refuses_source_view_over_limit() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        fs::write(root_path.join("small.txt"), "tiny").expect("Failed to create small.txt");
+        fs::write(root_path.join("large.txt"), "x".repeat(64)).expect("Failed to create large.txt");
+        fs::write(root_path.join("large.md"), "# ".repeat(32)).expect("Failed to create large.md");
+        let port = find_available_port();
+
+        start_web_server(
+            WebListenerSettings {
+                source_max_bytes: 32,
+                ..test_listener_settings(root_path)
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let body = client
+            .get(format!("http://127.0.0.1:{}/small.txt?mode=source", port))
+            .send()
+            .expect("Failed to send request")
+            .text()
+            .unwrap();
+        assert!(body.contains("Syntax Highlight"));
+        assert!(body.contains("tiny"));
+
+        for name in ["large.txt", "large.md"] {
+            let response = client
+                .get(format!("http://127.0.0.1:{}/{}?mode=source", port, name))
+                .send()
+                .expect("Failed to send request");
+            assert_eq!(response.status(), 200);
+            let body = response.text().unwrap();
+            assert!(
+                body.contains("too large for the source view"),
+                "{} should be refused",
+                name
+            );
+            assert!(body.contains("Too Large"));
+        }
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/large.txt?mode=raw", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.text().unwrap(), "x".repeat(64));
+    }
+
+    #[test]
+    fn test_start_web_server_negotiates_response_compression() {
+        use std::io::Read;
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        let large = "compress me please\n".repeat(500);
+        fs::write(root_path.join("large.txt"), &large).expect("Failed to create large.txt");
+        fs::write(root_path.join("small.txt"), "tiny").expect("Failed to create small.txt");
+        fs::write(root_path.join("image.png"), vec![0u8; 4096]).expect("Failed to create png");
+
+        let start = |port: u16, compression: CompressionSettings| {
+            start_web_server(
+                WebListenerSettings {
+                    compression,
+                    ..test_listener_settings(root_path.clone())
+                },
+                port,
+                1,
+                WebServerListenKind::Main,
+            )
+            .expect("Failed to start web server");
+        };
+        let port = find_available_port();
+        start(port, df_compression_settings());
+        let disabled_port = find_available_port();
+        start(
+            disabled_port,
+            CompressionSettings {
+                enabled: false,
+                min_bytes: 0,
+            },
+        );
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let get = |port: u16, path: &str, accept: &str| {
+            client
+                .get(format!("http://127.0.0.1:{}/{}?mode=raw", port, path))
+                .header("Accept-Encoding", accept)
+                .send()
+                .expect("Failed to send request")
+        };
+
+        let response = get(port, "large.txt", "gzip, br");
+        assert_eq!(response.headers().get("content-encoding").unwrap(), "br");
+        assert_eq!(response.headers().get("vary").unwrap(), "Accept-Encoding");
+        assert!(response.headers().get("accept-ranges").is_none());
+        assert!(
+            response
+                .headers()
+                .get("etag")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with("W/")
+        );
+        let compressed = response.bytes().unwrap();
+        assert!(compressed.len() < large.len());
+        let mut decoded = String::new();
+        brotli::Decompressor::new(compressed.as_ref(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, large);
+
+        let response = get(port, "large.txt", "gzip");
+        assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+        let compressed = response.bytes().unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(compressed.as_ref())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, large);
+
+        let response = get(port, "small.txt", "gzip, br");
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.text().unwrap(), "tiny");
+
+        let response = get(port, "image.png", "gzip, br");
+        assert!(response.headers().get("content-encoding").is_none());
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/large.txt", port))
+            .header("Accept-Encoding", "gzip, br")
+            .header("Range", "bytes=0-7")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 206);
+        assert!(response.headers().get("content-encoding").is_none());
+
+        let response = get(disabled_port, "large.txt", "gzip, br");
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.text().unwrap(), large);
+    }
+
+    #[test]
+    fn test_start_web_server_serves_listing_while_slow_request_pending() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        fs::write(root_path.join("file.txt"), "content").expect("Failed to create file.txt");
+        let port = find_available_port();
+
+        start_web_server(
+            WebListenerSettings {
+                slow: true,
+                ..test_listener_settings(root_path)
+            },
+            port,
+            4,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let slow_handle = thread::spawn(move || {
+            reqwest::blocking::Client::new()
+                .get(format!("http://127.0.0.1:{}/slow/3", port))
+                .send()
+                .map(|r| r.status().as_u16())
+        });
+        thread::sleep(std::time::Duration::from_millis(200));
+
+        let start = std::time::Instant::now();
+        let response = reqwest::blocking::Client::new()
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert!(
+            response.text().unwrap().contains("file.txt"),
+            "Should list file.txt"
+        );
+        assert!(
+            start.elapsed().as_secs() < 2,
+            "Directory listing should not wait for the pending /slow request"
+        );
+
+        let slow_status = slow_handle
+            .join()
+            .expect("Slow request thread panicked")
+            .expect("Failed to send slow request");
+        assert_eq!(slow_status, 200);
+    }
+
+    #[test]
+    fn test_reconfigure_listener_swaps_settings_and_rebinds_port() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().to_path_buf();
+        fs::write(root_path.join("file.txt"), "content").expect("Failed to create file.txt");
+        let port = find_available_port();
+        let mut slot = Some(
+            start_web_server(
+                test_listener_settings(root_path.clone()),
+                port,
+                2,
+                WebServerListenKind::Main,
+            )
+            .expect("Failed to start web server"),
+        );
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let dump_status = |port: u16| {
+            client
+                .get(format!("http://127.0.0.1:{}/dump", port))
+                .send()
+                .expect("Failed to send request")
+                .status()
+        };
+        assert_eq!(dump_status(port), 404);
+
+        let dump_on = WebListenerSettings {
+            dump: true,
+            ..test_listener_settings(root_path)
+        };
+        reconfigure_listener(
+            &mut slot,
+            dump_on.clone(),
+            port,
+            3,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to update listener");
+        assert_eq!(dump_status(port), 200, "Same port should update in place");
+
+        let new_port = find_available_port();
+        reconfigure_listener(&mut slot, dump_on, new_port, 1, WebServerListenKind::Main)
+            .expect("Failed to rebind listener");
+        assert_eq!(slot.as_ref().map(WebServerHandle::port), Some(new_port));
+        assert_eq!(dump_status(new_port), 200);
+
+        let fresh_client = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .expect("Failed to build client");
+        let old_port_closed = (0..20).any(|_| {
+            thread::sleep(std::time::Duration::from_millis(50));
+            fresh_client
+                .get(format!("http://127.0.0.1:{}/file.txt", port))
+                .send()
+                .is_err()
+        });
+        assert!(old_port_closed, "Old listener should stop after rebinding");
+    }
+
+    #[test]
+    fn test_spawn_request_workers_survives_panicking_handler() {
+        let port = find_available_port();
+        let server = Server::http(format!("127.0.0.1:{}", port)).expect("Failed to bind server");
+        spawn_request_workers(
+            Arc::new(server),
+            1,
+            Arc::new(|request: &mut Request| {
+                if request.url() == "/panic" {
+                    panic!("handler panic for test");
+                }
+                WebResponse::Handled(tiny_http::Response::from_string("alive").boxed())
+            }),
+            "Test Server",
+        );
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/panic", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 500);
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/after", port))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(
+            response.status(),
+            200,
+            "Single worker should still be alive"
+        );
+        assert_eq!(response.text().unwrap(), "alive");
+    }
+
+    #[test]
+    fn test_resolve_web_mounts() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let dir = temp_dir.path().to_string_lossy().to_string();
+        let json = format!(
+            r#"{{"/docs/": {:?}, "specs": {{"path": {:?}, "listing": false, "showHidden": false, "defaultMode": "source"}}}}"#,
+            dir, dir
+        );
+        let mounts: BTreeMap<String, WebMountConfig> =
+            serde_json::from_str(&json).expect("deserialize mounts");
+        let resolved = resolve_web_mounts(&mounts).expect("resolve mounts");
+        assert_eq!(
+            resolved,
+            vec![
+                WebMount {
+                    prefix: "/docs".to_string(),
+                    root: temp_dir.path().to_path_buf(),
+                    listing: true,
+                    show_hidden: true,
+                    default_mode: WebMountMode::Content,
+                    options: WebRootOptions::default(),
+                },
+                WebMount {
+                    prefix: "/specs".to_string(),
+                    root: temp_dir.path().to_path_buf(),
+                    listing: false,
+                    show_hidden: false,
+                    default_mode: WebMountMode::Source,
+                    options: WebRootOptions::default(),
+                },
+            ]
+        );
+
+        for bad_prefix in ["/", "/a/../b", "/a//b"] {
+            let mounts =
+                BTreeMap::from([(bad_prefix.to_string(), WebMountConfig::Path(dir.clone()))]);
+            assert!(resolve_web_mounts(&mounts).is_err(), "{}", bad_prefix);
+        }
+        let duplicate = BTreeMap::from([
+            ("/docs".to_string(), WebMountConfig::Path(dir.clone())),
+            ("/docs/".to_string(), WebMountConfig::Path(dir.clone())),
+        ]);
+        assert!(resolve_web_mounts(&duplicate).is_err());
+        let missing = BTreeMap::from([(
+            "/docs".to_string(),
+            WebMountConfig::Path(temp_dir.path().join("nope").to_string_lossy().to_string()),
+        )]);
+        assert!(resolve_web_mounts(&missing).is_err());
+    }
+
+    #[test]
+    fn test_start_web_server_serves_mounts() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let docs_dir = TempDir::new().expect("Failed to create temp dir");
+        let logs_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(root_dir.path().join("dump"), "root").expect("Failed to write root file");
+        fs::write(docs_dir.path().join("guide.txt"), "docs guide").expect("write guide");
+        fs::write(docs_dir.path().join(".env"), "SECRET=1").expect("write .env");
+        fs::create_dir(docs_dir.path().join("sub dir")).expect("create sub dir");
+        fs::write(
+            docs_dir.path().join("sub dir").join("dump"),
+            "not an endpoint",
+        )
+        .expect("write nested file");
+        fs::write(logs_dir.path().join("app.log"), "line 1\n").expect("write log");
+        fs::write(logs_dir.path().join(".hidden.log"), "hidden").expect("write hidden log");
+        fs::write(root_dir.path().join("secret.txt"), "outside").expect("write secret");
+
+        let mounts = vec![
+            WebMount {
+                prefix: "/docs".to_string(),
+                root: docs_dir.path().to_path_buf(),
+                listing: true,
+                show_hidden: true,
+                default_mode: WebMountMode::Content,
+                options: WebRootOptions::default(),
+            },
+            WebMount {
+                prefix: "/var/logs".to_string(),
+                root: logs_dir.path().to_path_buf(),
+                listing: false,
+                show_hidden: false,
+                default_mode: WebMountMode::Raw,
+                options: WebRootOptions::default(),
+            },
+        ];
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                dump: true,
+                mounts,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let get = |path: &str| {
+            client
+                .get(format!("http://127.0.0.1:{}{}", port, path))
+                .send()
+                .expect("Failed to send request")
+        };
+
+        let response = get("/docs/guide.txt?mode=raw");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "docs guide");
+
+        let listing = get("/docs/").text().unwrap();
+        assert!(listing.contains("guide.txt"));
+        assert!(listing.contains(".env"), "showHidden defaults to true");
+        let listing = get("/docs").text().unwrap();
+        assert!(listing.contains("sub%20dir"));
+
+        // Paths inside a mount are files, even when they look like a debug endpoint.
+        let response = get("/docs/sub%20dir/dump?mode=raw");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "not an endpoint");
+        assert_eq!(get("/dump").status(), 200);
+
+        // `defaultMode` applies only when the request has no `?mode=`.
+        let response = get("/var/logs/app.log");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "line 1\n");
+        let response = get("/var/logs/app.log?mode=source");
+        assert!(response.text().unwrap().contains("<!doctype html>"));
+
+        assert_eq!(get("/var/logs/").status(), 403);
+        assert_eq!(get("/var/logs/.hidden.log").status(), 404);
+        assert_eq!(get("/var/logs/%2Ehidden.log").status(), 404);
+        assert_eq!(get("/var/logsx/app.log").status(), 404);
+
+        // HTTP clients normalize `/../`, so only forms that reach the server are checked.
+        for traversal in ["/docs/..%2fsecret.txt", "/docs//secret.txt"] {
+            assert_eq!(get(traversal).status(), 400, "{}", traversal);
+        }
+    }
+
+    #[test]
+    fn test_start_web_server_serves_index_and_fallbacks() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let docs_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = root_dir.path();
+        fs::write(root.join("index.html"), "<p>app shell</p>").expect("write index");
+        fs::create_dir(root.join("guide")).expect("create guide");
+        fs::write(root.join("guide").join("index.html"), "<p>guide</p>").expect("write guide");
+        fs::write(docs_dir.path().join("404.html"), "<p>no such doc</p>").expect("write 404");
+
+        let root_options =
+            resolve_web_root_options("web", root, true, &Some("/index.html".to_string()), &None)
+                .expect("resolve root options");
+        let mount_options = resolve_web_root_options(
+            "web.mounts./docs",
+            docs_dir.path(),
+            false,
+            &None,
+            &Some("404.html".to_string()),
+        )
+        .expect("resolve mount options");
+        for bad in ["../index.html", "missing.html", "guide", ""] {
+            assert!(
+                resolve_web_root_options("web", root, false, &Some(bad.to_string()), &None)
+                    .is_err(),
+                "{}",
+                bad
+            );
+        }
+
+        let mounts = vec![WebMount {
+            prefix: "/docs".to_string(),
+            root: docs_dir.path().to_path_buf(),
+            listing: true,
+            show_hidden: true,
+            default_mode: WebMountMode::Content,
+            options: mount_options,
+        }];
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                mounts,
+                root_options,
+                ..test_listener_settings(root.to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("build client");
+        let get = |path: &str| {
+            client
+                .get(format!("http://127.0.0.1:{}{}", port, path))
+                .send()
+                .expect("Failed to send request")
+        };
+
+        let response = get("/");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "<p>app shell</p>");
+        let listing = get("/?mode=source").text().unwrap();
+        assert!(listing.contains("id=\"directory-listing\""));
+
+        let response = get("/guide?x=1");
+        assert_eq!(response.status(), 301);
+        assert_eq!(response.headers()["location"], "/guide/?x=1");
+        assert_eq!(get("/guide/").text().unwrap(), "<p>guide</p>");
+
+        // History-API routes get the app shell; `?mode=source` still reports the miss.
+        let response = get("/settings/profile");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "<p>app shell</p>");
+        assert_eq!(get("/settings/profile?mode=source").status(), 404);
+
+        let response = get("/docs/missing.md");
+        assert_eq!(response.status(), 404);
+        assert!(
+            response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        assert_eq!(response.text().unwrap(), "<p>no such doc</p>");
+        let listing = get("/docs/").text().unwrap();
+        assert!(listing.contains("404.html"), "index is off for the mount");
+    }
+
+    #[test]
+    fn test_start_web_server_applies_header_rules_and_cors() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let fixtures = root_dir.path().join("fixtures");
+        fs::create_dir(&fixtures).expect("create fixtures");
+        fs::write(fixtures.join("users.json"), "[]").expect("write users");
+        fs::write(fixtures.join("special.json"), "{}").expect("write special");
+
+        let rules: Vec<WebHeaderRuleConfig> = serde_json::from_str(
+            r#"[
+                {"path": "/fixtures/**", "headers": {"Cache-Control": "no-store", "X-Fixture": "1"}},
+                {"path": "/fixtures/special.json", "headers": {"X-Fixture": "2", "Content-Type": "application/vnd.test+json"}}
+            ]"#,
+        )
+        .expect("deserialize rules");
+        let cors: WebCorsConfig = serde_json::from_str(
+            r#"{"allowedOrigins": ["http://localhost:5173"], "allowCredentials": true, "exposeHeaders": ["X-Fixture"]}"#,
+        )
+        .expect("deserialize cors");
+        let response_headers =
+            resolve_response_header_settings(&rules, Some(&cors)).expect("resolve headers");
+        let bad_rule: Vec<WebHeaderRuleConfig> =
+            serde_json::from_str(r#"[{"path": "/**", "headers": {"Content-Length": "1"}}]"#)
+                .expect("deserialize bad rule");
+        assert!(resolve_response_header_settings(&bad_rule, None).is_err());
+
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                response_headers,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let header = |response: &reqwest::blocking::Response, name: &str| {
+            response
+                .headers()
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let response = client
+            .get(url("/fixtures/users.json?mode=raw"))
+            .header("Origin", "http://localhost:5173")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert_eq!(header(&response, "cache-control"), ["no-store"]);
+        assert_eq!(header(&response, "x-fixture"), ["1"]);
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            ["http://localhost:5173"]
+        );
+        assert_eq!(
+            header(&response, "access-control-allow-credentials"),
+            ["true"]
+        );
+        assert_eq!(
+            header(&response, "access-control-expose-headers"),
+            ["X-Fixture"]
+        );
+        assert!(header(&response, "vary").iter().any(|v| v == "Origin"));
+        assert_eq!(response.text().unwrap(), "[]");
+
+        // A later rule replaces headers from earlier rules and from the handler.
+        let response = client
+            .get(url("/fixtures/special.json"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(header(&response, "x-fixture"), ["2"]);
+        assert_eq!(
+            header(&response, "content-type"),
+            ["application/vnd.test+json"]
+        );
+        assert!(header(&response, "access-control-allow-origin").is_empty());
+
+        let response = client
+            .get(url("/fixtures/users.json"))
+            .header("Origin", "http://localhost:3000")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert!(header(&response, "access-control-allow-origin").is_empty());
+
+        let preflight = |origin: &str| {
+            client
+                .request(reqwest::Method::OPTIONS, url("/fixtures/users.json"))
+                .header("Origin", origin)
+                .header("Access-Control-Request-Method", "PUT")
+                .header("Access-Control-Request-Headers", "x-custom, content-type")
+                .send()
+                .expect("Failed to send request")
+        };
+        let response = preflight("http://localhost:5173");
+        assert_eq!(response.status(), 204);
+        assert_eq!(
+            header(&response, "access-control-allow-methods"),
+            ["GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS"]
+        );
+        assert_eq!(
+            header(&response, "access-control-allow-headers"),
+            ["x-custom, content-type"]
+        );
+        assert_eq!(header(&response, "access-control-max-age"), ["600"]);
+        let response = preflight("http://localhost:3000");
+        assert_ne!(response.status(), 204);
+        assert!(header(&response, "access-control-allow-origin").is_empty());
+    }
+
+    #[test]
+    fn test_start_web_server_records_access_log() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let log_dir = TempDir::new().expect("Failed to create temp dir");
+        let log_path = log_dir.path().join("access.log");
+        configure_access_log(Some(AccessLogSettings {
+            path: log_path.clone(),
+            max_bytes: df_access_log_max_bytes(),
+            max_files: df_access_log_max_files(),
+        }));
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                status: true,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let response = client
+            .get(url("/status/418?access-log-test"))
+            .header("User-Agent", "access-log-test/1.0")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 418);
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let feed: serde_json::Value = client
+            .get(url(
+                "/_mclocks/access-log/entries?status=4xx&path=access-log-test",
+            ))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("Failed to parse feed");
+        let entries = feed["entries"].as_array().expect("entries");
+        assert_eq!(entries.len(), 1, "{}", feed);
+        assert_eq!(entries[0]["listener"], "Main Server");
+        assert_eq!(entries[0]["method"], "GET");
+        assert_eq!(entries[0]["path"], "/status/418?access-log-test");
+        assert_eq!(entries[0]["status"], 418);
+        assert_eq!(entries[0]["userAgent"], "access-log-test/1.0");
+        let next = feed["next"].as_u64().expect("next");
+        let feed: serde_json::Value = client
+            .get(url(&format!(
+                "/_mclocks/access-log/entries?since={}&path=access-log-test",
+                next
+            )))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("Failed to parse feed");
+        assert_eq!(
+            feed["entries"],
+            serde_json::json!([]),
+            "viewer polls are not logged"
+        );
+
+        let response = client
+            .get(url(
+                "/status/200?token-log-test&mclocks_token=0123456789abcdef",
+            ))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        thread::sleep(std::time::Duration::from_millis(100));
+        let feed: serde_json::Value = client
+            .get(url("/_mclocks/access-log/entries?path=token-log-test"))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("Failed to parse feed");
+        assert_eq!(feed["entries"][0]["path"], "/status/200?token-log-test");
+
+        let viewer = client
+            .get(url("/_mclocks/access-log"))
+            .send()
+            .expect("Failed to send request");
+        assert!(
+            viewer.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        assert!(viewer.text().unwrap().contains("Access Log"));
+
+        let logged = fs::read_to_string(&log_path).expect("read access log");
+        configure_access_log(None);
+        let line = logged
+            .lines()
+            .find(|line| line.contains("access-log-test"))
+            .expect("logged line");
+        let entry: AccessLogEntry = serde_json::from_str(line).expect("parse line");
+        assert_eq!(entry.status, 418);
+        assert!(logged.contains("token-log-test"));
+        assert!(
+            !logged.contains("0123456789abcdef"),
+            "the token is not logged"
+        );
+    }
+
+    #[test]
+    fn test_start_web_server_guards_host_and_origin() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(root_dir.path().join("a.txt"), "a").expect("write file");
+        let security: WebSecurityConfig = serde_json::from_str(
+            r#"{"allowedHosts": ["mclocks.test"], "token": "0123456789abcdef"}"#,
+        )
+        .expect("deserialize security");
+        let access_guard = resolve_access_guard_settings(Some(&security)).expect("resolve");
+        let short: WebSecurityConfig =
+            serde_json::from_str(r#"{"token": "short"}"#).expect("deserialize security");
+        assert!(resolve_access_guard_settings(Some(&short)).is_err());
+
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                dump: true,
+                access_guard,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let get_with_host = |host: &str| {
+            client
+                .get(url("/a.txt?mode=raw"))
+                .header("Host", host)
+                .send()
+                .expect("Failed to send request")
+                .status()
+        };
+        assert_eq!(get_with_host(&format!("localhost:{}", port)), 200);
+        assert_eq!(get_with_host(&format!("mclocks.test:{}", port)), 200);
+        assert_eq!(get_with_host(&format!("rebind.example:{}", port)), 403);
+
+        let post_from = |origin: &str| {
+            client
+                .post(url("/dump"))
+                .header("Origin", origin)
+                .body("{}")
+                .send()
+                .expect("Failed to send request")
+                .status()
+        };
+        // Loopback clients need no token; only foreign origins are refused.
+        assert_eq!(post_from("http://localhost:5173"), 200);
+        assert_eq!(post_from("http://mclocks.test"), 200);
+        assert_eq!(post_from("https://evil.example"), 403);
+    }
+
+    #[test]
+    fn test_start_tls_web_server_serves_with_dev_ca() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let ca_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(root_dir.path().join("a.txt"), "a").expect("write file");
+        let certificate = ensure_dev_certificate(ca_dir.path(), &server_cert_hosts(&[]))
+            .expect("Failed to create certificate");
+
+        let tls_port = find_available_port();
+        start_tls_web_server(
+            test_listener_settings(root_dir.path().to_path_buf()),
+            tls_port,
+            1,
+            &certificate,
+        )
+        .expect("Failed to start TLS web server");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                dev_ca_pem: Some(certificate.ca_cert_pem.clone()),
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let ca = reqwest::blocking::get(format!("http://127.0.0.1:{}{}", port, DEV_CA_PATH))
+            .expect("Failed to send request");
+        assert_eq!(ca.status(), 200);
+        let ca_pem = ca.text().expect("read body");
+        assert_eq!(ca_pem, certificate.ca_cert_pem);
+
+        let client = reqwest::blocking::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(ca_pem.as_bytes()).unwrap())
+            .build()
+            .expect("Failed to build client");
+        let response = client
+            .get(format!("https://localhost:{}/a.txt?mode=raw", tls_port))
+            .send()
+            .expect("Failed to send TLS request");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "a");
+    }
+
+    #[test]
+    fn test_start_web_server_proxies_and_captures_exchanges() {
+        let upstream = Server::http("127.0.0.1:0").expect("Failed to start upstream");
+        let upstream_port = upstream.server_addr().to_ip().expect("ip addr").port();
+        thread::spawn(move || {
+            for mut request in upstream.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let forwarded_host = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("X-Forwarded-Host"))
+                    .map(|h| h.value.to_string())
+                    .unwrap_or_default();
+                let echo = format!(
+                    "{} {} {} {}",
+                    request.method(),
+                    request.url(),
+                    forwarded_host,
+                    body
+                );
+                let response = tiny_http::Response::from_string(echo).with_header(
+                    tiny_http::Header::from_bytes(&b"X-Upstream"[..], &b"yes"[..]).unwrap(),
+                );
+                let _ = request.respond(response);
+            }
+        });
+
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let proxy: Vec<WebProxyConfig> = serde_json::from_value(serde_json::json!([
+            { "path": "/api", "upstream": format!("http://127.0.0.1:{}", upstream_port) },
+            { "path": "/v2/", "upstream": format!("http://127.0.0.1:{}/next", upstream_port), "stripPrefix": true },
+            { "path": "/down", "upstream": format!("http://127.0.0.1:{}", upstream_port), "status": 503, "delayMs": 50 },
+        ]))
+        .expect("deserialize proxy");
+        let proxy_routes = resolve_proxy_routes(&proxy).expect("resolve proxy");
+        let too_slow: Vec<WebProxyConfig> = serde_json::from_value(serde_json::json!([
+            { "path": "/api", "upstream": "http://127.0.0.1:1", "delayMs": 10_000_000 },
+        ]))
+        .expect("deserialize proxy");
+        assert!(resolve_proxy_routes(&too_slow).is_err());
+
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                proxy_routes,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let client = reqwest::blocking::Client::new();
+        let (_, next) = crate::web::proxy::recent_exchanges(0);
+
+        let response = client
+            .post(url("/api/users?id=1"))
+            .body("hello")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-upstream"], "yes");
+        assert_eq!(
+            response.text().unwrap(),
+            format!("POST /api/users?id=1 127.0.0.1:{} hello", port)
+        );
+
+        let stripped = client
+            .get(url("/v2/items"))
+            .send()
+            .expect("Failed to send request");
+        assert!(stripped.text().unwrap().starts_with("GET /next/items "));
+
+        let overridden = client
+            .get(url("/down/health"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(overridden.status(), 503);
+        assert_eq!(overridden.headers()["retry-after"], "60");
+
+        let captured: serde_json::Value = client
+            .get(url(&format!("/_mclocks/proxy/exchanges?since={}", next)))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("parse exchanges");
+        let exchanges = captured["exchanges"].as_array().expect("exchanges");
+        let posted = exchanges
+            .iter()
+            .find(|e| e["path"] == "/api/users?id=1")
+            .expect("captured POST");
+        assert_eq!(posted["requestBody"]["text"], "hello");
+        assert_eq!(posted["status"], 200);
+        assert_eq!(
+            posted["upstreamUrl"],
+            format!("http://127.0.0.1:{}/api/users?id=1", upstream_port)
+        );
+        let down = exchanges
+            .iter()
+            .find(|e| e["path"] == "/down/health")
+            .expect("captured override");
+        assert_eq!(down["status"], 503);
+        assert!(down["upstreamUrl"].is_null());
+        assert!(down["durationMs"].as_u64().unwrap() >= 50);
+    }
+
+    #[test]
+    fn test_start_web_server_replays_recorded_exchanges() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let recordings_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(root_dir.path().join("a.txt"), "a").expect("write file");
+        let har = serde_json::json!({
+            "log": { "version": "1.2", "entries": [{
+                "request": {
+                    "method": "GET",
+                    "url": "https://api.example.com/api/users?page=2&_=111",
+                    "headers": [{ "name": "Accept", "value": "application/json" }],
+                },
+                "response": {
+                    "status": 200,
+                    "headers": [{ "name": "Content-Length", "value": "999" }],
+                    "content": { "mimeType": "application/json", "text": "eyJ1c2VycyI6W119", "encoding": "base64" },
+                },
+            }]}
+        });
+        fs::write(recordings_dir.path().join("a.har"), har.to_string()).expect("write har");
+        let recording = serde_json::json!({
+            "request": { "method": "POST", "path": "/api/login", "headers": [] },
+            "response": { "status": 201, "headers": [{ "X-Recorded": "yes" }], "body": "welcome" },
+        });
+        fs::write(recordings_dir.path().join("b.json"), recording.to_string())
+            .expect("write recording");
+
+        let mock_config = |unmatched: &str| -> WebMockConfig {
+            serde_json::from_value(serde_json::json!({
+                "source": recordings_dir.path().to_string_lossy(),
+                "path": "/api",
+                "unmatched": unmatched,
+                "ignoreQuery": ["_"],
+                "matchHeaders": ["Accept"],
+            }))
+            .expect("deserialize mock")
+        };
+        let mock = resolve_mock_settings(Some(&mock_config("notFound"))).expect("resolve mock");
+        assert_eq!(mock.as_ref().map(|m| m.exchanges.len()), Some(2));
+
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                mock,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let client = reqwest::blocking::Client::new();
+        let users = client
+            .get(url("/api/users?_=222&page=2"))
+            .header("Accept", "application/json")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(users.status(), 200);
+        assert_eq!(users.headers()["content-type"], "application/json");
+        assert_eq!(users.text().unwrap(), r#"{"users":[]}"#);
+
+        let other_accept = client
+            .get(url("/api/users?page=2"))
+            .header("Accept", "text/html")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(other_accept.status(), 404);
+
+        let login = client
+            .post(url("/api/login"))
+            .body("{}")
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(login.status(), 201);
+        assert_eq!(login.headers()["x-recorded"], "yes");
+        assert_eq!(login.text().unwrap(), "welcome");
+
+        let outside = client
+            .get(url("/a.txt?mode=raw"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(outside.status(), 200);
+
+        let passthrough = resolve_mock_settings(Some(&mock_config("passthrough")))
+            .expect("resolve mock")
+            .expect("mock settings");
+        assert_eq!(passthrough.unmatched, MockUnmatched::Passthrough);
+        let missing: WebMockConfig =
+            serde_json::from_value(serde_json::json!({ "source": "/no/such/recordings" }))
+                .expect("deserialize mock");
+        assert!(resolve_mock_settings(Some(&missing)).is_err());
+    }
+
+    #[test]
+    fn test_start_web_server_answers_declared_routes() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::create_dir(root_dir.path().join("mocks")).expect("create dir");
+        fs::write(
+            root_dir.path().join("mocks").join("user.json"),
+            r#"{"id": {{id}}, "lang": "{{query.lang}}"}"#,
+        )
+        .expect("write body file");
+        let routes: Vec<WebRouteConfig> = serde_json::from_value(serde_json::json!([
+            {
+                "path": "/api/users/:id",
+                "headers": { "X-User": "{{id}}" },
+                "bodyFile": "mocks/user.json",
+                "delayMs": 50,
+            },
+            { "method": "POST", "path": "/api/users", "status": 201, "body": "created" },
+            { "method": "*", "path": "/api/teapot/*", "status": 418, "body": "{{*}}" },
+        ]))
+        .expect("deserialize routes");
+        let resolved = resolve_web_routes(&routes, root_dir.path()).expect("resolve routes");
+        let missing: Vec<WebRouteConfig> = serde_json::from_value(serde_json::json!([
+            { "path": "/x", "bodyFile": "mocks/none.json" },
+        ]))
+        .expect("deserialize routes");
+        assert!(resolve_web_routes(&missing, root_dir.path()).is_err());
+
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                routes: resolved,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let client = reqwest::blocking::Client::new();
+        let started = std::time::Instant::now();
+        let user = client
+            .get(url("/api/users/42?lang=ja"))
+            .send()
+            .expect("Failed to send request");
+        assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+        assert_eq!(user.status(), 200);
+        assert_eq!(user.headers()["x-user"], "42");
+        assert_eq!(
+            user.headers()["content-type"],
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(user.text().unwrap(), r#"{"id": 42, "lang": "ja"}"#);
+
+        let injected = client
+            .get(url("/api/users/1%0d%0aSet-Cookie:x=1"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(injected.status(), 400);
+        assert!(injected.headers().get("set-cookie").is_none());
+
+        let created = client
+            .post(url("/api/users"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(created.status(), 201);
+        assert_eq!(created.text().unwrap(), "created");
+
+        let teapot = client
+            .delete(url("/api/teapot/a/b"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(teapot.status(), 418);
+        assert_eq!(teapot.text().unwrap(), "a/b");
+
+        let unrouted = client
+            .get(url("/api/users"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(unrouted.status(), 404);
+    }
+
+    #[test]
+    fn test_start_web_server_stores_requests_in_bins() {
+        let bin: WebBinConfig = serde_json::from_value(serde_json::json!({ "maxRequests": 2 }))
+            .expect("deserialize bin");
+        configure_request_bin(resolve_request_bin_settings(Some(&bin)).expect("resolve bin"));
+        let too_many: WebBinConfig =
+            serde_json::from_value(serde_json::json!({ "maxRequests": 0 }))
+                .expect("deserialize bin");
+        assert!(resolve_request_bin_settings(Some(&too_many)).is_err());
+
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            test_listener_settings(root_dir.path().to_path_buf()),
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let client = reqwest::blocking::Client::new();
+        for n in 0..3 {
+            let stored = client
+                .post(url("/bin/hooks/github?n=1"))
+                .header("X-Event", format!("push-{}", n))
+                .body(format!(r#"{{"n":{}}}"#, n))
+                .send()
+                .expect("Failed to send request");
+            assert_eq!(stored.status(), 200);
+            let stored: serde_json::Value = stored.json().expect("json");
+            assert_eq!(stored["bin"], "hooks");
+        }
+        let invalid = client
+            .get(url("/bin/a.b"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(invalid.status(), 400);
+
+        let history: serde_json::Value = client
+            .get(url("/_mclocks/bin/hooks/requests"))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("json");
+        let requests = history["requests"].as_array().expect("requests");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["method"], "POST");
+        assert_eq!(requests[1]["path"], "/github?n=1");
+        assert_eq!(requests[1]["body"], r#"{"n":2}"#);
+        assert!(
+            requests[1]["headers"]
+                .as_array()
+                .expect("headers")
+                .iter()
+                .any(|h| h[0] == "x-event" && h[1] == "push-2")
+        );
+
+        let page = client
+            .get(url("/_mclocks/bin/hooks"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(page.status(), 200);
+        assert!(page.text().unwrap().contains("Request Bin: hooks"));
+        let cleared = client
+            .delete(url("/_mclocks/bin/hooks/requests"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(cleared.status(), 200);
+        let history: serde_json::Value = client
+            .get(url("/_mclocks/bin/hooks/requests"))
+            .send()
+            .expect("Failed to send request")
+            .json()
+            .expect("json");
+        assert_eq!(history["requests"].as_array().map(Vec::len), Some(0));
+
+        configure_request_bin(None);
+        let disabled = client
+            .post(url("/bin/hooks"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(disabled.status(), 404);
+    }
+
+    #[test]
+    fn test_start_web_server_drips_slow_body() {
+        use std::io::Read;
+
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                slow: true,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            1,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let started = std::time::Instant::now();
+        let mut response = reqwest::blocking::get(url("/slow/drip?bytes=300&chunk=100&duration=2"))
+            .expect("Failed to send request");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["transfer-encoding"], "chunked");
+        let mut first = [0u8; 100];
+        response.read_exact(&mut first).expect("read first chunk");
+        let first_chunk_at = started.elapsed();
+        let mut rest = Vec::new();
+        response.read_to_end(&mut rest).expect("read rest");
+        assert!(first_chunk_at < std::time::Duration::from_millis(1500));
+        assert!(started.elapsed() >= std::time::Duration::from_secs(2));
+        assert_eq!(rest.len(), 200);
+        assert!(first.starts_with(b"0123456789abc"));
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(2))
+            .build()
+            .expect("build client");
+        let mut stalled = client
+            .get(url("/slow/drip?bytes=300&chunk=100&duration=0&stall=100"))
+            .send()
+            .expect("Failed to send request");
+        let mut body = Vec::new();
+        assert!(stalled.read_to_end(&mut body).is_err());
+        assert_eq!(body.len(), 100);
+
+        // The stalled connection is still held, but the only worker is free again.
+        let invalid = client.get(url("/slow/drip?chunk=0")).send().expect("send");
+        assert_eq!(invalid.status(), 400);
+    }
+
+    #[test]
+    fn test_start_web_server_sends_faults() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                fault: true,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            2,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        let fault_port = find_available_port();
+        configure_fault_listener(Some(fault_port), "127.0.0.1").expect("start fault listener");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let no_redirect = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(std::time::Duration::from_secs(5))
+            .build()
+            .expect("build client");
+        let redirect = no_redirect
+            .get(url("/fault/reset"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(redirect.status(), 307);
+        assert_eq!(
+            redirect.headers()["location"],
+            format!("http://127.0.0.1:{}/fault/reset", fault_port).as_str()
+        );
+
+        // `close` and `reset` end the connection when the fault port is hit directly,
+        // without following any redirect.
+        for path in ["/fault/close", "/fault/reset"] {
+            let result = no_redirect
+                .get(format!("http://127.0.0.1:{}{}", fault_port, path))
+                .send()
+                .and_then(|r| r.bytes());
+            assert!(result.is_err(), "{} should fail on the fault port", path);
+        }
+
+        // In-band faults are answered on the request's own connection.
+        let long = no_redirect
+            .get(url("/fault/long-headers?bytes=100"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(long.status(), 200);
+        assert_eq!(long.headers()["x-long"].len(), 100);
+        assert_eq!(long.text().unwrap(), "OK");
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .build()
+            .expect("build client");
+        let started = std::time::Instant::now();
+        for path in [
+            "/fault/close",
+            "/fault/reset",
+            "/fault/short-body",
+            "/fault/bad-chunked",
+            "/fault/bad-status",
+        ] {
+            let result = client
+                .get(url(path))
+                .header("Connection", "close")
+                .send()
+                .and_then(|r| r.bytes());
+            assert!(result.is_err(), "{} should fail", path);
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(4));
+
+        let unknown = client
+            .get(url("/fault/unknown"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(unknown.status(), 404);
+
+        // Disabling faults stops the fault listener and releases its port.
+        configure_fault_listener(None, "127.0.0.1").expect("stop fault listener");
+        assert!(std::net::TcpStream::connect(("127.0.0.1", fault_port)).is_err());
+        let unavailable = client
+            .get(url("/fault/close"))
+            .send()
+            .expect("Failed to send request");
+        assert_eq!(unavailable.status(), 503);
+    }
+
+    #[test]
+    fn test_start_web_server_serves_httpbin_endpoints() {
+        use std::io::Read;
+
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                httpbin: true,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            2,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+        let json = |path: &str| -> serde_json::Value {
+            client
+                .get(url(path))
+                .header("X-Test", "a")
+                .header("User-Agent", "mclocks-test")
+                .send()
+                .expect("Failed to send request")
+                .json()
+                .expect("json body")
+        };
+
+        assert_eq!(json("/headers")["headers"]["x-test"], "a");
+        assert_eq!(json("/ip")["origin"], "127.0.0.1");
+        assert_eq!(json("/user-agent")["user-agent"], "mclocks-test");
+        let uuid = json("/uuid")["uuid"].as_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&uuid).is_ok());
+
+        let bytes = |path: &str| client.get(url(path)).send().unwrap().bytes().unwrap();
+        assert_eq!(bytes("/bytes/1000").len(), 1000);
+        assert_eq!(bytes("/bytes/64?seed=7"), bytes("/bytes/64?seed=7"));
+        assert_eq!(client.get(url("/bytes/abc")).send().unwrap().status(), 400);
+
+        let stream = client.get(url("/stream/3")).send().unwrap().text().unwrap();
+        let lines: Vec<serde_json::Value> = stream
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2]["id"], 2);
+
+        assert_eq!(
+            client
+                .get(url("/base64/aGVsbG8gbWNsb2Nrcw=="))
+                .send()
+                .unwrap()
+                .text()
+                .unwrap(),
+            "hello mclocks"
+        );
+
+        let gzip = client.get(url("/gzip")).send().unwrap();
+        assert_eq!(gzip.headers()["content-encoding"], "gzip");
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(&gzip.bytes().unwrap()[..])
+            .read_to_string(&mut body)
+            .expect("gunzip");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()["gzipped"],
+            true
+        );
+
+        let deflate = client.get(url("/deflate")).send().unwrap();
+        assert_eq!(deflate.headers()["content-encoding"], "deflate");
+        let mut body = String::new();
+        flate2::read::ZlibDecoder::new(&deflate.bytes().unwrap()[..])
+            .read_to_string(&mut body)
+            .expect("inflate");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()["deflated"],
+            true
+        );
+    }
+
+    #[test]
+    fn test_start_web_server_challenges_auth_endpoints() {
+        use md5::{Digest, Md5};
+
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                auth: Some(AuthEndpointSettings {
+                    bearer_token: Some("secret".to_string()),
+                }),
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            2,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+
+        let denied = client.get(url("/basic-auth/alice/pw")).send().unwrap();
+        assert_eq!(denied.status(), 401);
+        assert_eq!(
+            denied.headers()["www-authenticate"],
+            "Basic realm=\"mclocks\""
+        );
+        let wrong = client
+            .get(url("/basic-auth/alice/pw"))
+            .basic_auth("alice", Some("nope"))
+            .send()
+            .unwrap();
+        assert_eq!(wrong.status(), 401);
+        let ok: serde_json::Value = client
+            .get(url("/basic-auth/alice/pw"))
+            .basic_auth("alice", Some("pw"))
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(ok["user"], "alice");
+
+        let missing = client.get(url("/bearer")).send().unwrap();
+        assert_eq!(missing.status(), 401);
+        assert_eq!(
+            missing.headers()["www-authenticate"],
+            "Bearer realm=\"mclocks\""
+        );
+        let invalid = client
+            .get(url("/bearer"))
+            .bearer_auth("other")
+            .send()
+            .unwrap();
+        assert_eq!(invalid.status(), 401);
+        assert!(
+            invalid.headers()["www-authenticate"]
+                .to_str()
+                .unwrap()
+                .contains("error=\"invalid_token\"")
+        );
+        let ok: serde_json::Value = client
+            .get(url("/bearer"))
+            .bearer_auth("secret")
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(ok["token"], "secret");
+
+        let path = "/digest-auth/auth/bob/pw";
+        let challenge = client.get(url(path)).send().unwrap();
+        assert_eq!(challenge.status(), 401);
+        let header = challenge.headers()["www-authenticate"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(header.starts_with("Digest realm=\"mclocks\", qop=\"auth\""));
+        let nonce = header
+            .split("nonce=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap()
+            .to_string();
+        let hex = |data: String| -> String {
+            Md5::digest(data.as_bytes())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        };
+        let digest_header = |pass: &str| {
+            let ha1 = hex(format!("bob:mclocks:{}", pass));
+            let ha2 = hex(format!("GET:{}", path));
+            let response = hex(format!("{}:{}:00000001:abcdef:auth:{}", ha1, nonce, ha2));
+            format!(
+                "Digest username=\"bob\", realm=\"mclocks\", nonce=\"{}\", uri=\"{}\", qop=auth, nc=00000001, cnonce=\"abcdef\", response=\"{}\"",
+                nonce, path, response
+            )
+        };
+        let ok = client
+            .get(url(path))
+            .header("Authorization", digest_header("pw"))
+            .send()
+            .unwrap();
+        assert_eq!(ok.status(), 200);
+        assert_eq!(ok.json::<serde_json::Value>().unwrap()["user"], "bob");
+        let wrong = client
+            .get(url(path))
+            .header("Authorization", digest_header("nope"))
+            .send()
+            .unwrap();
+        assert_eq!(wrong.status(), 401);
+        assert_eq!(
+            client
+                .get(url("/digest-auth/bogus/bob/pw"))
+                .send()
+                .unwrap()
+                .status(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_start_web_server_serves_cookie_and_cache_endpoints() {
+        let root_dir = TempDir::new().expect("Failed to create temp dir");
+        let port = find_available_port();
+        start_web_server(
+            WebListenerSettings {
+                cookies_and_caching: true,
+                ..test_listener_settings(root_dir.path().to_path_buf())
+            },
+            port,
+            2,
+            WebServerListenKind::Main,
+        )
+        .expect("Failed to start web server");
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to create client");
+        let url = |path: &str| format!("http://127.0.0.1:{}{}", port, path);
+
+        let echoed: serde_json::Value = client
+            .get(url("/cookies"))
+            .header("Cookie", "a=1; session=xyz")
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(echoed["cookies"]["a"], "1");
+        assert_eq!(echoed["cookies"]["session"], "xyz");
+
+        let set = client.get(url("/cookies/set?a=1&b=two")).send().unwrap();
+        assert_eq!(set.status(), 302);
+        assert_eq!(set.headers()["location"], "/cookies");
+        let set_cookies: Vec<&str> = set
+            .headers()
+            .get_all("set-cookie")
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        assert_eq!(set_cookies, vec!["a=1; Path=/", "b=two; Path=/"]);
+
+        let deleted = client.get(url("/cookies/delete?a")).send().unwrap();
+        assert_eq!(deleted.status(), 302);
+        assert!(
+            deleted.headers()["set-cookie"]
+                .to_str()
+                .unwrap()
+                .starts_with("a=; Path=/; Max-Age=0")
+        );
+        assert_eq!(
+            client
+                .get(url("/cookies/set?bad%20name=1"))
+                .send()
+                .unwrap()
+                .status(),
+            400
+        );
+
+        let cached = client.get(url("/cache/60")).send().unwrap();
+        assert_eq!(cached.status(), 200);
+        assert_eq!(cached.headers()["cache-control"], "public, max-age=60");
+        let etag = cached.headers()["etag"].to_str().unwrap().to_string();
+        let last_modified = cached.headers()["last-modified"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let revalidated = client
+            .get(url("/cache/60"))
+            .header("If-None-Match", &etag)
+            .send()
+            .unwrap();
+        assert_eq!(revalidated.status(), 304);
+        assert_eq!(revalidated.headers()["etag"], etag.as_str());
+        let by_date = client
+            .get(url("/cache/60"))
+            .header("If-Modified-Since", &last_modified)
+            .send()
+            .unwrap();
+        assert_eq!(by_date.status(), 304);
+
+        let tagged = client.get(url("/etag/v1")).send().unwrap();
+        assert_eq!(tagged.status(), 200);
+        assert_eq!(tagged.headers()["etag"], "\"v1\"");
+        let matching = client
+            .get(url("/etag/v1"))
+            .header("If-None-Match", "\"v0\", W/\"v1\"")
+            .send()
+            .unwrap();
+        assert_eq!(matching.status(), 304);
+        let stale = client
+            .get(url("/etag/v2"))
+            .header("If-None-Match", "\"v1\"")
+            .send()
+            .unwrap();
+        assert_eq!(stale.status(), 200);
+    }
 }
@@ -1,5 +1,6 @@
 import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow } from '@tauri-apps/api/window';
+import { listen } from '@tauri-apps/api/event';
 
 import { initClocks, refreshClocks, adjustWindowSize, startClocks } from './clock_matter.js';
 import { ClockCtx } from './clock_ctx.js';
@@ -337,8 +338,14 @@ const clockMain = async (clockCtx) => {
 
     const { cleanup } = initKeyboardHandlers(clockCtx, cfg, clocks);
 
+    // config.json was edited; rebuild the clocks from the new settings
+    const unlistenConfigChanged = await listen('config-changed', () => {
+      window.location.reload();
+    });
+
     window.addEventListener('beforeunload', () => {
       cleanup();
+      unlistenConfigChanged();
     });
   } catch (error) {
     console.error('Err:', error);